@@ -186,6 +186,7 @@ pub trait BoolIndexRead {
             points_count: self.indexed_count(),
             points_values_count: self.trues_count() + self.falses_count(),
             histogram_bucket_size: None,
+            cardinality_estimation_error: None,
             index_type: self.telemetry_index_type(),
         }
     }
@@ -3,11 +3,14 @@ mod field_index;
 mod field_index_read;
 mod field_index_read_impl;
 mod payload_field_index;
+mod query_io_stats;
 mod read_only;
 mod value_indexer;
 
 pub use builder::{FieldIndexBuilder, FieldIndexBuilderTrait};
 pub use field_index::FieldIndex;
 pub use field_index_read::FieldIndexRead;
+pub(crate) use payload_field_index::unsupported_condition_reason;
 pub use payload_field_index::{PayloadFieldIndex, PayloadFieldIndexRead};
+pub use query_io_stats::{QueryIoStats, QueryIoTotals};
 pub use value_indexer::ValueIndexer;
@@ -5,11 +5,17 @@ use common::counter::hardware_counter::HardwareCounterCell;
 use common::types::PointOffsetType;
 use serde_json::Value;
 
+use super::query_io_stats::{IoStatsIter, QueryIoStats};
 use crate::common::Flusher;
 use crate::common::operation_error::OperationResult;
-use crate::index::field_index::{CardinalityEstimation, PayloadBlockCondition};
+use crate::index::field_index::{
+    CardinalityEstimation, ConditionServeability, PayloadBlockCondition,
+};
 use crate::index::query_optimization::optimized_filter::ConditionCheckerFn;
-use crate::types::{FieldCondition, PayloadKeyType};
+use crate::types::{
+    AnyVariants, FieldCondition, Match, MatchAny, MatchValue, PayloadKeyType, RangeInterface,
+    ValueVariants,
+};
 
 /// Read-only operations available on every payload field index.
 ///
@@ -31,6 +37,25 @@ pub trait PayloadFieldIndexRead {
         hw_counter: &'a HardwareCounterCell,
     ) -> OperationResult<Option<Box<dyn Iterator<Item = PointOffsetType> + 'a>>>;
 
+    /// [`Self::filter`], also returning a handle that reports the bytes read and the points
+    /// yielded by this query once the iterator is fully consumed.
+    ///
+    /// Bytes are measured on the payload index read counter of `hw_counter`, so other reads
+    /// into the same counter while the iterator is alive are attributed to this query too.
+    fn filter_with_io_stats<'a>(
+        &'a self,
+        condition: &'a FieldCondition,
+        hw_counter: &'a HardwareCounterCell,
+    ) -> OperationResult<Option<(Box<dyn Iterator<Item = PointOffsetType> + 'a>, QueryIoStats)>>
+    {
+        let bytes_read_before = hw_counter.payload_index_io_read_counter().get();
+        let Some(iter) = self.filter(condition, hw_counter)? else {
+            return Ok(None);
+        };
+        let (iter, stats) = IoStatsIter::new(iter, hw_counter, bytes_read_before);
+        Ok(Some((Box::new(iter), stats)))
+    }
+
     /// Return estimation of amount of points which satisfy given condition.
     /// Returns `Ok(None)` if the condition does not match the index type
     fn estimate_cardinality(
@@ -39,6 +64,29 @@ pub trait PayloadFieldIndexRead {
         hw_counter: &HardwareCounterCell,
     ) -> OperationResult<Option<CardinalityEstimation>>;
 
+    /// Dry run of [`Self::estimate_cardinality`] for diagnostics: tells whether
+    /// this index serves the condition, and why not if it falls back to a full
+    /// scan.
+    fn explain_condition(
+        &self,
+        condition: &FieldCondition,
+        hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<ConditionServeability> {
+        let serveability = match self.estimate_cardinality(condition, hw_counter)? {
+            Some(estimation) => ConditionServeability::Served(estimation),
+            None => ConditionServeability::NotServed {
+                reason: self.not_served_reason(condition),
+            },
+        };
+        Ok(serveability)
+    }
+
+    /// Why [`Self::explain_condition`] reports `condition` as not served. Indexes override
+    /// this to point at the offending part of conditions they serve in general.
+    fn not_served_reason(&self, condition: &FieldCondition) -> String {
+        unsupported_condition_reason(condition)
+    }
+
     /// Iterate conditions for payload blocks with minimum size of `threshold`
     /// Required for building HNSW index
     fn for_each_payload_block(
@@ -89,3 +137,69 @@ pub trait PayloadFieldIndex: PayloadFieldIndexRead {
 
     fn immutable_files(&self) -> Vec<PathBuf>;
 }
+
+/// Default [`PayloadFieldIndexRead::not_served_reason`]: the kind of condition is unsupported.
+pub(crate) fn unsupported_condition_reason(condition: &FieldCondition) -> String {
+    format!(
+        "{} is not supported by this index type",
+        describe_condition(condition),
+    )
+}
+
+/// Human readable kind of a field condition, used in [`PayloadFieldIndexRead::explain_condition`].
+fn describe_condition(condition: &FieldCondition) -> &'static str {
+    let FieldCondition {
+        key: _,
+        r#match,
+        range,
+        geo_bounding_box,
+        geo_radius,
+        geo_polygon,
+        values_count,
+        is_empty,
+        is_null,
+    } = condition;
+
+    if let Some(r#match) = r#match {
+        return match r#match {
+            Match::Value(MatchValue { value }) => match value {
+                ValueVariants::String(_) => "keyword match",
+                ValueVariants::Integer(_) => "integer match",
+                ValueVariants::Bool(_) => "bool match",
+            },
+            Match::Text(_) => "full-text match",
+            Match::TextAny(_) => "full-text any match",
+            Match::Phrase(_) => "phrase match",
+            Match::Any(MatchAny { any }) => match any {
+                AnyVariants::Strings(_) => "keyword match any",
+                AnyVariants::Integers(_) => "integer match any",
+            },
+            Match::Except(_) => "match except",
+        };
+    }
+    if let Some(range) = range {
+        return match range {
+            RangeInterface::Float(_) => "range",
+            RangeInterface::DateTime(_) => "datetime range",
+        };
+    }
+    if geo_bounding_box.is_some() {
+        return "geo bounding box";
+    }
+    if geo_radius.is_some() {
+        return "geo radius";
+    }
+    if geo_polygon.is_some() {
+        return "geo polygon";
+    }
+    if values_count.is_some() {
+        return "values count";
+    }
+    if is_empty.is_some() {
+        return "is empty";
+    }
+    if is_null.is_some() {
+        return "is null";
+    }
+    "empty condition"
+}
@@ -0,0 +1,83 @@
+use std::sync::{Arc, OnceLock};
+
+use common::counter::hardware_counter::HardwareCounterCell;
+
+/// I/O of a single query, see [`QueryIoStats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryIoTotals {
+    /// Bytes charged to the payload index read counter, from the start of the query until its
+    /// iterator was exhausted
+    pub bytes_read: usize,
+    /// Number of entries the iterator yielded
+    pub entries: usize,
+}
+
+/// Handle to the I/O totals of a query, available once its iterator is fully consumed.
+///
+/// Returned by [`PayloadFieldIndexRead::filter_with_io_stats`]. Clones share the totals, so
+/// the handle stays usable after the iterator has been moved into a consumer.
+///
+/// [`PayloadFieldIndexRead::filter_with_io_stats`]: super::PayloadFieldIndexRead::filter_with_io_stats
+#[derive(Debug, Clone, Default)]
+pub struct QueryIoStats(Arc<OnceLock<QueryIoTotals>>);
+
+impl QueryIoStats {
+    /// `None` until the iterator is exhausted. Iterators dropped early never report.
+    pub fn totals(&self) -> Option<QueryIoTotals> {
+        self.0.get().copied()
+    }
+}
+
+/// Iterator recording [`QueryIoTotals`] into a [`QueryIoStats`] when exhausted.
+pub(super) struct IoStatsIter<'a, I> {
+    /// Dropped on exhaustion, iterators measuring their I/O report it by then
+    inner: Option<I>,
+    hw_counter: &'a HardwareCounterCell,
+    bytes_read_before: usize,
+    entries: usize,
+    stats: QueryIoStats,
+}
+
+impl<'a, I> IoStatsIter<'a, I> {
+    /// Wrap `inner`, attributing to it everything read into `hw_counter` since
+    /// `bytes_read_before` was taken.
+    pub(super) fn new(
+        inner: I,
+        hw_counter: &'a HardwareCounterCell,
+        bytes_read_before: usize,
+    ) -> (Self, QueryIoStats) {
+        let stats = QueryIoStats::default();
+        let iter = Self {
+            inner: Some(inner),
+            hw_counter,
+            bytes_read_before,
+            entries: 0,
+            stats: stats.clone(),
+        };
+        (iter, stats)
+    }
+}
+
+impl<I: Iterator> Iterator for IoStatsIter<'_, I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.as_mut()?.next();
+        if item.is_some() {
+            self.entries += 1;
+            return item;
+        }
+
+        drop(self.inner.take());
+        let bytes_read = self
+            .hw_counter
+            .payload_index_io_read_counter()
+            .get()
+            .saturating_sub(self.bytes_read_before);
+        let _ = self.stats.0.set(QueryIoTotals {
+            bytes_read,
+            entries: self.entries,
+        });
+        None
+    }
+}
@@ -51,6 +51,7 @@ pub trait FullTextIndexRead {
             points_values_count: self.points_count(),
             points_count: self.points_count(),
             histogram_bucket_size: None,
+            cardinality_estimation_error: None,
         }
     }
 
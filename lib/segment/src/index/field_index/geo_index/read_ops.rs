@@ -194,6 +194,7 @@ pub trait GeoMapIndexRead {
             points_count: self.points_count(),
             points_values_count: self.points_values_count(),
             histogram_bucket_size: None,
+            cardinality_estimation_error: None,
             index_type: self.telemetry_index_type(),
         }
     }
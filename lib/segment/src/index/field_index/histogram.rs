@@ -52,6 +52,28 @@ impl<T: Numericable + Serialize + DeserializeOwned> Histogram<T> {
         }
     }
 
+    /// Histogram of `points`, which must be unique and sorted ascending. They are consumed one
+    /// by one, without being collected.
+    pub fn from_sorted(
+        max_bucket_size: usize,
+        precision: f64,
+        points: impl IntoIterator<Item = Point<T>>,
+    ) -> Self {
+        let mut histogram = Self::new(max_bucket_size, precision);
+        for point in points {
+            // Each point is above all borders, so at most the last border moves onto it, which
+            // doesn't look up any neighbours
+            histogram.insert(point, |_| None, |_| None);
+        }
+        histogram
+    }
+
+    /// Histogram of `points` with the bucket size and precision settings of this one. See
+    /// [`Self::from_sorted`].
+    pub fn rebuilt(&self, points: impl IntoIterator<Item = Point<T>>) -> Self {
+        Self::from_sorted(self.max_bucket_size, self.precision, points)
+    }
+
     pub fn load_via<Fs: UniversalReadFs>(fs: &Fs, path: &Path) -> OperationResult<Self> {
         let config_path = path.join(CONFIG_PATH);
         let borders_path = path.join(BORDERS_PATH);
@@ -54,6 +54,13 @@ impl<N: Default> ImmutablePointToValues<N> {
             + values_container.capacity() * std::mem::size_of::<N>()
     }
 
+    /// Approximate RAM usage in bytes of a structure holding `points_count` points, of which
+    /// `multi_values_count` values belong to multi-value points, without building it.
+    pub fn estimate_ram_usage_bytes(points_count: usize, multi_values_count: usize) -> usize {
+        points_count * std::mem::size_of::<PointValueEntry<N>>()
+            + multi_values_count * std::mem::size_of::<N>()
+    }
+
     pub fn new(src: Vec<Vec<N>>) -> Self {
         let mut point_entries = Vec::with_capacity(src.len());
 
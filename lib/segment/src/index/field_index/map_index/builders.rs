@@ -11,9 +11,9 @@ use common::universal_io::MmapFs;
 use gridstore::Blob;
 use serde_json::Value;
 
-use super::MapIndex;
 use super::key::MapIndexKey;
 use super::universal_map_index::UniversalMapIndex;
+use super::{Collation, MapIndex};
 use crate::common::operation_error::{OperationError, OperationResult};
 use crate::index::field_index::{FieldIndexBuilderTrait, PayloadFieldIndex, ValueIndexer};
 
@@ -56,6 +56,15 @@ pub struct MapIndexMmapBuilder<N: MapIndexKey + ?Sized> {
     pub(super) values_to_points: HashMap<<N as MapIndexKey>::Owned, Vec<PointOffsetType>>,
     pub(super) is_on_disk: bool,
     pub(super) deleted_points: BitVec,
+    pub(super) collation: Collation,
+}
+
+impl<N: MapIndexKey + ?Sized> MapIndexMmapBuilder<N> {
+    /// Ordering of string keys used by the built index, see [`Collation`].
+    pub fn with_collation(mut self, collation: Collation) -> Self {
+        self.collation = collation;
+        self
+    }
 }
 
 impl<N: MapIndexKey + ?Sized> FieldIndexBuilderTrait for MapIndexMmapBuilder<N>
@@ -117,6 +126,7 @@ where
             self.values_to_points,
             self.is_on_disk,
             &self.deleted_points,
+            self.collation,
         )?)))
     }
 }
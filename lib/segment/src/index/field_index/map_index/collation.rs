@@ -0,0 +1,100 @@
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+/// Ordering of string keys, used for sorted enumeration and range evaluation over keyword
+/// indexes.
+///
+/// Exact-match lookups are always byte-wise and are not affected by the collation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Collation {
+    /// Byte-wise comparison
+    #[default]
+    Binary,
+    /// Comparison of lowercased characters, `"Apple"` and `"apple"` are equal
+    CaseInsensitive,
+    /// Runs of ASCII digits are compared by numeric value, so `"item2"` sorts before `"item10"`
+    Natural,
+}
+
+impl Collation {
+    pub fn compare(self, a: &str, b: &str) -> Ordering {
+        match self {
+            Collation::Binary => a.cmp(b),
+            Collation::CaseInsensitive => a
+                .chars()
+                .flat_map(char::to_lowercase)
+                .cmp(b.chars().flat_map(char::to_lowercase)),
+            Collation::Natural => natural_cmp(a, b),
+        }
+    }
+
+    /// Whether `key` starts with `prefix` under this collation.
+    ///
+    /// The natural collation matches prefixes byte-wise, as a prefix may end inside a run of
+    /// digits.
+    pub fn starts_with(self, key: &str, prefix: &str) -> bool {
+        match self {
+            Collation::Binary | Collation::Natural => key.starts_with(prefix),
+            Collation::CaseInsensitive => {
+                let mut key = key.chars().flat_map(char::to_lowercase);
+                prefix
+                    .chars()
+                    .flat_map(char::to_lowercase)
+                    .all(|c| key.next() == Some(c))
+            }
+        }
+    }
+}
+
+fn natural_cmp(mut a: &str, mut b: &str) -> Ordering {
+    loop {
+        match (a.is_empty(), b.is_empty()) {
+            (true, true) => return Ordering::Equal,
+            (true, false) => return Ordering::Less,
+            (false, true) => return Ordering::Greater,
+            (false, false) => {}
+        }
+
+        let (a_chunk, a_rest) = split_chunk(a);
+        let (b_chunk, b_rest) = split_chunk(b);
+
+        let ordering = if is_digits(a_chunk) && is_digits(b_chunk) {
+            cmp_digits(a_chunk, b_chunk)
+        } else {
+            a_chunk.cmp(b_chunk)
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+
+        a = a_rest;
+        b = b_rest;
+    }
+}
+
+/// Split off the leading run of either ASCII digits or non-digits.
+fn split_chunk(s: &str) -> (&str, &str) {
+    let starts_with_digit = s.starts_with(|c: char| c.is_ascii_digit());
+    let end = s
+        .find(|c: char| c.is_ascii_digit() != starts_with_digit)
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+fn is_digits(chunk: &str) -> bool {
+    chunk.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Compare runs of ASCII digits by value, without parsing them into a fixed-size integer.
+fn cmp_digits(a: &str, b: &str) -> Ordering {
+    let a_trimmed = a.trim_start_matches('0');
+    let b_trimmed = b.trim_start_matches('0');
+    a_trimmed
+        .len()
+        .cmp(&b_trimmed.len())
+        .then_with(|| a_trimmed.cmp(b_trimmed))
+        // Keep distinct keys like "07" and "7" apart
+        .then_with(|| a.len().cmp(&b.len()))
+}
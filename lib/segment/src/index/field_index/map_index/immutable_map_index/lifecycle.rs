@@ -198,8 +198,32 @@ where
     }
 
     pub fn remove_point(&mut self, idx: PointOffsetType) -> OperationResult<()> {
+        if self.remove_point_values(idx) {
+            // Update persisted storage
+            match self.storage {
+                Storage::Mmap(ref mut index) => index.remove_point(idx)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Batch version of [`Self::remove_point`]. `ids` must be ascending.
+    pub fn remove_points(&mut self, ids: &[PointOffsetType]) -> OperationResult<()> {
+        for &idx in ids {
+            self.remove_point_values(idx);
+        }
+        // Update persisted storage
+        match self.storage {
+            Storage::Mmap(ref mut index) => index.remove_points(ids)?,
+        }
+        Ok(())
+    }
+
+    /// Remove the values of point `idx` from the in-memory structures. Returns whether the point
+    /// had any values.
+    fn remove_point_values(&mut self, idx: PointOffsetType) -> bool {
+        let mut removed_values_count = 0;
         if let Some(removed_values) = self.point_to_values.get_values(idx) {
-            let mut removed_values_count = 0;
             for value in removed_values {
                 Self::remove_idx_from_value_list(
                     &mut self.value_to_points,
@@ -208,13 +232,6 @@ where
                     value.borrow(),
                     idx,
                 );
-
-                // Update persisted storage
-                match self.storage {
-                    Storage::Mmap(ref mut index) => {
-                        index.remove_point(idx);
-                    }
-                }
                 removed_values_count += 1;
             }
 
@@ -224,7 +241,7 @@ where
             self.values_count = self.values_count.saturating_sub(removed_values_count);
         }
         self.point_to_values.remove_point(idx);
-        Ok(())
+        removed_values_count > 0
     }
 
     #[inline]
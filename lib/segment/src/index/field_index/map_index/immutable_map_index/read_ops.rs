@@ -1,10 +1,13 @@
-use std::borrow::{Borrow as _, Cow};
+use std::borrow::{Borrow, Cow};
 use std::iter;
 
+use common::bitvec::BitVec;
 use common::counter::hardware_counter::HardwareCounterCell;
 use common::types::PointOffsetType;
 use gridstore::Blob;
+use itertools::Itertools;
 
+use super::super::integrity::{check_count, integrity_error};
 use super::super::read_ops::MapIndexRead;
 use super::super::{IdIter, MapIndexKey};
 use super::{ContainerSegment, ImmutableMapIndex, Storage};
@@ -70,6 +73,15 @@ where
         }
     }
 
+    /// Container slices are sorted, only repeated values of a point need to be skipped.
+    fn get_iterator_sorted(&self, value: &N, _hw_counter: &HardwareCounterCell) -> IdIter<'_> {
+        if let Some(entry) = self.value_to_points.get(value) {
+            Box::new(self.get_entry_iterator(entry).dedup())
+        } else {
+            Box::new(iter::empty::<PointOffsetType>())
+        }
+    }
+
     fn for_each_value(&self, mut f: impl FnMut(&N) -> OperationResult<()>) -> OperationResult<()> {
         self.value_to_points.keys().try_for_each(|v| f(v.borrow()))
     }
@@ -132,6 +144,122 @@ where
         });
     }
 
+    /// Check the invariants of the in-memory structures and of the backing storage, see
+    /// [`MapIndex::verify_integrity`].
+    ///
+    /// [`MapIndex::verify_integrity`]: super::super::MapIndex::verify_integrity
+    pub fn verify_integrity(&self) -> OperationResult<()> {
+        let container_len = self.value_to_points_container.len();
+        if self.deleted_value_to_points_container.len() > container_len {
+            return Err(integrity_error(format_args!(
+                "{} deleted flags for {container_len} container entries",
+                self.deleted_value_to_points_container.len(),
+            )));
+        }
+
+        let mut live_pairs_count = 0;
+        for (value, entry) in &self.value_to_points {
+            let value: &N = value.borrow();
+            let range = entry.range.start as usize..entry.range.end as usize;
+            let Some(ids) = self.value_to_points_container.get(range) else {
+                return Err(integrity_error(format_args!(
+                    "range {:?} of value {value} is out of the container of {container_len} ids",
+                    entry.range,
+                )));
+            };
+            if !ids.is_sorted() {
+                return Err(integrity_error(format_args!(
+                    "ids of value {value} are not sorted",
+                )));
+            }
+
+            let mut live_count = 0;
+            for idx in self.get_entry_iterator(entry) {
+                let has_value = self
+                    .point_to_values
+                    .get_values(idx)
+                    .is_some_and(|mut values| values.any(|v| Borrow::<N>::borrow(v) == value));
+                if !has_value {
+                    return Err(integrity_error(format_args!(
+                        "value {value} refers to point {idx}, which doesn't have it",
+                    )));
+                }
+                live_count += 1;
+            }
+            if live_count == 0 {
+                return Err(integrity_error(format_args!(
+                    "value {value} has no points left",
+                )));
+            }
+            check_count(
+                &format!("count of value {value}"),
+                entry.count as usize,
+                live_count,
+            )?;
+            live_pairs_count += live_count;
+        }
+        check_count("values count", self.values_count, live_pairs_count)?;
+
+        let mut indexed_points = 0;
+        let mut point_values_count = 0;
+        for idx in 0..self.storage_points_count() as PointOffsetType {
+            let count = self.point_to_values.get_values_count(idx).unwrap_or(0);
+            if count > 0 {
+                indexed_points += 1;
+            }
+            point_values_count += count;
+        }
+        check_count("values count", self.values_count, point_values_count)?;
+        check_count("indexed points", self.indexed_points, indexed_points)?;
+
+        match &self.storage {
+            Storage::Mmap(index) => index.verify_integrity(),
+        }
+    }
+
+    /// See [`UniversalMapIndex::deleted_ratio`].
+    ///
+    /// [`UniversalMapIndex::deleted_ratio`]: super::super::universal_map_index::UniversalMapIndex::deleted_ratio
+    pub fn deleted_ratio(&self) -> f64 {
+        match &self.storage {
+            Storage::Mmap(index) => index.deleted_ratio(),
+        }
+    }
+
+    /// See [`MapIndex::has_value_bitmap`].
+    ///
+    /// Removals are forwarded to the backing storage, which also knows the points without
+    /// values.
+    ///
+    /// [`MapIndex::has_value_bitmap`]: super::super::MapIndex::has_value_bitmap
+    pub fn has_value_bitmap(&self) -> BitVec {
+        match &self.storage {
+            Storage::Mmap(index) => index.has_value_bitmap(),
+        }
+    }
+
+    /// See [`UniversalMapIndex::get_iterator_including_deleted`].
+    ///
+    /// The in-memory containers drop removed points, the backing storage still lists them.
+    ///
+    /// [`UniversalMapIndex::get_iterator_including_deleted`]: super::super::universal_map_index::UniversalMapIndex::get_iterator_including_deleted
+    pub fn get_iterator_including_deleted(
+        &self,
+        value: &N,
+        hw_counter: &HardwareCounterCell,
+    ) -> IdIter<'_> {
+        match &self.storage {
+            Storage::Mmap(index) => index.get_iterator_including_deleted(value, hw_counter),
+        }
+    }
+
+    /// Number of points known to the backing storage.
+    fn storage_points_count(&self) -> usize {
+        match &self.storage {
+            Storage::Mmap(index) => index.storage.point_to_values.len(),
+        }
+    }
+
     fn get_entry_iterator(
         &self,
         entry: &ContainerSegment,
@@ -0,0 +1,19 @@
+//! Helpers for `verify_integrity` of the map index variants.
+
+use std::fmt::Display;
+
+use crate::common::operation_error::{OperationError, OperationResult};
+
+pub(super) fn integrity_error(description: impl Display) -> OperationError {
+    OperationError::inconsistent_storage(format!("Map index integrity check failed: {description}"))
+}
+
+/// Check a counter kept alongside the index against the `actual` count.
+pub(super) fn check_count(name: &str, expected: usize, actual: usize) -> OperationResult<()> {
+    if expected != actual {
+        return Err(integrity_error(format_args!(
+            "{name} is {expected}, but the index holds {actual}",
+        )));
+    }
+    Ok(())
+}
@@ -13,6 +13,12 @@ use crate::types::{IntPayloadType, UuidIntType};
 pub trait MapIndexKey: Key + StoredValue + Eq + Display + Debug {
     type Owned: Borrow<Self> + Hash + Eq + Clone + FromStr + Default + 'static;
 
+    /// Whether immutable indexes store all keys ordered by their [`Collation`], for range and
+    /// prefix lookups. Only string keys have a collation.
+    ///
+    /// [`Collation`]: super::Collation
+    const KEEPS_SORTED_KEYS: bool = false;
+
     fn to_owned(&self) -> <Self as MapIndexKey>::Owned;
 
     fn gridstore_block_size() -> usize {
@@ -29,6 +35,8 @@ pub trait MapIndexKey: Key + StoredValue + Eq + Display + Debug {
 impl MapIndexKey for str {
     type Owned = EcoString;
 
+    const KEEPS_SORTED_KEYS: bool = true;
+
     fn to_owned(&self) -> <Self as MapIndexKey>::Owned {
         EcoString::from(self)
     }
@@ -5,12 +5,12 @@ use common::types::PointOffsetType;
 use common::universal_io::MmapFs;
 use gridstore::Blob;
 
-use super::MapIndex;
 use super::builders::MapIndexMmapBuilder;
 use super::immutable_map_index::ImmutableMapIndex;
 use super::key::MapIndexKey;
 use super::mutable_map_index::MutableMapIndex;
 use super::universal_map_index::UniversalMapIndex;
+use super::{Collation, MapIndex};
 use crate::common::Flusher;
 use crate::common::operation_error::OperationResult;
 
@@ -62,6 +62,7 @@ where
             values_to_points: Default::default(),
             is_on_disk,
             deleted_points: deleted_points.to_owned(),
+            collation: Collation::default(),
         }
     }
 
@@ -89,10 +90,16 @@ where
         match self {
             MapIndex::Mutable(index) => index.remove_point(id),
             MapIndex::Immutable(index) => index.remove_point(id),
-            MapIndex::Mmap(index) => {
-                index.remove_point(id);
-                Ok(())
-            }
+            MapIndex::Mmap(index) => index.remove_point(id),
+        }
+    }
+
+    /// Remove a batch of points at once. `ids` must be ascending.
+    pub(crate) fn remove_points(&mut self, ids: &[PointOffsetType]) -> OperationResult<()> {
+        match self {
+            MapIndex::Mutable(index) => ids.iter().try_for_each(|&id| index.remove_point(id)),
+            MapIndex::Immutable(index) => index.remove_points(ids),
+            MapIndex::Mmap(index) => index.remove_points(ids),
         }
     }
 
@@ -2,14 +2,17 @@ use common::types::PointOffsetType;
 use gridstore::Blob;
 
 pub use self::builders::{MapIndexBuilder, MapIndexGridstoreBuilder, MapIndexMmapBuilder};
+pub use self::collation::Collation;
 use self::immutable_map_index::ImmutableMapIndex;
 pub use self::key::MapIndexKey;
 use self::mutable_map_index::MutableMapIndex;
 use self::universal_map_index::UniversalMapIndex;
 
 mod builders;
+mod collation;
 mod facet_index_impl;
 pub mod immutable_map_index;
+mod integrity;
 pub mod key;
 mod lifecycle;
 pub mod mutable_map_index;
@@ -2,11 +2,13 @@ use std::borrow::{Borrow, Cow};
 use std::collections::HashMap;
 use std::iter;
 
+use common::bitvec::BitVec;
 use common::counter::hardware_counter::HardwareCounterCell;
 use common::types::PointOffsetType;
 use gridstore::Blob;
 use roaring::RoaringBitmap;
 
+use super::super::integrity::{check_count, integrity_error};
 use super::super::read_ops::MapIndexRead;
 use super::super::{IdIter, MapIndexKey};
 use crate::common::operation_error::OperationResult;
@@ -94,6 +96,57 @@ where
         true
     }
 
+    /// See [`MapIndex::has_value_bitmap`](super::super::MapIndex::has_value_bitmap).
+    pub(in crate::index::field_index::map_index) fn has_value_bitmap(&self) -> BitVec {
+        self.point_to_values
+            .iter()
+            .map(|values| !values.is_empty())
+            .collect()
+    }
+
+    /// Check the invariants of the in-memory structures, see
+    /// [`MapIndex::verify_integrity`](super::super::MapIndex::verify_integrity).
+    pub(in crate::index::field_index::map_index) fn verify_integrity(&self) -> OperationResult<()> {
+        for (value, ids) in &self.map {
+            let value: &N = value.borrow();
+            for idx in ids {
+                let has_value = self
+                    .point_to_values
+                    .get(idx as usize)
+                    .is_some_and(|values| values.iter().any(|v| Borrow::<N>::borrow(v) == value));
+                if !has_value {
+                    return Err(integrity_error(format_args!(
+                        "value {value} refers to point {idx}, which doesn't have it",
+                    )));
+                }
+            }
+        }
+
+        for (idx, values) in self.point_to_values.iter().enumerate() {
+            for value in values {
+                let is_indexed = self
+                    .map
+                    .get(value)
+                    .is_some_and(|ids| ids.contains(idx as PointOffsetType));
+                if !is_indexed {
+                    let value: &N = value.borrow();
+                    return Err(integrity_error(format_args!(
+                        "value {value} of point {idx} is missing from the index",
+                    )));
+                }
+            }
+        }
+
+        let values_count = self.point_to_values.iter().map(Vec::len).sum();
+        check_count("values count", self.values_count, values_count)?;
+        let indexed_points = self
+            .point_to_values
+            .iter()
+            .filter(|values| !values.is_empty())
+            .count();
+        check_count("indexed points", self.indexed_points, indexed_points)
+    }
+
     pub(in crate::index::field_index::map_index) fn for_points_values(
         &self,
         points: impl Iterator<Item = PointOffsetType>,
@@ -166,6 +219,11 @@ where
             .unwrap_or_else(|| Box::new(iter::empty::<PointOffsetType>()))
     }
 
+    /// Bitmaps iterate in ascending order already.
+    fn get_iterator_sorted(&self, value: &N, hw_counter: &HardwareCounterCell) -> IdIter<'_> {
+        self.get_iterator(value, hw_counter)
+    }
+
     fn for_each_value(&self, mut f: impl FnMut(&N) -> OperationResult<()>) -> OperationResult<()> {
         self.map.keys().try_for_each(|v| f(v.borrow()))
     }
@@ -58,6 +58,10 @@ where
         self.inner.get_iterator(value, hw_counter)
     }
 
+    fn get_iterator_sorted(&self, value: &N, hw_counter: &HardwareCounterCell) -> IdIter<'_> {
+        self.inner.get_iterator_sorted(value, hw_counter)
+    }
+
     fn for_each_value(&self, f: impl FnMut(&N) -> OperationResult<()>) -> OperationResult<()> {
         self.inner.for_each_value(f)
     }
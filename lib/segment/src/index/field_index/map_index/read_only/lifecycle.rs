@@ -31,7 +31,8 @@ where
     }
 
     /// Read-only mirror of [`MapIndex::new_mmap`][1]: open the immutable
-    /// (mmap-format) map index read-only through [`UniversalMapIndex::open`],
+    /// (mmap-format) map index read-only through
+    /// [`UniversalMapIndex::open_read_only`],
     /// threading every file open through the filesystem handle `fs`.
     ///
     /// The writable enum has two mmap variants (`Immutable` for in-RAM with
@@ -52,7 +53,7 @@ where
             is_on_disk || common::low_memory::low_memory_mode().prefer_disk();
 
         Ok(
-            UniversalMapIndex::open(fs, path, effective_is_on_disk, deleted_points)?
+            UniversalMapIndex::open_read_only(fs, path, effective_is_on_disk, deleted_points)?
                 .map(Self::Immutable),
         )
     }
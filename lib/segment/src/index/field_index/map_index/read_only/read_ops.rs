@@ -92,6 +92,13 @@ where
         }
     }
 
+    fn get_iterator_sorted(&self, value: &N, hw_counter: &HardwareCounterCell) -> IdIter<'_> {
+        match self {
+            ReadOnlyMapIndex::Appendable(index) => index.get_iterator_sorted(value, hw_counter),
+            ReadOnlyMapIndex::Immutable(index) => index.get_iterator_sorted(value, hw_counter),
+        }
+    }
+
     fn for_each_value(&self, f: impl FnMut(&N) -> OperationResult<()>) -> OperationResult<()> {
         match self {
             ReadOnlyMapIndex::Appendable(index) => index.for_each_value(f),
@@ -1,6 +1,7 @@
 use std::borrow::{Borrow, Cow};
 use std::hash::{BuildHasher, Hash};
 
+use common::bitvec::BitVec;
 use common::counter::hardware_counter::HardwareCounterCell;
 use common::types::PointOffsetType;
 use gridstore::Blob;
@@ -8,7 +9,7 @@ use indexmap::IndexSet;
 
 use super::key::MapIndexKey;
 use super::{IdIter, MapIndex};
-use crate::common::operation_error::OperationResult;
+use crate::common::operation_error::{OperationError, OperationResult};
 use crate::index::field_index::CardinalityEstimation;
 use crate::index::field_index::stat_tools::number_of_selected_points;
 use crate::index::payload_config::{IndexMutability, StorageType};
@@ -51,6 +52,18 @@ pub trait MapIndexRead<N: MapIndexKey + ?Sized> {
 
     fn get_iterator(&self, value: &N, hw_counter: &HardwareCounterCell) -> IdIter<'_>;
 
+    /// Same points as [`Self::get_iterator`], in ascending order and each point once.
+    ///
+    /// Sorted iterators of several values can be intersected or united with a linear merge. The
+    /// default sorts the ids of [`Self::get_iterator`]; variants storing them sorted override it
+    /// to stream them.
+    fn get_iterator_sorted(&self, value: &N, hw_counter: &HardwareCounterCell) -> IdIter<'_> {
+        let mut ids: Vec<_> = self.get_iterator(value, hw_counter).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        Box::new(ids.into_iter())
+    }
+
     fn for_each_value(&self, f: impl FnMut(&N) -> OperationResult<()>) -> OperationResult<()>;
 
     /// Iterate `(value, count)` pairs.
@@ -94,10 +107,29 @@ pub trait MapIndexRead<N: MapIndexKey + ?Sized> {
             points_count: self.get_indexed_points(),
             points_values_count: self.get_values_count(),
             histogram_bucket_size: None,
+            cardinality_estimation_error: None,
             index_type: self.telemetry_index_type(),
         }
     }
 
+    /// Owned copies of the values of point `idx`, for callers that keep them
+    /// beyond the borrow of the index.
+    ///
+    /// Reads are charged to `hw_counter` like [`Self::get_values`]. Returns
+    /// `None` for deleted and absent points, which some variants otherwise
+    /// report as an empty iterator.
+    fn get_values_owned(
+        &self,
+        idx: PointOffsetType,
+        hw_counter: &HardwareCounterCell,
+    ) -> Option<Vec<<N as MapIndexKey>::Owned>> {
+        let values: Vec<_> = self
+            .get_values(idx, hw_counter)?
+            .map(|value| MapIndexKey::to_owned(value.as_ref()))
+            .collect();
+        (!values.is_empty()).then_some(values)
+    }
+
     fn values_is_empty(&self, idx: PointOffsetType) -> bool {
         self.values_count(idx).unwrap_or(0) == 0
     }
@@ -194,6 +226,33 @@ pub trait MapIndexRead<N: MapIndexKey + ?Sized> {
         })?;
         Ok(Box::new(points.into_iter()))
     }
+
+    /// Distribution of values by the number of live points they occur in.
+    ///
+    /// Point counts are split into `buckets` equally wide ranges, each reported
+    /// as `(bucket_upper_bound, num_values_in_bucket)` with an inclusive upper
+    /// bound. Values without live points fall into the first bucket, so the
+    /// bucket sizes always add up to [`Self::get_unique_values_count`].
+    fn value_frequency_histogram(&self, buckets: usize) -> OperationResult<Vec<(usize, usize)>> {
+        let mut counts = Vec::with_capacity(self.get_unique_values_count());
+        self.for_each_count_per_value(None, |_, count| {
+            counts.push(count);
+            Ok(())
+        })?;
+
+        if buckets == 0 || counts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let max_count = counts.iter().copied().max().unwrap_or_default().max(1);
+        let bucket_width = max_count.div_ceil(buckets);
+
+        let mut histogram: Vec<_> = (1..=buckets).map(|i| (i * bucket_width, 0)).collect();
+        for count in counts {
+            histogram[count.saturating_sub(1) / bucket_width].1 += 1;
+        }
+        Ok(histogram)
+    }
 }
 
 impl<N: MapIndexKey + ?Sized> MapIndexRead<N> for MapIndex<N>
@@ -277,6 +336,14 @@ where
         }
     }
 
+    fn get_iterator_sorted(&self, value: &N, hw_counter: &HardwareCounterCell) -> IdIter<'_> {
+        match self {
+            MapIndex::Mutable(index) => index.get_iterator_sorted(value, hw_counter),
+            MapIndex::Immutable(index) => index.get_iterator_sorted(value, hw_counter),
+            MapIndex::Mmap(index) => index.get_iterator_sorted(value, hw_counter),
+        }
+    }
+
     fn for_each_value(&self, f: impl FnMut(&N) -> OperationResult<()>) -> OperationResult<()> {
         match self {
             MapIndex::Mutable(index) => index.for_each_value(f),
@@ -350,6 +417,7 @@ where
             points_count: <Self as MapIndexRead<N>>::get_indexed_points(self),
             points_values_count: <Self as MapIndexRead<N>>::get_values_count(self),
             histogram_bucket_size: None,
+            cardinality_estimation_error: None,
             index_type: match self {
                 MapIndex::Mutable(_) => "mutable_map",
                 MapIndex::Immutable(_) => "immutable_map",
@@ -389,6 +457,71 @@ where
         <Self as MapIndexRead<N>>::ram_usage_bytes(self)
     }
 
+    /// Check the internal invariants of the index:
+    ///
+    /// - every point listed for a value has that value, and vice versa
+    /// - id lists are sorted where lookups rely on it
+    /// - deletion flags agree with the deleted count
+    /// - value and point counters match the stored values
+    ///
+    /// Meant for diagnostics, reads the whole index. Returns an
+    /// [`OperationError::InconsistentStorage`] describing the first violation.
+    ///
+    /// [`OperationError::InconsistentStorage`]: crate::common::operation_error::OperationError::InconsistentStorage
+    pub fn verify_integrity(&self) -> OperationResult<()> {
+        match self {
+            MapIndex::Mutable(index) => index.inner.verify_integrity(),
+            MapIndex::Immutable(index) => index.verify_integrity(),
+            MapIndex::Mmap(index) => index.verify_integrity(),
+        }
+    }
+
+    /// A bit per point id, set for points with at least one value. Bits of deleted points and
+    /// points without values are clear.
+    ///
+    /// Covers the point ids known to the index, higher ids have no value.
+    pub fn has_value_bitmap(&self) -> BitVec {
+        match self {
+            MapIndex::Mutable(index) => index.inner.has_value_bitmap(),
+            MapIndex::Immutable(index) => index.has_value_bitmap(),
+            MapIndex::Mmap(index) => index.has_value_bitmap(),
+        }
+    }
+
+    /// Whether the share of deleted points exceeds `threshold`, so that rebuilding the index
+    /// would reclaim enough space to be worth it.
+    ///
+    /// The mutable variant removes points in place and never needs compaction.
+    pub fn needs_compaction(&self, threshold: f64) -> bool {
+        let deleted_ratio = match self {
+            MapIndex::Mutable(_) => return false,
+            MapIndex::Immutable(index) => index.deleted_ratio(),
+            MapIndex::Mmap(index) => index.deleted_ratio(),
+        };
+        deleted_ratio > threshold
+    }
+
+    /// Points indexed for `value`, including the ones removed since the index was built, for
+    /// audit trails. [`MapIndexRead::get_iterator`] yields live points only.
+    ///
+    /// Deleted points stay listed until the index is rebuilt. The mutable variant removes
+    /// points in place and can't list them.
+    pub fn get_iterator_including_deleted(
+        &self,
+        value: &N,
+        hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<IdIter<'_>> {
+        match self {
+            MapIndex::Mutable(_) => Err(OperationError::service_error(
+                "Can't list deleted points of mutable map index",
+            )),
+            MapIndex::Immutable(index) => {
+                Ok(index.get_iterator_including_deleted(value, hw_counter))
+            }
+            MapIndex::Mmap(index) => Ok(index.get_iterator_including_deleted(value, hw_counter)),
+        }
+    }
+
     pub fn is_on_disk(&self) -> bool {
         match self {
             MapIndex::Mutable(_) => false,
@@ -1,20 +1,26 @@
 use std::borrow::Borrow;
 use std::collections::HashSet;
 use std::hint::black_box;
+use std::ops::Bound;
 use std::path::Path;
 
+use ahash::HashMap;
 use common::bitvec::BitVec;
 use common::counter::hardware_counter::HardwareCounterCell;
+use common::persisted_hashmap::READ_ENTRY_OVERHEAD;
 use common::types::PointOffsetType;
+use common::universal_io::{MmapFile, MmapFs, ReadOnly, UniversalRead, UniversalReadFileOps};
 use ecow::EcoString;
 use gridstore::Blob;
+use itertools::Itertools;
 use rstest::rstest;
 use serde_json::Value;
 use tempfile::Builder;
 
-use super::MapIndex;
 use super::key::MapIndexKey;
 use super::read_ops::MapIndexRead;
+use super::universal_map_index::{CONFIG_PATH, SORTED_KEYS_PATH, UniversalMapIndex};
+use super::{Collation, MapIndex};
 use crate::index::field_index::{
     CardinalityEstimation, FieldIndexBuilderTrait, PayloadFieldIndex, PayloadFieldIndexRead,
     ValueIndexer,
@@ -90,15 +96,11 @@ fn save_map_index<N>(
     }
 }
 
-fn load_map_index<N: MapIndexKey + ?Sized>(
-    data: &[Vec<<N as MapIndexKey>::Owned>],
-    path: &Path,
-    index_type: IndexType,
-) -> MapIndex<N>
+fn open_map_index<N: MapIndexKey + ?Sized>(path: &Path, index_type: IndexType) -> MapIndex<N>
 where
     Vec<<N as MapIndexKey>::Owned>: Blob + Send + Sync,
 {
-    let index = match index_type {
+    match index_type {
         IndexType::MutableGridstore => MapIndex::<N>::new_gridstore(path.to_path_buf(), true)
             .unwrap()
             .unwrap(),
@@ -108,7 +110,22 @@ where
         IndexType::RamMmap => MapIndex::<N>::new_mmap(path, false, &empty_deleted())
             .unwrap()
             .unwrap(),
-    };
+    }
+}
+
+/// Open the index saved from `data` and check that it has the values of every point.
+///
+/// Indexes with points without values are opened with [`open_map_index`] instead, as those
+/// points may be reported as absent.
+fn load_map_index<N: MapIndexKey + ?Sized>(
+    data: &[Vec<<N as MapIndexKey>::Owned>],
+    path: &Path,
+    index_type: IndexType,
+) -> MapIndex<N>
+where
+    Vec<<N as MapIndexKey>::Owned>: Blob + Send + Sync,
+{
+    let index = open_map_index(path, index_type);
     let hw_counter = HardwareCounterCell::new();
     for (idx, values) in data.iter().enumerate() {
         let index_values: HashSet<<N as MapIndexKey>::Owned> = index
@@ -391,6 +408,374 @@ fn test_map_index_reload(#[case] index_type: IndexType) {
     assert_eq!(hits, vec![3, 4]);
 }
 
+/// `get_count_for_value` must only count live points, both after runtime
+/// deletions and after reopening with a deletion bitslice.
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_count_for_value_excludes_deleted(#[case] index_type: IndexType) {
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    let data: Vec<Vec<IntPayloadType>> = vec![
+        vec![1, 2], // id 0
+        vec![1],    // id 1
+        vec![2],    // id 2
+        vec![1, 3], // id 3
+        vec![2, 3], // id 4
+        vec![3],    // id 5
+    ];
+
+    save_map_index::<IntPayloadType>(&data, temp_dir.path(), index_type, |v| (*v).into());
+    let mut index = load_map_index::<IntPayloadType>(&data, temp_dir.path(), index_type);
+
+    let hw_counter = HardwareCounterCell::new();
+    assert_eq!(index.get_count_for_value(&1, &hw_counter), Some(3));
+
+    index.remove_point(1).unwrap();
+    index.remove_point(5).unwrap();
+
+    assert_eq!(index.get_count_for_value(&1, &hw_counter), Some(2));
+    assert_eq!(index.get_count_for_value(&2, &hw_counter), Some(3));
+    assert_eq!(index.get_count_for_value(&3, &hw_counter), Some(2));
+    assert_eq!(index.get_count_for_value(&4, &hw_counter), None);
+    drop(index);
+
+    if index_type == IndexType::MutableGridstore {
+        return;
+    }
+
+    let deleted = deleted_with(&[0, 4]);
+    let is_on_disk = index_type == IndexType::Mmap;
+    let index = MapIndex::<IntPayloadType>::new_mmap(temp_dir.path(), is_on_disk, &deleted)
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(index.get_count_for_value(&1, &hw_counter), Some(2));
+    assert_eq!(index.get_count_for_value(&2, &hw_counter), Some(1));
+    assert_eq!(index.get_count_for_value(&3, &hw_counter), Some(2));
+}
+
+/// Points stored without values don't count as deletions: counts are read from the stored
+/// lengths until a point with values is deleted.
+#[test]
+fn test_count_for_value_with_points_without_values() {
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    let data: Vec<Vec<IntPayloadType>> = vec![
+        vec![1, 2], // id 0
+        vec![],     // id 1
+        vec![1],    // id 2
+        vec![],     // id 3
+        vec![2],    // id 4
+    ];
+    save_map_index::<IntPayloadType>(&data, temp_dir.path(), IndexType::Mmap, |v| (*v).into());
+    let mut index = MapIndex::<IntPayloadType>::new_mmap(temp_dir.path(), true, &empty_deleted())
+        .unwrap()
+        .unwrap();
+
+    let hw_counter = HardwareCounterCell::new();
+    assert_eq!(index.get_count_for_value(&1, &hw_counter), Some(2));
+    assert_eq!(index.get_count_for_value(&2, &hw_counter), Some(2));
+    assert_eq!(
+        hw_counter.payload_index_io_read_counter().get(),
+        2 * READ_ENTRY_OVERHEAD,
+    );
+
+    // Deleting a point without values changes no count
+    index.remove_point(3).unwrap();
+    assert_eq!(index.get_count_for_value(&1, &hw_counter), Some(2));
+
+    index.remove_point(2).unwrap();
+    assert_eq!(index.get_count_for_value(&1, &hw_counter), Some(1));
+    assert_eq!(index.get_count_for_value(&2, &hw_counter), Some(2));
+}
+
+/// Deleted points stay listed by the audit iterator, while the normal iterator skips them.
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_iterator_including_deleted(#[case] index_type: IndexType) {
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    let data: Vec<Vec<IntPayloadType>> = vec![
+        vec![1, 2], // id 0
+        vec![1],    // id 1
+        vec![2],    // id 2
+        vec![1, 3], // id 3
+        vec![3],    // id 4
+    ];
+
+    save_map_index::<IntPayloadType>(&data, temp_dir.path(), index_type, |v| (*v).into());
+    let mut index = load_map_index::<IntPayloadType>(&data, temp_dir.path(), index_type);
+    index.remove_point(1).unwrap();
+    index.remove_point(3).unwrap();
+
+    let hw_counter = HardwareCounterCell::new();
+    let live = index.get_iterator(&1, &hw_counter).sorted().collect_vec();
+    assert_eq!(live, vec![0]);
+
+    if index_type == IndexType::MutableGridstore {
+        assert!(
+            index
+                .get_iterator_including_deleted(&1, &hw_counter)
+                .is_err()
+        );
+        return;
+    }
+
+    let audit = index
+        .get_iterator_including_deleted(&1, &hw_counter)
+        .unwrap()
+        .sorted()
+        .collect_vec();
+    assert_eq!(audit, vec![0, 1, 3]);
+    let audit = index
+        .get_iterator_including_deleted(&2, &hw_counter)
+        .unwrap()
+        .sorted()
+        .collect_vec();
+    assert_eq!(audit, vec![0, 2]);
+    assert_eq!(
+        index
+            .get_iterator_including_deleted(&4, &hw_counter)
+            .unwrap()
+            .count(),
+        0,
+    );
+}
+
+/// Without deletions, counting reads the stored lengths only: the charged I/O of a pure-count
+/// pass depends on the number of values, not on the number of points. Points stored without
+/// values are no deletions.
+#[test]
+fn test_count_for_value_charges_lookup_overhead_only() {
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    let values_count = 10;
+    // Every seventh point has no value
+    let data: Vec<Vec<IntPayloadType>> = (0..2000)
+        .map(|i| {
+            if i % 7 == 0 {
+                vec![]
+            } else {
+                vec![i % values_count]
+            }
+        })
+        .collect();
+
+    save_map_index::<IntPayloadType>(&data, temp_dir.path(), IndexType::Mmap, |v| (*v).into());
+    let index = MapIndex::<IntPayloadType>::new_mmap(temp_dir.path(), true, &empty_deleted())
+        .unwrap()
+        .unwrap();
+
+    let hw_counter = HardwareCounterCell::new();
+    let counted: usize = (0..values_count)
+        .map(|value| index.get_count_for_value(&value, &hw_counter).unwrap())
+        .sum();
+    assert_eq!(
+        counted,
+        data.iter().filter(|values| !values.is_empty()).count()
+    );
+
+    let charged = hw_counter.payload_index_io_read_counter().get();
+    assert_eq!(charged, values_count as usize * READ_ENTRY_OVERHEAD);
+    assert!(charged < data.len() * size_of::<PointOffsetType>());
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_value_frequency_histogram(#[case] index_type: IndexType) {
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+
+    // One hot value present in every point, five warm values in 6 points
+    // each, and 30 unique values present in a single point each.
+    let data: Vec<Vec<IntPayloadType>> = (0..30).map(|i| vec![0, i % 5 + 1, i + 100]).collect();
+
+    save_map_index::<IntPayloadType>(&data, temp_dir.path(), index_type, |v| (*v).into());
+    let index = load_map_index::<IntPayloadType>(&data, temp_dir.path(), index_type);
+
+    let histogram = index.value_frequency_histogram(3).unwrap();
+    assert_eq!(histogram, vec![(10, 35), (20, 0), (30, 1)]);
+    assert_eq!(
+        histogram.iter().map(|(_, count)| count).sum::<usize>(),
+        index.get_unique_values_count(),
+    );
+
+    assert!(index.value_frequency_histogram(0).unwrap().is_empty());
+}
+
+#[rstest]
+#[case(true)]
+#[case(false)]
+fn test_mmap_get_iterator_regex(#[case] is_on_disk: bool) {
+    let data: Vec<Vec<EcoString>> = vec![
+        vec![EcoString::from("err-1"), EcoString::from("warn-1")], // id 0
+        vec![EcoString::from("error-22")],                         // id 1
+        vec![EcoString::from("my-error-3")],                       // id 2
+        vec![EcoString::from("err-x")],                            // id 3
+        vec![EcoString::from("error-4"), EcoString::from("err-5")], // id 4
+    ];
+
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    save_map_index::<str>(&data, temp_dir.path(), IndexType::Mmap, |v| {
+        v.to_string().into()
+    });
+    let index =
+        UniversalMapIndex::<str>::open(&MmapFs, temp_dir.path(), is_on_disk, &deleted_with(&[0]))
+            .unwrap()
+            .unwrap();
+
+    let hw_counter = HardwareCounterCell::new();
+    let regex_points = |pattern: &str| -> Vec<PointOffsetType> {
+        index
+            .get_iterator_regex(pattern, &hw_counter)
+            .unwrap()
+            .collect()
+    };
+
+    // Anchored, deleted point 0 is excluded and point 4 is reported once
+    assert_eq!(regex_points(r"^err(or)?-\d+$"), vec![1, 4]);
+    // Unanchored
+    assert_eq!(regex_points("error"), vec![1, 2, 4]);
+    // Nothing matches
+    assert!(regex_points("^fatal").is_empty());
+
+    assert!(index.get_iterator_regex("err(", &hw_counter).is_err());
+
+    if is_on_disk {
+        assert!(hw_counter.payload_index_io_read_counter().get() > 0);
+    }
+}
+
+#[rstest]
+#[case(Collation::Binary, &["Item3", "item1", "item10", "item2"], &[0, 1, 2])]
+#[case(Collation::CaseInsensitive, &["item1", "item10", "item2", "Item3"], &[0, 1, 2, 3])]
+#[case(Collation::Natural, &["Item3", "item1", "item2", "item10"], &[0, 2])]
+fn test_mmap_collation(
+    #[case] collation: Collation,
+    #[case] expected_sorted: &[&str],
+    #[case] expected_range: &[PointOffsetType],
+) {
+    let data: Vec<Vec<EcoString>> = vec![
+        vec![EcoString::from("item1")],  // id 0
+        vec![EcoString::from("item10")], // id 1
+        vec![EcoString::from("item2")],  // id 2
+        vec![EcoString::from("Item3")],  // id 3
+    ];
+
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    let hw_counter = HardwareCounterCell::new();
+    let mut builder = MapIndex::<str>::builder_mmap(temp_dir.path(), true, &empty_deleted())
+        .with_collation(collation);
+    builder.init().unwrap();
+    for (idx, values) in data.iter().enumerate() {
+        let values: Vec<Value> = values.iter().map(|v| v.to_string().into()).collect();
+        let values: Vec<_> = values.iter().collect();
+        builder
+            .add_point(idx as PointOffsetType, &values, &hw_counter)
+            .unwrap();
+    }
+    drop(builder.finalize().unwrap());
+
+    // Collation is persisted in the index config
+    let index = UniversalMapIndex::<str>::open(&MmapFs, temp_dir.path(), true, &empty_deleted())
+        .unwrap()
+        .unwrap();
+    assert_eq!(index.collation(), collation);
+    assert_eq!(index.sorted_values().collect_vec(), expected_sorted);
+
+    // Points with a value in ["item1", "item3"]
+    let range: Vec<_> = index
+        .get_iterator_range(
+            Bound::Included("item1"),
+            Bound::Included("item3"),
+            &hw_counter,
+        )
+        .unwrap()
+        .collect();
+    assert_eq!(range, expected_range);
+    index.verify_integrity().unwrap();
+    drop(index);
+
+    // Indexes built before the sorted keys were stored sort them on open
+    fs_err::remove_file(temp_dir.path().join(SORTED_KEYS_PATH)).unwrap();
+    let index = UniversalMapIndex::<str>::open(&MmapFs, temp_dir.path(), true, &empty_deleted())
+        .unwrap()
+        .unwrap();
+    assert_eq!(index.sorted_values().collect_vec(), expected_sorted);
+    index.verify_integrity().unwrap();
+}
+
+#[rstest]
+#[case(Collation::Binary)]
+#[case(Collation::CaseInsensitive)]
+#[case(Collation::Natural)]
+fn test_estimate_unique_values(#[case] collation: Collation) {
+    let keys = [
+        "apple", "Apple", "apricot", "banana", "band", "bandana", "item1", "item10", "item2",
+        "Item3", "",
+    ];
+
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    let hw_counter = HardwareCounterCell::new();
+    let mut builder = MapIndex::<str>::builder_mmap(temp_dir.path(), true, &empty_deleted())
+        .with_collation(collation);
+    builder.init().unwrap();
+    for (idx, key) in keys.iter().enumerate() {
+        let value = Value::from(*key);
+        builder
+            .add_point(idx as PointOffsetType, &[&value], &hw_counter)
+            .unwrap();
+    }
+    drop(builder.finalize().unwrap());
+    let index = UniversalMapIndex::<str>::open(&MmapFs, temp_dir.path(), true, &empty_deleted())
+        .unwrap()
+        .unwrap();
+
+    for prefix in ["", "a", "ap", "APP", "ban", "band", "item1", "Item", "x"] {
+        let exact = keys
+            .iter()
+            .filter(|key| collation.starts_with(key, prefix))
+            .count();
+        assert_eq!(
+            index.estimate_unique_values_with_prefix(prefix).unwrap(),
+            exact,
+            "prefix {prefix:?}",
+        );
+    }
+
+    let bounds = [
+        (Bound::Included("apple"), Bound::Excluded("band")),
+        (Bound::Excluded("apple"), Bound::Included("band")),
+        (Bound::Unbounded, Bound::Included("item2")),
+        (Bound::Included("item1"), Bound::Unbounded),
+        (Bound::Included("z"), Bound::Included("a")),
+    ];
+    for (from, to) in bounds {
+        let exact = keys
+            .iter()
+            .filter(|key| {
+                let after_from = match from {
+                    Bound::Included(from) => collation.compare(key, from).is_ge(),
+                    Bound::Excluded(from) => collation.compare(key, from).is_gt(),
+                    Bound::Unbounded => true,
+                };
+                let before_to = match to {
+                    Bound::Included(to) => collation.compare(key, to).is_le(),
+                    Bound::Excluded(to) => collation.compare(key, to).is_lt(),
+                    Bound::Unbounded => true,
+                };
+                after_from && before_to
+            })
+            .count();
+        assert_eq!(
+            index.estimate_unique_values_in_range(from, to).unwrap(),
+            exact,
+            "range {from:?}..{to:?}",
+        );
+    }
+}
+
 /// Regression test: when reloading an mmap map index with a `deleted_points`
 /// bitslice shorter than `point_to_values.len()`, missing entries must
 /// default to live, not deleted. Empty-payload bits from the on-disk
@@ -451,3 +836,526 @@ fn test_map_index_reload_short_deleted_bitslice(#[case] index_type: IndexType) {
     hits.sort();
     assert_eq!(hits, vec![3]);
 }
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_get_values_owned(#[case] index_type: IndexType) {
+    let data = vec![
+        vec![
+            EcoString::from("AABB"),
+            EcoString::from("UUFF"),
+            EcoString::from("IIBB"),
+        ],
+        vec![EcoString::from("PPGG")],
+        vec![EcoString::from("FFMM"), EcoString::from("IICC")],
+    ];
+
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    save_map_index::<str>(&data, temp_dir.path(), index_type, |v| v.to_string().into());
+    let mut index = load_map_index::<str>(&data, temp_dir.path(), index_type);
+
+    let hw_counter = HardwareCounterCell::new();
+    for idx in 0..data.len() as PointOffsetType {
+        let referenced: Vec<EcoString> = index
+            .get_values(idx, &hw_counter)
+            .unwrap()
+            .map(|v| MapIndexKey::to_owned(v.as_ref()))
+            .collect();
+        let owned = index.get_values_owned(idx, &hw_counter).unwrap();
+        assert_eq!(owned, referenced);
+    }
+
+    index.remove_point(0).unwrap();
+    assert_eq!(index.get_values_owned(0, &hw_counter), None);
+    assert_eq!(
+        index.get_values_owned(1, &hw_counter),
+        Some(vec![EcoString::from("PPGG")]),
+    );
+    // Absent point
+    assert_eq!(index.get_values_owned(100, &hw_counter), None);
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_remove_points_batch(#[case] index_type: IndexType) {
+    let data: Vec<Vec<IntPayloadType>> = (0..100).map(|i| vec![i % 7, i % 11]).collect();
+
+    let batched_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    save_map_index::<IntPayloadType>(&data, batched_dir.path(), index_type, |v| (*v).into());
+    let mut batched = load_map_index::<IntPayloadType>(&data, batched_dir.path(), index_type);
+
+    let individual_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    save_map_index::<IntPayloadType>(&data, individual_dir.path(), index_type, |v| (*v).into());
+    let mut individual = load_map_index::<IntPayloadType>(&data, individual_dir.path(), index_type);
+
+    // Include an id past the end of the index
+    let ids: Vec<PointOffsetType> = (0..100).step_by(4).chain([1000]).collect();
+    batched.remove_points(&ids).unwrap();
+    for &id in &ids {
+        individual.remove_point(id).unwrap();
+    }
+
+    assert_eq!(batched.get_indexed_points(), 75);
+    assert_eq!(
+        batched.get_indexed_points(),
+        individual.get_indexed_points()
+    );
+    assert_eq!(batched.get_values_count(), individual.get_values_count());
+
+    let hw_counter = HardwareCounterCell::new();
+    for idx in 0..100 {
+        assert_eq!(
+            batched.get_values_owned(idx, &hw_counter),
+            individual.get_values_owned(idx, &hw_counter),
+        );
+        assert_eq!(batched.values_is_empty(idx), idx % 4 == 0);
+    }
+    for value in 0..11 {
+        let mut batched_hits: Vec<_> = batched.get_iterator(&value, &hw_counter).collect();
+        let mut individual_hits: Vec<_> = individual.get_iterator(&value, &hw_counter).collect();
+        batched_hits.sort();
+        individual_hits.sort();
+        assert_eq!(batched_hits, individual_hits);
+        assert_eq!(
+            batched.get_count_for_value(&value, &hw_counter),
+            individual.get_count_for_value(&value, &hw_counter),
+        );
+    }
+}
+
+#[test]
+fn test_build_progress() {
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    let point_to_values: Vec<Vec<IntPayloadType>> = (0..100).map(|i| vec![i % 10]).collect();
+    let mut values_to_points = HashMap::<IntPayloadType, Vec<PointOffsetType>>::default();
+    for (idx, values) in point_to_values.iter().enumerate() {
+        for value in values {
+            values_to_points
+                .entry(*value)
+                .or_default()
+                .push(idx as PointOffsetType);
+        }
+    }
+
+    let mut reported = Vec::new();
+    let index = UniversalMapIndex::<IntPayloadType>::build_with_progress(
+        &MmapFs,
+        temp_dir.path(),
+        point_to_values,
+        values_to_points,
+        false,
+        &empty_deleted(),
+        Collation::default(),
+        &mut |fraction| reported.push(fraction),
+    )
+    .unwrap();
+
+    assert_eq!(index.get_indexed_points(), 100);
+    assert!(reported.len() > 1, "{reported:?}");
+    assert!(reported.is_sorted_by(|a, b| a < b), "{reported:?}");
+    assert!(reported.iter().all(|fraction| *fraction > 0.0));
+    assert_eq!(reported.last(), Some(&1.0));
+}
+
+/// The empty string is a value like any other, unlike a point without values.
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_empty_string_vs_no_values(#[case] index_type: IndexType) {
+    let data = vec![
+        vec![EcoString::from("")],                       // id 0
+        vec![],                                          // id 1
+        vec![EcoString::from("a")],                      // id 2
+        vec![EcoString::from(""), EcoString::from("a")], // id 3
+        vec![],                                          // id 4
+    ];
+
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    save_map_index::<str>(&data, temp_dir.path(), index_type, |v| v.to_string().into());
+    let index = open_map_index::<str>(temp_dir.path(), index_type);
+
+    let hw_counter = HardwareCounterCell::new();
+
+    let values = index.get_values(0, &hw_counter).unwrap().collect_vec();
+    assert_eq!(values, [""]);
+    assert_eq!(index.values_count(0), Some(1));
+    assert!(!index.values_is_empty(0));
+    let values = index
+        .get_values(3, &hw_counter)
+        .unwrap()
+        .sorted()
+        .collect_vec();
+    assert_eq!(values, ["", "a"]);
+    for idx in [1, 4] {
+        // Absent or without values, depending on the storage
+        assert!(
+            index
+                .get_values(idx, &hw_counter)
+                .is_none_or(|mut values| values.next().is_none()),
+            "point {idx}",
+        );
+        assert!(index.values_is_empty(idx));
+    }
+
+    assert_eq!(index.get_count_for_value("", &hw_counter), Some(2));
+    let mut empty_string_points: Vec<_> = index.get_iterator("", &hw_counter).collect();
+    empty_string_points.sort();
+    assert_eq!(empty_string_points, vec![0, 3]);
+
+    assert_eq!(index.get_indexed_points(), 3);
+    assert_eq!(index.get_values_count(), 4);
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_verify_integrity(#[case] index_type: IndexType) {
+    let data: Vec<Vec<IntPayloadType>> = (0..100).map(|i| vec![i % 7, 10 + i % 11]).collect();
+
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    save_map_index::<IntPayloadType>(&data, temp_dir.path(), index_type, |v| (*v).into());
+    let mut index = load_map_index::<IntPayloadType>(&data, temp_dir.path(), index_type);
+    index.verify_integrity().unwrap();
+
+    for idx in [0, 17, 50, 99] {
+        index.remove_point(idx).unwrap();
+    }
+    index.verify_integrity().unwrap();
+}
+
+#[test]
+fn test_verify_integrity_detects_wrong_pairs_count() {
+    let data: Vec<Vec<IntPayloadType>> = (0..100).map(|i| vec![i % 7]).collect();
+
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    save_map_index::<IntPayloadType>(&data, temp_dir.path(), IndexType::Mmap, |v| (*v).into());
+
+    let config_path = temp_dir.path().join(CONFIG_PATH);
+    let mut config: Value = serde_json::from_slice(&fs_err::read(&config_path).unwrap()).unwrap();
+    config["total_key_value_pairs"] = Value::from(101);
+    fs_err::write(&config_path, serde_json::to_vec(&config).unwrap()).unwrap();
+
+    let index = load_map_index::<IntPayloadType>(&data, temp_dir.path(), IndexType::Mmap);
+    let err = index.verify_integrity().unwrap_err();
+    assert!(err.to_string().contains("total_key_value_pairs"), "{err}");
+}
+
+#[test]
+fn test_merge() {
+    let data_a: Vec<Vec<EcoString>> = (0..50)
+        .map(|i| match i % 4 {
+            0 => vec![],
+            1 => vec![format!("v{}", i % 5).into()],
+            _ => vec![format!("v{}", i % 5).into(), format!("w{}", i % 3).into()],
+        })
+        .collect();
+    let data_b: Vec<Vec<EcoString>> = (0..30)
+        .map(|i| vec![format!("v{}", i % 7).into()])
+        .collect();
+    let removed_from_a = [1, 6, 13];
+
+    let open_universal = |data: &[Vec<EcoString>], path: &Path| {
+        save_map_index::<str>(data, path, IndexType::Mmap, |v| v.to_string().into());
+        UniversalMapIndex::<str>::open(&MmapFs, path, false, &empty_deleted())
+            .unwrap()
+            .unwrap()
+    };
+
+    let dir_a = Builder::new().prefix("store_dir").tempdir().unwrap();
+    let mut index_a = open_universal(&data_a, dir_a.path());
+    for idx in removed_from_a {
+        index_a.remove_point(idx).unwrap();
+    }
+    let dir_b = Builder::new().prefix("store_dir").tempdir().unwrap();
+    let index_b = open_universal(&data_b, dir_b.path());
+
+    let merged_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    let offset_b = data_a.len() as PointOffsetType;
+    let merged = UniversalMapIndex::merge(
+        &MmapFs,
+        &[(&index_b, offset_b), (&index_a, 0)],
+        merged_dir.path(),
+        false,
+    )
+    .unwrap();
+
+    let mut union = data_a.clone();
+    for idx in removed_from_a {
+        union[idx as usize].clear();
+    }
+    union.extend(data_b.iter().cloned());
+    let expected_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    let expected = open_universal(&union, expected_dir.path());
+
+    let count_per_value = |index: &UniversalMapIndex<str>| {
+        let mut counts = HashMap::default();
+        index
+            .for_each_count_per_value(None, |value, count| {
+                counts.insert(value.to_string(), count);
+                Ok(())
+            })
+            .unwrap();
+        counts
+    };
+    assert_eq!(count_per_value(&merged), count_per_value(&expected));
+
+    let hw_counter = HardwareCounterCell::new();
+    let mut values_count = 0;
+    expected
+        .for_each_value(|value| {
+            let mut merged_points: Vec<_> = merged.get_iterator(value, &hw_counter).collect();
+            let mut expected_points: Vec<_> = expected.get_iterator(value, &hw_counter).collect();
+            merged_points.sort();
+            expected_points.sort();
+            assert_eq!(merged_points, expected_points, "points of {value}");
+            values_count += 1;
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(values_count, merged.get_unique_values_count());
+    assert_eq!(merged.get_indexed_points(), expected.get_indexed_points());
+    assert_eq!(merged.get_values_count(), expected.get_values_count());
+
+    let overlapping = UniversalMapIndex::merge(
+        &MmapFs,
+        &[(&index_a, 0), (&index_b, offset_b - 1)],
+        Builder::new().prefix("store_dir").tempdir().unwrap().path(),
+        false,
+    );
+    assert!(overlapping.is_err());
+}
+
+/// Add `data` to `builder` in descending point id order.
+fn build_reversed<B: FieldIndexBuilderTrait>(mut builder: B, data: &[Vec<IntPayloadType>]) {
+    let hw_counter = HardwareCounterCell::new();
+    builder.init().unwrap();
+    for (idx, values) in data.iter().enumerate().rev() {
+        let values: Vec<Value> = values.iter().map(|i| (*i).into()).collect();
+        let values: Vec<_> = values.iter().collect();
+        builder
+            .add_point(idx as PointOffsetType, &values, &hw_counter)
+            .unwrap();
+    }
+    builder.finalize().unwrap();
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_get_iterator_sorted(#[case] index_type: IndexType) {
+    // Points divisible by 10 have value 2 twice
+    let data: Vec<Vec<IntPayloadType>> = (0..200)
+        .map(|i| {
+            [(i % 2 == 0, 2), (i % 3 == 0, 3), (i % 5 == 0, 2)]
+                .into_iter()
+                .filter_map(|(has_value, value)| has_value.then_some(value))
+                .collect()
+        })
+        .collect();
+
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    match index_type {
+        IndexType::MutableGridstore => build_reversed(
+            MapIndex::<IntPayloadType>::builder_gridstore(temp_dir.path().to_path_buf()),
+            &data,
+        ),
+        IndexType::Mmap | IndexType::RamMmap => build_reversed(
+            MapIndex::<IntPayloadType>::builder_mmap(temp_dir.path(), false, &empty_deleted()),
+            &data,
+        ),
+    }
+    let index = load_map_index::<IntPayloadType>(&data, temp_dir.path(), index_type);
+
+    let hw_counter = HardwareCounterCell::new();
+    for value in [2, 3] {
+        let sorted: Vec<_> = index.get_iterator_sorted(&value, &hw_counter).collect();
+        assert!(sorted.is_sorted_by(|a, b| a < b), "{sorted:?}");
+
+        let mut unsorted: Vec<_> = index.get_iterator(&value, &hw_counter).collect();
+        unsorted.sort();
+        unsorted.dedup();
+        assert_eq!(sorted, unsorted);
+    }
+
+    let intersection: Vec<_> = index
+        .get_iterator_sorted(&2, &hw_counter)
+        .merge_join_by(index.get_iterator_sorted(&3, &hw_counter), Ord::cmp)
+        .filter_map(|pair| pair.both())
+        .map(|(idx, _)| idx)
+        .collect();
+    let expected: Vec<PointOffsetType> = (0..200)
+        .filter(|i| (i % 2 == 0 || i % 5 == 0) && i % 3 == 0)
+        .collect();
+    assert_eq!(intersection, expected);
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore, false)]
+#[case(IndexType::Mmap, true)]
+#[case(IndexType::RamMmap, true)]
+fn test_needs_compaction(#[case] index_type: IndexType, #[case] compacts: bool) {
+    let data: Vec<Vec<IntPayloadType>> = (0..100).map(|i| vec![i % 7, 10 + i % 11]).collect();
+
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    save_map_index::<IntPayloadType>(&data, temp_dir.path(), index_type, |v| (*v).into());
+    let mut index = load_map_index::<IntPayloadType>(&data, temp_dir.path(), index_type);
+    assert!(!index.needs_compaction(0.0));
+
+    for idx in 0..10 {
+        index.remove_point(idx).unwrap();
+    }
+    assert!(!index.needs_compaction(0.1));
+
+    index.remove_point(10).unwrap();
+    assert_eq!(index.needs_compaction(0.1), compacts);
+}
+
+/// Points without values are marked deleted at build time, but they are no garbage to compact.
+#[rstest]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_needs_compaction_sparse_field(#[case] index_type: IndexType) {
+    let data: Vec<Vec<IntPayloadType>> = (0..100)
+        .map(|i| if i % 10 == 0 { vec![i] } else { vec![] })
+        .collect();
+
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    save_map_index::<IntPayloadType>(&data, temp_dir.path(), index_type, |v| (*v).into());
+    let mut index = open_map_index::<IntPayloadType>(temp_dir.path(), index_type);
+    assert!(!index.needs_compaction(0.0));
+
+    // Removing a point without values frees nothing
+    index.remove_point(1).unwrap();
+    assert!(!index.needs_compaction(0.0));
+
+    // One of ten points with values
+    index.remove_point(10).unwrap();
+    assert!(index.needs_compaction(0.05));
+    assert!(!index.needs_compaction(0.1));
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_has_value_bitmap(#[case] index_type: IndexType) {
+    let data: Vec<Vec<IntPayloadType>> = (0..50)
+        .map(|i| if i % 5 == 0 { vec![] } else { vec![i, i % 3] })
+        .collect();
+
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    save_map_index::<IntPayloadType>(&data, temp_dir.path(), index_type, |v| (*v).into());
+    let mut index = open_map_index::<IntPayloadType>(temp_dir.path(), index_type);
+    for idx in [1, 7, 10, 49] {
+        index.remove_point(idx).unwrap();
+    }
+
+    let bitmap = index.has_value_bitmap();
+    for idx in 0..data.len() {
+        let expected = !data[idx].is_empty() && ![1, 7, 10, 49].contains(&idx);
+        let has_value = bitmap.get(idx).is_some_and(|bit| *bit);
+        assert_eq!(has_value, expected, "point {idx}");
+    }
+}
+
+#[test]
+fn test_dump_jsonl() {
+    let data: Vec<Vec<EcoString>> = (0..40)
+        .map(|i| match i % 5 {
+            0 => vec![],
+            _ => vec![format!("v{}", i % 3).into(), format!("w{}", i % 4).into()],
+        })
+        .collect();
+    // All points of `w0` are removed, so it's not dumped
+    let removed = (0..40).filter(|i| i % 4 == 0).collect_vec();
+
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    save_map_index::<str>(&data, temp_dir.path(), IndexType::Mmap, |v| {
+        v.to_string().into()
+    });
+    let mut index =
+        UniversalMapIndex::<str>::open(&MmapFs, temp_dir.path(), false, &empty_deleted())
+            .unwrap()
+            .unwrap();
+    for &idx in &removed {
+        index.remove_point(idx).unwrap();
+    }
+
+    let hw_counter = HardwareCounterCell::new();
+    let mut output = Vec::new();
+    index.dump_jsonl(&mut output, &hw_counter).unwrap();
+
+    let dumped: HashMap<String, Vec<PointOffsetType>> = output
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let object: Value = serde_json::from_slice(line).unwrap();
+            let points = serde_json::from_value(object["points"].clone()).unwrap();
+            (object["value"].as_str().unwrap().to_string(), points)
+        })
+        .collect();
+
+    let mut expected = HashMap::default();
+    index
+        .for_each_value(|value| {
+            let points = index.get_iterator(value, &hw_counter).collect_vec();
+            if !points.is_empty() {
+                expected.insert(value.to_string(), points);
+            }
+            Ok(())
+        })
+        .unwrap();
+    assert!(!expected.contains_key("w0"));
+    assert_eq!(dumped.len(), 6);
+
+    for (value, mut points) in dumped {
+        assert!(points.iter().all(|idx| !removed.contains(idx)));
+        let mut expected_points = expected.remove(&value).unwrap();
+        points.sort_unstable();
+        expected_points.sort_unstable();
+        assert_eq!(points, expected_points, "value {value}");
+    }
+    assert!(expected.is_empty());
+}
+
+#[test]
+fn test_open_read_only() {
+    let data: Vec<Vec<IntPayloadType>> = (0..50).map(|i| vec![i % 5]).collect();
+
+    let temp_dir = Builder::new().prefix("store_dir").tempdir().unwrap();
+    save_map_index::<IntPayloadType>(&data, temp_dir.path(), IndexType::Mmap, |v| (*v).into());
+
+    type RoFs = <ReadOnly<MmapFile> as UniversalRead>::Fs;
+    let fs = RoFs::from_context(Default::default()).unwrap();
+    let mut index = UniversalMapIndex::<IntPayloadType, ReadOnly<MmapFile>>::open_read_only(
+        &fs,
+        temp_dir.path(),
+        false,
+        &deleted_with(&[3]),
+    )
+    .unwrap()
+    .unwrap();
+
+    let hw_counter = HardwareCounterCell::new();
+    assert_eq!(
+        index.get_iterator(&3, &hw_counter).sorted().collect_vec(),
+        vec![8, 13, 18, 23, 28, 33, 38, 43, 48],
+    );
+    assert_eq!(index.get_indexed_points(), 49);
+
+    let err = index.remove_point(8).unwrap_err();
+    assert!(err.to_string().contains("read-only"), "{err}");
+    assert!(index.remove_points(&[8, 13]).is_err());
+    assert_eq!(index.get_count_for_value(&3, &hw_counter), Some(9));
+
+    // Deletions of the id tracker still apply
+    index.live_reload(&fs, &[8], &[], &hw_counter).unwrap();
+    assert_eq!(index.get_count_for_value(&3, &hw_counter), Some(8));
+}
@@ -3,7 +3,8 @@ use std::ops::BitOrAssign;
 use std::path::{Path, PathBuf};
 
 use ahash::HashMap;
-use common::bitvec::{BitSlice, BitSliceExt};
+use common::bitvec::{BitSlice, BitSliceExt, BitVec};
+use common::counter::conditioned_counter::ConditionedCounter;
 use common::fs::{atomic_save_json, clear_disk_cache};
 use common::mmap::{AdviceSetting, create_and_ensure_length};
 use common::persisted_hashmap::{Key, UniversalHashMap, serialize_hashmap};
@@ -14,10 +15,12 @@ use common::universal_io::{
     read_json_via,
 };
 use fs_err as fs;
+use itertools::Itertools;
 
-use super::super::MapIndexKey;
+use super::super::{Collation, MapIndexKey};
 use super::{
-    CONFIG_PATH, DELETED_PATH, HASHMAP_PATH, Storage, UniversalMapIndex, UniversalMapIndexConfig,
+    CONFIG_PATH, DELETED_PATH, HASHMAP_PATH, SORTED_KEYS_PATH, SortedKeys, Storage,
+    UniversalMapIndex, UniversalMapIndexConfig,
 };
 use crate::common::Flusher;
 use crate::common::operation_error::{OperationError, OperationResult};
@@ -61,13 +64,30 @@ where
         )?;
         let point_to_values = StoredPointToValues::open(fs, path, do_populate)?;
 
+        let sorted_keys = if N::KEEPS_SORTED_KEYS {
+            match SortedKeys::open(path, do_populate)? {
+                Some(sorted_keys) => sorted_keys,
+                // Indexes built before the sorted keys were stored sort them on open
+                None => {
+                    let mut keys = Vec::new();
+                    value_to_points.for_each_key(|key| {
+                        keys.push(key.to_string());
+                        Ok(())
+                    })?;
+                    SortedKeys::in_ram(keys.iter().map(String::as_str), config.collation)
+                }
+            }
+        } else {
+            SortedKeys::empty()
+        };
+
         let mut deleted = deleted_points.to_owned();
 
         let deleted_payload_mmap = StoredBitSlice::<S>::open(
             fs,
             &deleted_path,
             OpenOptions {
-                writeable: true,
+                writeable: false,
                 need_sequential: false,
                 populate: Populate::from(do_populate),
                 advice: AdviceSetting::Global,
@@ -86,6 +106,8 @@ where
         deleted.bitor_assign(deleted_payloads_bitslice.as_ref());
 
         let deleted_count = deleted.count_ones();
+        let empty_payloads = deleted_payloads_bitslice.as_ref();
+        let empty_count = empty_payloads[..empty_payloads.len().min(deleted.len())].count_ones();
 
         Ok(Some(Self {
             path: path.to_path_buf(),
@@ -95,22 +117,83 @@ where
                 deleted,
             },
             deleted_count,
+            empty_count,
             total_key_value_pairs: config.total_key_value_pairs,
             is_on_disk,
+            collation: config.collation,
+            sorted_point_ids: config.sorted_point_ids,
+            read_only: false,
+            sorted_keys,
+        }))
+    }
+
+    /// Open the index at `path` for reading only, for read replicas and inspection tools.
+    ///
+    /// [`Self::remove_point`] and [`Self::remove_points`] fail on the returned index.
+    /// Deletions of the id tracker are still applied by [`Self::live_reload`].
+    pub fn open_read_only(
+        fs: &S::Fs,
+        path: &Path,
+        is_on_disk: bool,
+        deleted_points: &BitSlice,
+    ) -> OperationResult<Option<Self>> {
+        let index = Self::open(fs, path, is_on_disk, deleted_points)?;
+        Ok(index.map(|index| Self {
+            read_only: true,
+            ..index
         }))
     }
 
     /// Marks `idx` as deleted in the in-memory deletion bitvec.
     ///
     /// Not persisted: on reopen, deletions must be re-supplied via the
-    /// `deleted_points` argument to [`Self::open`].
-    pub fn remove_point(&mut self, idx: PointOffsetType) {
+    /// `deleted_points` argument to [`Self::open`]. Fails if the index was opened with
+    /// [`Self::open_read_only`].
+    pub fn remove_point(&mut self, idx: PointOffsetType) -> OperationResult<()> {
+        self.check_writable()?;
+        self.mark_deleted(idx);
+        Ok(())
+    }
+
+    /// Batch version of [`Self::remove_point`]. `ids` must be ascending.
+    pub fn remove_points(&mut self, ids: &[PointOffsetType]) -> OperationResult<()> {
+        self.check_writable()?;
+        self.mark_deleted_many(ids);
+        Ok(())
+    }
+
+    fn check_writable(&self) -> OperationResult<()> {
+        if self.read_only {
+            return Err(OperationError::service_error(format!(
+                "Map index at {} is opened read-only",
+                self.path.display(),
+            )));
+        }
+        Ok(())
+    }
+
+    /// Mark `idx` as deleted, regardless of the open mode.
+    pub(super) fn mark_deleted(&mut self, idx: PointOffsetType) {
         let idx = idx as usize;
         if idx < self.storage.deleted.len() && !self.storage.deleted.get_bit(idx).unwrap_or(true) {
             self.storage.deleted.set(idx, true);
             self.deleted_count += 1;
         }
     }
+
+    /// Batch version of [`Self::mark_deleted`]. `ids` must be ascending.
+    fn mark_deleted_many(&mut self, ids: &[PointOffsetType]) {
+        debug_assert!(ids.is_sorted(), "ids must be ascending");
+        let deleted = &mut self.storage.deleted;
+        let mut newly_deleted = 0;
+        for &idx in ids {
+            let idx = idx as usize;
+            if idx < deleted.len() && !deleted.replace(idx, true) {
+                newly_deleted += 1;
+            }
+        }
+        self.deleted_count += newly_deleted;
+    }
 }
 
 impl<N, S> UniversalMapIndex<N, S>
@@ -126,7 +209,36 @@ where
         values_to_points: HashMap<<N as MapIndexKey>::Owned, Vec<PointOffsetType>>,
         is_on_disk: bool,
         deleted_points: &BitSlice,
+        collation: Collation,
+    ) -> OperationResult<Self> {
+        Self::build_with_progress(
+            fs,
+            path,
+            point_to_values,
+            values_to_points,
+            is_on_disk,
+            deleted_points,
+            collation,
+            &mut |_| {},
+        )
+    }
+
+    /// Same as [`Self::build`], calling `progress` with the fraction complete after writing each
+    /// structure. The last call reports `1.0`, once the built index is opened.
+    #[allow(clippy::too_many_arguments)]
+    pub fn build_with_progress(
+        fs: &S::Fs,
+        path: &Path,
+        point_to_values: Vec<Vec<<N as MapIndexKey>::Owned>>,
+        mut values_to_points: HashMap<<N as MapIndexKey>::Owned, Vec<PointOffsetType>>,
+        is_on_disk: bool,
+        deleted_points: &BitSlice,
+        collation: Collation,
+        progress: &mut dyn FnMut(f32),
     ) -> OperationResult<Self> {
+        const STEPS: u8 = 5;
+        let mut report = |step: u8| progress(f32::from(step) / f32::from(STEPS));
+
         fs::create_dir_all(path)?;
 
         let hashmap_path = path.join(HASHMAP_PATH);
@@ -137,8 +249,15 @@ where
             &config_path,
             &UniversalMapIndexConfig {
                 total_key_value_pairs: point_to_values.iter().map(|v| v.len()).sum(),
+                collation,
+                sorted_point_ids: true,
             },
         )?;
+        report(1);
+
+        for ids in values_to_points.values_mut() {
+            ids.sort_unstable();
+        }
 
         serialize_hashmap(
             &hashmap_path,
@@ -146,6 +265,14 @@ where
                 .iter()
                 .map(|(value, ids)| (value.borrow(), ids.iter().copied())),
         )?;
+        if N::KEEPS_SORTED_KEYS {
+            let keys = values_to_points
+                .keys()
+                .map(|key| Borrow::<N>::borrow(key).to_string())
+                .collect_vec();
+            SortedKeys::save(path, keys.iter().map(String::as_str), collation)?;
+        }
+        report(2);
 
         StoredPointToValues::<N, MmapFile>::from_iter(
             &MmapFs,
@@ -157,6 +284,7 @@ where
                 )
             }),
         )?;
+        report(3);
 
         {
             let deleted_flags_count = point_to_values.len();
@@ -187,10 +315,102 @@ where
             )?;
             deleted.flusher()()?;
         }
+        report(4);
 
-        Self::open(fs, path, is_on_disk, deleted_points)?.ok_or_else(|| {
+        let index = Self::open(fs, path, is_on_disk, deleted_points)?.ok_or_else(|| {
             OperationError::service_error("Failed to open UniversalMapIndex after building it")
-        })
+        })?;
+        report(STEPS);
+        Ok(index)
+    }
+
+    /// Build an index at `path` from the live points of `sources`, shifting the point ids of each
+    /// source by its paired offset.
+    ///
+    /// Values are taken from the source indexes, which is much cheaper than rebuilding from
+    /// payloads. Deleted points of the sources are left out. The shifted id ranges of the sources
+    /// must not overlap, and all sources must use the same collation.
+    pub fn merge(
+        fs: &S::Fs,
+        sources: &[(&Self, PointOffsetType)],
+        path: &Path,
+        is_on_disk: bool,
+    ) -> OperationResult<Self> {
+        let collation = sources
+            .first()
+            .map_or_else(Collation::default, |(source, _)| source.collation);
+        if let Some((source, _)) = sources
+            .iter()
+            .find(|(source, _)| source.collation != collation)
+        {
+            return Err(OperationError::validation_error(format!(
+                "Can't merge map indexes with different collations: {collation:?} and {:?}",
+                source.collation,
+            )));
+        }
+
+        let mut shifted_ranges = sources
+            .iter()
+            .map(|(source, offset)| {
+                let start = *offset as usize;
+                start..start + source.storage.point_to_values.len()
+            })
+            .collect::<Vec<_>>();
+        shifted_ranges.sort_unstable_by_key(|range| range.start);
+        if let Some((previous, next)) = shifted_ranges
+            .iter()
+            .tuple_windows()
+            .find(|(previous, next)| next.start < previous.end)
+        {
+            return Err(OperationError::validation_error(format!(
+                "Can't merge map indexes with overlapping point ids {previous:?} and {next:?}",
+            )));
+        }
+        let points_count = shifted_ranges.last().map_or(0, |range| range.end);
+        if points_count > PointOffsetType::MAX as usize {
+            return Err(OperationError::validation_error(format!(
+                "Can't merge map indexes into {points_count} points",
+            )));
+        }
+
+        let mut point_to_values = vec![Vec::new(); points_count];
+        for (source, offset) in sources {
+            for idx in 0..source.storage.point_to_values.len() as PointOffsetType {
+                if source.storage.deleted.get_bit(idx as usize) != Some(false) {
+                    continue;
+                }
+                let Some(values) = source
+                    .storage
+                    .point_to_values
+                    .values_iter(idx, ConditionedCounter::never())?
+                else {
+                    continue;
+                };
+                point_to_values[(offset + idx) as usize] = values
+                    .map(|value| MapIndexKey::to_owned(value.as_ref()))
+                    .collect();
+            }
+        }
+
+        let mut values_to_points = HashMap::<_, Vec<PointOffsetType>>::default();
+        for (idx, values) in point_to_values.iter().enumerate() {
+            for value in values {
+                values_to_points
+                    .entry(value.clone())
+                    .or_default()
+                    .push(idx as PointOffsetType);
+            }
+        }
+
+        Self::build(
+            fs,
+            path,
+            point_to_values,
+            values_to_points,
+            is_on_disk,
+            &BitVec::new(),
+            collation,
+        )
     }
 
     /// No-op flusher: the on-disk state is build-time only. See the type-level
@@ -217,6 +437,9 @@ where
             self.path.join(DELETED_PATH),
             self.path.join(CONFIG_PATH),
         ];
+        if self.sorted_keys.is_mapped() {
+            files.push(self.path.join(SORTED_KEYS_PATH));
+        }
         files.extend(self.storage.point_to_values.files());
         files
     }
@@ -227,6 +450,9 @@ where
             self.path.join(DELETED_PATH),
             self.path.join(CONFIG_PATH),
         ];
+        if self.sorted_keys.is_mapped() {
+            files.push(self.path.join(SORTED_KEYS_PATH));
+        }
         files.extend(self.storage.point_to_values.immutable_files());
         files
     }
@@ -236,6 +462,7 @@ where
     pub fn populate(&self) -> OperationResult<()> {
         self.storage.value_to_points.populate()?;
         self.storage.point_to_values.populate()?;
+        self.sorted_keys.populate();
         Ok(())
     }
 
@@ -245,8 +472,13 @@ where
             path,
             storage,
             deleted_count: _,
+            empty_count: _,
             total_key_value_pairs: _,
             is_on_disk: _,
+            collation: _,
+            sorted_point_ids: _,
+            read_only: _,
+            sorted_keys,
         } = self;
         let Storage {
             value_to_points,
@@ -256,10 +488,13 @@ where
         value_to_points.clear_ram_cache()?;
         clear_disk_cache(&path.join(DELETED_PATH))?;
         point_to_values.clear_cache()?;
+        if sorted_keys.is_mapped() {
+            clear_disk_cache(&path.join(SORTED_KEYS_PATH))?;
+        }
         Ok(())
     }
 
     pub(crate) fn ram_usage_bytes(&self) -> usize {
-        self.storage.ram_usage_bytes()
+        self.storage.ram_usage_bytes() + self.sorted_keys.ram_usage_bytes()
     }
 }
@@ -23,7 +23,7 @@ where
         // this UniversalMapIndex is not mutable.
         // We only patch in-memory deleted bitslice representation.
         for deleted_point in deleted_points {
-            self.remove_point(*deleted_point)
+            self.mark_deleted(*deleted_point);
         }
 
         Ok(())
@@ -6,16 +6,19 @@ use common::types::PointOffsetType;
 use common::universal_io::{MmapFile, UniversalRead};
 use serde::{Deserialize, Serialize};
 
-use super::MapIndexKey;
+use self::sorted_keys::SortedKeys;
+use super::{Collation, MapIndexKey};
 use crate::index::field_index::stored_point_to_values::StoredPointToValues;
 
 mod lifecycle;
 mod live_reload;
 mod read_ops;
+mod sorted_keys;
 
 pub(super) const DELETED_PATH: &str = "deleted.bin";
 pub(super) const HASHMAP_PATH: &str = "values_to_points.bin";
 pub(super) const CONFIG_PATH: &str = "mmap_field_index_config.json";
+pub(super) const SORTED_KEYS_PATH: &str = "sorted_keys.bin";
 
 /// Immutable map index served directly from a [`UniversalRead`] storage backend.
 ///
@@ -23,10 +26,12 @@ pub(super) const CONFIG_PATH: &str = "mmap_field_index_config.json";
 /// implementation works — e.g. io_uring or disk-cache wrappers.
 ///
 /// On-disk state (`values_to_points.bin`, `deleted.bin`, `point_to_values.*`,
-/// `mmap_field_index_config.json`) is written once during [`Self::build`] and
+/// `sorted_keys.bin`, `mmap_field_index_config.json`) is written once during [`Self::build`] and
 /// not mutated afterwards: `deleted.bin` records only the points whose payload
 /// was empty at build time.
 ///
+/// All files are mapped read-only after the build.
+///
 /// Runtime deletions live in the in-memory `Storage::deleted` bitvec. They are
 /// **not persisted** — [`Self::flusher`] is a no-op and [`Self::remove_point`]
 /// only updates the in-memory bitvec. Callers must re-supply the authoritative
@@ -36,8 +41,19 @@ pub struct UniversalMapIndex<N: MapIndexKey + Key + ?Sized, S: UniversalRead = M
     pub(super) path: PathBuf,
     pub(super) storage: Storage<N, S>,
     pub(super) deleted_count: usize,
+    /// Points stored without values, marked deleted at build time. Included in
+    /// `deleted_count`, but they take up no space in the sorted storage.
+    pub(super) empty_count: usize,
     pub(super) total_key_value_pairs: usize,
     pub(super) is_on_disk: bool,
+    /// Ordering of string keys, see [`Collation`]
+    pub(super) collation: Collation,
+    /// Whether the point ids of each value are stored in ascending order
+    pub(super) sorted_point_ids: bool,
+    /// Opened with [`Self::open_read_only`], rejects [`Self::remove_point`]
+    pub(super) read_only: bool,
+    /// All keys ordered by the collation. Empty unless [`MapIndexKey::KEEPS_SORTED_KEYS`].
+    sorted_keys: SortedKeys,
 }
 
 pub(super) struct Storage<N: MapIndexKey + Key + ?Sized, S: UniversalRead = MmapFile> {
@@ -65,4 +81,9 @@ impl<N: MapIndexKey + Key + ?Sized, S: UniversalRead> Storage<N, S> {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(super) struct UniversalMapIndexConfig {
     pub(super) total_key_value_pairs: usize,
+    #[serde(default)]
+    pub(super) collation: Collation,
+    /// Absent for indexes built before point ids were sorted
+    #[serde(default)]
+    pub(super) sorted_point_ids: bool,
 }
@@ -1,7 +1,9 @@
 use std::borrow::Cow;
+use std::io::Write;
 use std::iter;
+use std::ops::Bound;
 
-use common::bitvec::BitSliceExt;
+use common::bitvec::{BitSliceExt, BitVec};
 use common::counter::conditioned_counter::ConditionedCounter;
 use common::counter::hardware_counter::HardwareCounterCell;
 use common::counter::iterator_hw_measurement::HwMeasurementIteratorExt;
@@ -9,14 +11,21 @@ use common::persisted_hashmap::{Key, READ_ENTRY_OVERHEAD};
 use common::types::PointOffsetType;
 use common::universal_io::UniversalRead;
 use itertools::Itertools;
+use regex::RegexBuilder;
+use serde::Serialize;
 
+use super::super::integrity::{check_count, integrity_error};
 use super::super::read_ops::MapIndexRead;
-use super::super::{IdIter, MapIndexKey};
+use super::super::{Collation, IdIter, MapIndexKey};
 use super::UniversalMapIndex;
-use crate::common::operation_error::OperationResult;
+use crate::common::operation_error::{OperationError, OperationResult};
 use crate::index::field_index::stored_point_to_values::ValuesIter;
 use crate::index::payload_config::StorageType;
 
+/// Upper bound on the compiled size of a keyword regex, rejects patterns which blow up during
+/// compilation.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
 impl<N: MapIndexKey + Key + ?Sized, S: UniversalRead> MapIndexRead<N> for UniversalMapIndex<N, S> {
     fn check_values_any(
         &self,
@@ -100,6 +109,12 @@ impl<N: MapIndexKey + Key + ?Sized, S: UniversalRead> MapIndexRead<N> for Univer
     fn get_count_for_value(&self, value: &N, hw_counter: &HardwareCounterCell) -> Option<usize> {
         let hw_counter = self.make_conditioned_counter(hw_counter);
 
+        // Points stored without values are marked deleted too, but no stored count includes them
+        if self.deleted_count > self.empty_count {
+            // Stored counts include deleted points, so we have to read the ids themselves
+            return self.get_live_count_for_value(value, &hw_counter);
+        }
+
         // Since `value_to_points.get` doesn't actually force read from disk for all values
         // we need to only account for the overhead of hashmap lookup
         hw_counter
@@ -125,37 +140,22 @@ impl<N: MapIndexKey + Key + ?Sized, S: UniversalRead> MapIndexRead<N> for Univer
     }
 
     fn get_iterator(&self, value: &N, hw_counter: &HardwareCounterCell) -> IdIter<'_> {
-        let hw_counter = self.make_conditioned_counter(hw_counter);
-
-        match self.storage.value_to_points.unbatched_get(value) {
-            Ok(Some(values)) => {
-                // We're iterating over the whole (mmapped) slice
-                hw_counter
-                    .payload_index_io_read_counter()
-                    .incr_delta(size_of_val(values.as_slice()) + READ_ENTRY_OVERHEAD);
-
-                Box::new(
-                    values.into_iter().filter(|idx| {
-                        !self.storage.deleted.get_bit(*idx as usize).unwrap_or(false)
-                    }),
-                )
-            }
-            Ok(None) => {
-                hw_counter
-                    .payload_index_io_read_counter()
-                    .incr_delta(READ_ENTRY_OVERHEAD);
+        Box::new(
+            self.get_iterator_including_deleted(value, hw_counter)
+                .filter(|idx| !self.storage.deleted.get_bit(*idx as usize).unwrap_or(false)),
+        )
+    }
 
-                Box::new(iter::empty())
-            }
-            Err(err) => {
-                debug_assert!(
-                    false,
-                    "Error while getting iterator for value {value:?}: {err:?}",
-                );
-                log::error!("Error while getting iterator for value {value:?}: {err:?}");
-                Box::new(iter::empty())
-            }
+    /// Streams the stored ids if the index was built with sorted ids, sorts them otherwise.
+    fn get_iterator_sorted(&self, value: &N, hw_counter: &HardwareCounterCell) -> IdIter<'_> {
+        let ids = self.get_iterator(value, hw_counter);
+        if !self.sorted_point_ids {
+            let mut ids = ids.collect_vec();
+            ids.sort_unstable();
+            ids.dedup();
+            return Box::new(ids.into_iter());
         }
+        Box::new(ids.dedup())
     }
 
     fn for_each_value(&self, f: impl FnMut(&N) -> OperationResult<()>) -> OperationResult<()> {
@@ -226,6 +226,66 @@ impl<N: MapIndexKey + Key + ?Sized, S: UniversalRead> MapIndexRead<N> for Univer
 }
 
 impl<N: MapIndexKey + Key + ?Sized, S: UniversalRead> UniversalMapIndex<N, S> {
+    /// Check the invariants of the stored index, see [`MapIndex::verify_integrity`].
+    ///
+    /// Reads all backing files.
+    ///
+    /// [`MapIndex::verify_integrity`]: super::super::MapIndex::verify_integrity
+    pub fn verify_integrity(&self) -> OperationResult<()> {
+        let points_count = self.storage.point_to_values.len();
+        if self.storage.deleted.len() != points_count {
+            return Err(integrity_error(format_args!(
+                "{} deleted flags for {points_count} points",
+                self.storage.deleted.len(),
+            )));
+        }
+        check_count(
+            "deleted count",
+            self.deleted_count,
+            self.storage.deleted.count_ones(),
+        )?;
+
+        let mut point_values_count = 0;
+        for idx in 0..points_count as PointOffsetType {
+            point_values_count += self
+                .storage
+                .point_to_values
+                .get_values_count(idx)?
+                .unwrap_or(0);
+        }
+        check_count(
+            "total_key_value_pairs",
+            self.total_key_value_pairs,
+            point_values_count,
+        )?;
+
+        let mut pairs_count = 0;
+        self.storage.value_to_points.for_each_entry(|value, ids| {
+            if let Some(idx) = ids.iter().find(|&&idx| idx as usize >= points_count) {
+                return Err(integrity_error(format_args!(
+                    "value {value} refers to point {idx}, but there are only {points_count} points",
+                )));
+            }
+            pairs_count += ids.len();
+            Ok(())
+        })?;
+        check_count(
+            "total_key_value_pairs",
+            self.total_key_value_pairs,
+            pairs_count,
+        )?;
+
+        if N::KEEPS_SORTED_KEYS {
+            check_count(
+                "sorted keys",
+                self.sorted_keys.len(),
+                self.storage.value_to_points.keys_count(),
+            )?;
+            self.sorted_keys.verify(self.collation)?;
+        }
+        Ok(())
+    }
+
     pub fn for_points_values(
         &self,
         mut points: impl Iterator<Item = PointOffsetType>,
@@ -245,6 +305,51 @@ impl<N: MapIndexKey + Key + ?Sized, S: UniversalRead> UniversalMapIndex<N, S> {
         })
     }
 
+    /// Count points with `value`, skipping the ones marked in `deleted`.
+    fn get_live_count_for_value(
+        &self,
+        value: &N,
+        hw_counter: &ConditionedCounter,
+    ) -> Option<usize> {
+        self.stored_point_ids(value, hw_counter).map(|ids| {
+            ids.into_iter()
+                .filter(|idx| !self.storage.deleted.get_bit(*idx as usize).unwrap_or(false))
+                .count()
+        })
+    }
+
+    /// Stored ids of the points with `value`, deleted points included. `None` if no point was
+    /// indexed with `value`.
+    fn stored_point_ids(
+        &self,
+        value: &N,
+        hw_counter: &ConditionedCounter,
+    ) -> Option<Vec<PointOffsetType>> {
+        match self.storage.value_to_points.unbatched_get(value) {
+            Ok(Some(values)) => {
+                // We're reading the whole (mmapped) slice
+                hw_counter
+                    .payload_index_io_read_counter()
+                    .incr_delta(size_of_val(values.as_slice()) + READ_ENTRY_OVERHEAD);
+                Some(values)
+            }
+            Ok(None) => {
+                hw_counter
+                    .payload_index_io_read_counter()
+                    .incr_delta(READ_ENTRY_OVERHEAD);
+                None
+            }
+            Err(err) => {
+                debug_assert!(
+                    false,
+                    "Error while reading points of value {value:?}: {err:?}",
+                );
+                log::error!("Error while reading points of value {value:?}: {err:?}");
+                None
+            }
+        }
+    }
+
     pub(super) fn make_conditioned_counter<'a>(
         &self,
         hw_counter: &'a HardwareCounterCell,
@@ -252,7 +357,229 @@ impl<N: MapIndexKey + Key + ?Sized, S: UniversalRead> UniversalMapIndex<N, S> {
         ConditionedCounter::new(self.is_on_disk, hw_counter)
     }
 
+    /// Same as [`MapIndexRead::get_iterator`], but also yields the points deleted since the
+    /// index was built.
+    ///
+    /// Removing a point only sets its `deleted` flag, its ids stay stored for the value until
+    /// the index is rebuilt.
+    pub fn get_iterator_including_deleted(
+        &self,
+        value: &N,
+        hw_counter: &HardwareCounterCell,
+    ) -> IdIter<'_> {
+        let hw_counter = self.make_conditioned_counter(hw_counter);
+        Box::new(
+            self.stored_point_ids(value, &hw_counter)
+                .into_iter()
+                .flatten(),
+        )
+    }
+
     pub fn is_on_disk(&self) -> bool {
         self.is_on_disk
     }
+
+    /// See [`MapIndex::has_value_bitmap`].
+    ///
+    /// [`MapIndex::has_value_bitmap`]: super::super::MapIndex::has_value_bitmap
+    pub fn has_value_bitmap(&self) -> BitVec {
+        // Points without values are marked deleted at build time
+        !self.storage.deleted.clone()
+    }
+
+    /// Share of stored points with values that are deleted, but still take up space until a
+    /// rebuild. Points stored without values are left out, they take up no space.
+    pub fn deleted_ratio(&self) -> f64 {
+        let points_with_values = self.storage.point_to_values.len() - self.empty_count;
+        if points_with_values == 0 {
+            return 0.0;
+        }
+        (self.deleted_count - self.empty_count) as f64 / points_with_values as f64
+    }
+
+    /// Write the live contents of the index to `writer` as newline-delimited JSON, one
+    /// `{"value": ..., "points": [...]}` object per value that has live points.
+    ///
+    /// Meant for debugging. Points are streamed from storage, so memory use doesn't grow with
+    /// the index. `writer` isn't buffered here, wrap it in a [`std::io::BufWriter`] if needed.
+    pub fn dump_jsonl(
+        &self,
+        mut writer: impl Write,
+        hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<()>
+    where
+        N: Serialize,
+    {
+        self.for_each_value_map(hw_counter, |value, points| {
+            let mut points = points.peekable();
+            if points.peek().is_none() {
+                return Ok(());
+            }
+
+            writer.write_all(b"{\"value\":")?;
+            serde_json::to_writer(&mut writer, value)?;
+            writer.write_all(b",\"points\":[")?;
+            for (i, idx) in points.enumerate() {
+                if i > 0 {
+                    writer.write_all(b",")?;
+                }
+                write!(writer, "{idx}")?;
+            }
+            writer.write_all(b"]}\n")?;
+            Ok(())
+        })?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<S: UniversalRead> UniversalMapIndex<str, S> {
+    pub fn collation(&self) -> Collation {
+        self.collation
+    }
+
+    /// All keys of the index, ordered by the index [`Collation`].
+    ///
+    /// Keys equal under the collation are ordered byte-wise.
+    pub fn sorted_values(&self) -> impl Iterator<Item = &str> + '_ {
+        self.sorted_keys.iter()
+    }
+
+    /// Positions in the sorted keys of the keys within the given bounds.
+    fn sorted_keys_range(&self, from: Bound<&str>, to: Bound<&str>) -> std::ops::Range<usize> {
+        let keys = &self.sorted_keys;
+        let collation = self.collation;
+        let start = match from {
+            Bound::Included(from) => {
+                keys.partition_point(0..keys.len(), |key| collation.compare(key, from).is_lt())
+            }
+            Bound::Excluded(from) => {
+                keys.partition_point(0..keys.len(), |key| collation.compare(key, from).is_le())
+            }
+            Bound::Unbounded => 0,
+        };
+        let end = match to {
+            Bound::Included(to) => {
+                keys.partition_point(0..keys.len(), |key| collation.compare(key, to).is_le())
+            }
+            Bound::Excluded(to) => {
+                keys.partition_point(0..keys.len(), |key| collation.compare(key, to).is_lt())
+            }
+            Bound::Unbounded => keys.len(),
+        };
+        start..end.max(start)
+    }
+
+    /// Number of distinct keys within the given bounds, compared with the index [`Collation`].
+    ///
+    /// Binary search over the sorted keys stored with the index, `O(log n)` in the number of
+    /// keys. Point ids are not read, so keys whose points are all deleted are counted too.
+    pub fn estimate_unique_values_in_range(
+        &self,
+        from: Bound<&str>,
+        to: Bound<&str>,
+    ) -> OperationResult<usize> {
+        Ok(self.sorted_keys_range(from, to).len())
+    }
+
+    /// Number of distinct keys starting with `prefix`, see [`Collation::starts_with`].
+    ///
+    /// Like [`Self::estimate_unique_values_in_range`], keys whose points are all deleted are
+    /// counted too. Binary search for the binary and case-insensitive collations, under which
+    /// the keys with a common prefix are adjacent, in `O(log n)`. The natural collation doesn't
+    /// keep them adjacent, so it scans all keys in `O(n)`.
+    pub fn estimate_unique_values_with_prefix(&self, prefix: &str) -> OperationResult<usize> {
+        let keys = &self.sorted_keys;
+        let collation = self.collation;
+        match collation {
+            Collation::Binary | Collation::CaseInsensitive => {
+                // Keys with the prefix never sort before the prefix itself
+                let start = keys
+                    .partition_point(0..keys.len(), |key| collation.compare(key, prefix).is_lt());
+                let end = keys
+                    .partition_point(start..keys.len(), |key| collation.starts_with(key, prefix));
+                Ok(end - start)
+            }
+            Collation::Natural => Ok(keys
+                .iter()
+                .filter(|key| collation.starts_with(key, prefix))
+                .count()),
+        }
+    }
+
+    /// Iterate over points having at least one value within the given bounds, compared with
+    /// the index [`Collation`].
+    ///
+    /// The keys within the bounds are found by binary search over the sorted keys stored with the
+    /// index, only the points of those keys are read.
+    pub fn get_iterator_range(
+        &self,
+        from: Bound<&str>,
+        to: Bound<&str>,
+        hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<IdIter<'_>> {
+        let positions = self.sorted_keys_range(from, to);
+        Ok(self.union_of_keys(
+            positions.map(|position| self.sorted_keys.get(position)),
+            hw_counter,
+        ))
+    }
+
+    /// Iterate over points having at least one value matching the regex `pattern`.
+    ///
+    /// This is a full scan over all keys of the index, every scanned key is charged to
+    /// `hw_counter`.
+    pub fn get_iterator_regex(
+        &self,
+        pattern: &str,
+        hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<IdIter<'_>> {
+        let regex = RegexBuilder::new(pattern)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .build()
+            .map_err(|err| {
+                OperationError::validation_error(format!("Invalid regex {pattern:?}: {err}"))
+            })?;
+
+        self.get_iterator_matching_keys(hw_counter, |key| regex.is_match(key))
+    }
+
+    /// Union of live points of all keys accepted by `matches`, scanning every key.
+    fn get_iterator_matching_keys(
+        &self,
+        hw_counter: &HardwareCounterCell,
+        matches: impl Fn(&str) -> bool,
+    ) -> OperationResult<IdIter<'_>> {
+        let conditioned_counter = self.make_conditioned_counter(hw_counter);
+
+        let mut matching_keys = Vec::new();
+        self.storage
+            .value_to_points
+            .for_each_key(|key: &str| -> OperationResult<()> {
+                conditioned_counter
+                    .payload_index_io_read_counter()
+                    .incr_delta(key.write_bytes());
+                if matches(key) {
+                    matching_keys.push(String::from(key));
+                }
+                Ok(())
+            })?;
+
+        Ok(self.union_of_keys(matching_keys.iter().map(String::as_str), hw_counter))
+    }
+
+    /// Sorted and deduplicated live points of all `keys`.
+    fn union_of_keys<'k>(
+        &self,
+        keys: impl Iterator<Item = &'k str>,
+        hw_counter: &HardwareCounterCell,
+    ) -> IdIter<'_> {
+        let mut points = keys
+            .flat_map(|key| self.get_iterator(key, hw_counter))
+            .collect_vec();
+        points.sort_unstable();
+        points.dedup();
+
+        Box::new(points.into_iter())
+    }
 }
@@ -0,0 +1,218 @@
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use common::mmap::{AdviceSetting, Madviseable, open_read_mmap};
+use fs_err::File;
+use itertools::{Either, Itertools};
+use memmap2::Mmap;
+
+use super::super::Collation;
+use super::super::integrity::integrity_error;
+use super::SORTED_KEYS_PATH;
+use crate::common::operation_error::OperationResult;
+
+/// Width of the key count and of the key end offsets
+const WORD_SIZE: usize = size_of::<u64>();
+
+/// Keys of a string index ordered by its [`Collation`], keys equal under the collation ordered
+/// byte-wise. Written at build time and mapped from disk, so opening the index doesn't read them.
+///
+/// Layout: the number of keys, the end offset of every key within the key bytes, all as
+/// little-endian `u64`, then the UTF-8 bytes of all keys back to back.
+pub(super) struct SortedKeys {
+    data: Either<Mmap, Vec<u8>>,
+    len: usize,
+}
+
+impl SortedKeys {
+    /// No keys, for indexes of keys without a collation.
+    pub fn empty() -> Self {
+        Self::in_ram(std::iter::empty(), Collation::default())
+    }
+
+    /// Sort `keys` by `collation` and keep them in RAM, for indexes built before the sorted keys
+    /// were written.
+    pub fn in_ram<'a>(keys: impl IntoIterator<Item = &'a str>, collation: Collation) -> Self {
+        let (data, len) = serialize(keys, collation);
+        Self {
+            data: Either::Right(data),
+            len,
+        }
+    }
+
+    /// Sort `keys` by `collation` and write them to the index at `path`.
+    pub fn save<'a>(
+        path: &Path,
+        keys: impl IntoIterator<Item = &'a str>,
+        collation: Collation,
+    ) -> OperationResult<()> {
+        let (data, _) = serialize(keys, collation);
+        let mut writer = BufWriter::new(File::create(path.join(SORTED_KEYS_PATH))?);
+        writer.write_all(&data)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Map the sorted keys of the index at `path`, `None` if it was built without them.
+    pub fn open(path: &Path, populate: bool) -> OperationResult<Option<Self>> {
+        let keys_path = path.join(SORTED_KEYS_PATH);
+        if !keys_path.exists() {
+            return Ok(None);
+        }
+        let mmap = open_read_mmap(&keys_path, AdviceSetting::Global, populate)?;
+
+        let word_count = mmap.len() / WORD_SIZE;
+        let len = read_word(&mmap, 0)
+            .filter(|&len| len < word_count)
+            .ok_or_else(|| integrity_error("sorted keys are truncated"))?;
+        let keys_start = (len + 1) * WORD_SIZE;
+        let keys_end = if len == 0 {
+            Some(0)
+        } else {
+            read_word(&mmap, len)
+        };
+        if keys_end.and_then(|end| keys_start.checked_add(end)) != Some(mmap.len()) {
+            return Err(integrity_error(format_args!(
+                "sorted keys of {} bytes don't match the size of their {len} keys",
+                mmap.len(),
+            )));
+        }
+
+        Ok(Some(Self {
+            data: Either::Left(mmap),
+            len,
+        }))
+    }
+
+    /// Whether the keys are mapped from the file written at build time.
+    pub fn is_mapped(&self) -> bool {
+        self.data.is_left()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Key at `position`. Corrupted keys read as empty, [`Self::verify`] reports them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `position` is out of bounds.
+    pub fn get(&self, position: usize) -> &str {
+        assert!(position < self.len, "sorted key {position} out of bounds");
+        self.key_bytes(position)
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> + '_ {
+        (0..self.len).map(|position| self.get(position))
+    }
+
+    /// Like [`slice::partition_point`], over the keys at `positions`.
+    pub fn partition_point(
+        &self,
+        positions: Range<usize>,
+        mut pred: impl FnMut(&str) -> bool,
+    ) -> usize {
+        let Range { mut start, mut end } = positions;
+        while start < end {
+            let middle = start + (end - start) / 2;
+            if pred(self.get(middle)) {
+                start = middle + 1;
+            } else {
+                end = middle;
+            }
+        }
+        start
+    }
+
+    /// Check that the keys are valid UTF-8 and ordered by `collation`.
+    pub fn verify(&self, collation: Collation) -> OperationResult<()> {
+        for position in 0..self.len {
+            let bytes = self.key_bytes(position).ok_or_else(|| {
+                integrity_error(format_args!("sorted key {position} is out of bounds"))
+            })?;
+            if std::str::from_utf8(bytes).is_err() {
+                return Err(integrity_error(format_args!(
+                    "sorted key {position} is not valid UTF-8",
+                )));
+            }
+        }
+        if let Some((previous, next)) = self
+            .iter()
+            .tuple_windows()
+            .find(|(previous, next)| compare(collation, previous, next).is_gt())
+        {
+            return Err(integrity_error(format_args!(
+                "sorted keys are not sorted: {previous:?} before {next:?}",
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn populate(&self) {
+        if let Either::Left(mmap) = &self.data {
+            mmap.populate();
+        }
+    }
+
+    pub fn ram_usage_bytes(&self) -> usize {
+        match &self.data {
+            Either::Left(_) => 0,
+            Either::Right(data) => data.capacity(),
+        }
+    }
+
+    /// Bytes of the key at `position`, `None` if its offsets are corrupted.
+    fn key_bytes(&self, position: usize) -> Option<&[u8]> {
+        let start = match position {
+            0 => 0,
+            _ => self.word(position),
+        };
+        let end = self.word(position + 1);
+        let keys_start = (self.len + 1) * WORD_SIZE;
+        self.data
+            .get(keys_start.checked_add(start)?..keys_start.checked_add(end)?)
+    }
+
+    /// Word at `index` of the header, `0` if out of bounds.
+    fn word(&self, index: usize) -> usize {
+        read_word(&self.data, index).unwrap_or_default()
+    }
+}
+
+/// Order of keys in [`SortedKeys`].
+fn compare(collation: Collation, a: &str, b: &str) -> std::cmp::Ordering {
+    collation.compare(a, b).then_with(|| a.cmp(b))
+}
+
+/// `u64` at word `index` of `data`, `None` if out of bounds or not addressable.
+fn read_word(data: &[u8], index: usize) -> Option<usize> {
+    let start = index.checked_mul(WORD_SIZE)?;
+    let bytes = data.get(start..start.checked_add(WORD_SIZE)?)?;
+    usize::try_from(u64::from_le_bytes(bytes.try_into().ok()?)).ok()
+}
+
+/// Layout of `keys` sorted by `collation`, and the number of keys.
+fn serialize<'a>(
+    keys: impl IntoIterator<Item = &'a str>,
+    collation: Collation,
+) -> (Vec<u8>, usize) {
+    let mut keys = keys.into_iter().collect_vec();
+    keys.sort_unstable_by(|a, b| compare(collation, a, b));
+
+    let keys_size: usize = keys.iter().map(|key| key.len()).sum();
+    let mut data = Vec::with_capacity((keys.len() + 1) * WORD_SIZE + keys_size);
+    data.extend_from_slice(&(keys.len() as u64).to_le_bytes());
+    let mut end = 0;
+    for key in &keys {
+        end += key.len();
+        data.extend_from_slice(&(end as u64).to_le_bytes());
+    }
+    for key in &keys {
+        data.extend_from_slice(key.as_bytes());
+    }
+    (data, keys.len())
+}
@@ -97,6 +97,19 @@ impl CardinalityEstimation {
         self
     }
 
+    /// Whether the estimation is the actual count, i.e. `min == max`.
+    pub const fn is_exact(&self) -> bool {
+        self.min == self.max
+    }
+
+    /// Width of the `[min, max]` band relative to `exp`. Zero for exact estimations.
+    pub fn relative_error(&self) -> f64 {
+        if self.is_exact() {
+            return 0.0;
+        }
+        (self.max - self.min) as f64 / self.exp.max(1) as f64
+    }
+
     #[cfg(test)]
     pub const fn equals_min_exp_max(&self, other: &Self) -> bool {
         self.min == other.min && self.exp == other.exp && self.max == other.max
@@ -145,6 +158,21 @@ impl CardinalityEstimation {
     }
 }
 
+/// Whether a field index can serve a condition, see [`PayloadFieldIndexRead::explain_condition`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConditionServeability {
+    /// Served by the index, with the estimated number of matching points
+    Served(CardinalityEstimation),
+    /// Not supported by the index, the condition falls back to a full scan
+    NotServed { reason: String },
+}
+
+impl ConditionServeability {
+    pub fn is_served(&self) -> bool {
+        matches!(self, ConditionServeability::Served(_))
+    }
+}
+
 pub trait EstimationMerge: Iterator<Item = CardinalityEstimation> {
     fn merge_independent(self) -> CardinalityEstimation
     where
@@ -100,6 +100,7 @@ pub trait NullIndexRead {
             points_count,
             points_values_count: points_count,
             histogram_bucket_size: None,
+            cardinality_estimation_error: None,
             index_type: self.telemetry_index_type(),
         }
     }
@@ -0,0 +1,159 @@
+//! Export of numeric index contents as an Arrow [`RecordBatch`], for offline analytics with
+//! tools like DuckDB or Polars.
+//!
+//! The batch has two non-nullable columns: `point_id` as UInt32 and `value` in the Arrow type of
+//! the index key, see [`ArrowValue`].
+
+use std::sync::Arc;
+
+use arrow::array::{
+    Array, ArrayRef, AsArray, FixedSizeBinaryArray, Float64Array, Int64Array, UInt32Array,
+};
+use arrow::buffer::Buffer;
+use arrow::datatypes::{DataType, Field, Float64Type, Int64Type, Schema, UInt32Type};
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+use common::types::PointOffsetType;
+use gridstore::Blob;
+
+use super::Encodable;
+use super::storage::NumericIndexInner;
+use crate::common::operation_error::{OperationError, OperationResult};
+use crate::index::field_index::numeric_point::Numericable;
+use crate::index::field_index::stored_point_to_values::StoredValue;
+
+const POINT_ID_COLUMN: &str = "point_id";
+const VALUE_COLUMN: &str = "value";
+
+/// Width of UUIDs in a `FixedSizeBinary` column
+const UUID_WIDTH: i32 = 16;
+
+/// Index key types with an Arrow counterpart for the `value` column.
+pub trait ArrowValue: Numericable {
+    const DATA_TYPE: DataType;
+
+    /// Column of `values`, of [`Self::DATA_TYPE`].
+    fn into_array(values: Vec<Self>) -> Result<ArrayRef, ArrowError>;
+
+    /// Inverse of [`Self::into_array`], `None` if `array` is not a column of [`Self::DATA_TYPE`].
+    fn from_array(array: &dyn Array) -> Option<Vec<Self>>;
+}
+
+impl ArrowValue for i64 {
+    const DATA_TYPE: DataType = DataType::Int64;
+
+    fn into_array(values: Vec<Self>) -> Result<ArrayRef, ArrowError> {
+        Ok(Arc::new(Int64Array::from(values)))
+    }
+
+    fn from_array(array: &dyn Array) -> Option<Vec<Self>> {
+        Some(array.as_primitive_opt::<Int64Type>()?.values().to_vec())
+    }
+}
+
+impl ArrowValue for f64 {
+    const DATA_TYPE: DataType = DataType::Float64;
+
+    fn into_array(values: Vec<Self>) -> Result<ArrayRef, ArrowError> {
+        Ok(Arc::new(Float64Array::from(values)))
+    }
+
+    fn from_array(array: &dyn Array) -> Option<Vec<Self>> {
+        Some(array.as_primitive_opt::<Float64Type>()?.values().to_vec())
+    }
+}
+
+/// UUIDs, as 16 big-endian bytes: the byte order of `Uuid::as_bytes`, so readers see the usual
+/// UUID bytes.
+impl ArrowValue for u128 {
+    const DATA_TYPE: DataType = DataType::FixedSizeBinary(UUID_WIDTH);
+
+    fn into_array(values: Vec<Self>) -> Result<ArrayRef, ArrowError> {
+        let bytes: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect();
+        let array = FixedSizeBinaryArray::try_new(UUID_WIDTH, Buffer::from_vec(bytes), None)?;
+        Ok(Arc::new(array))
+    }
+
+    fn from_array(array: &dyn Array) -> Option<Vec<Self>> {
+        let array = array.as_fixed_size_binary_opt()?;
+        if array.value_length() != UUID_WIDTH {
+            return None;
+        }
+        array
+            .iter()
+            .map(|bytes| Some(u128::from_be_bytes(bytes?.try_into().ok()?)))
+            .collect()
+    }
+}
+
+impl<T> NumericIndexInner<T>
+where
+    T: Encodable + ArrowValue + StoredValue + Send + Sync + Default,
+    Vec<T>: Blob,
+{
+    /// Export the `(point_id, value)` pairs of all live points as an Arrow record batch, in
+    /// ascending point order and, for points with several values, in stored value order.
+    ///
+    /// Builds the whole batch in memory: twelve to twenty bytes per pair.
+    pub fn export_arrow(&self) -> OperationResult<RecordBatch> {
+        let mut point_ids = Vec::new();
+        let mut values = Vec::new();
+        self.for_each_point_values(|point_id, point_values| {
+            for &value in point_values {
+                point_ids.push(point_id);
+                values.push(value);
+            }
+        })?;
+
+        let schema = Schema::new(vec![
+            Field::new(POINT_ID_COLUMN, DataType::UInt32, false),
+            Field::new(VALUE_COLUMN, T::DATA_TYPE, false),
+        ]);
+        let columns = vec![
+            Arc::new(UInt32Array::from(point_ids)) as ArrayRef,
+            T::into_array(values).map_err(arrow_error)?,
+        ];
+        RecordBatch::try_new(Arc::new(schema), columns).map_err(arrow_error)
+    }
+}
+
+fn arrow_error(err: ArrowError) -> OperationError {
+    OperationError::service_error(format!("Failed to build Arrow record batch: {err}"))
+}
+
+/// Read back the `(point_id, value)` pairs of a batch made by
+/// [`NumericIndexInner::export_arrow`] for an index of key type `T`.
+pub fn pairs_from_record_batch<T: ArrowValue>(
+    batch: &RecordBatch,
+) -> OperationResult<Vec<(PointOffsetType, T)>> {
+    let column = |name: &str| {
+        let column = batch.column_by_name(name).ok_or_else(|| {
+            OperationError::validation_error(format!("Arrow record batch has no {name:?} column"))
+        })?;
+        if column.null_count() > 0 {
+            return Err(OperationError::validation_error(format!(
+                "Arrow column {name:?} has nulls",
+            )));
+        }
+        Ok(column)
+    };
+    let wrong_type = |name: &str, expected: &DataType, column: &ArrayRef| {
+        OperationError::validation_error(format!(
+            "Arrow column {name:?} is of type {}, expected {expected}",
+            column.data_type(),
+        ))
+    };
+
+    let point_ids = column(POINT_ID_COLUMN)?;
+    let point_ids = point_ids
+        .as_primitive_opt::<UInt32Type>()
+        .ok_or_else(|| wrong_type(POINT_ID_COLUMN, &DataType::UInt32, point_ids))?;
+    let values = column(VALUE_COLUMN)?;
+    let values = T::from_array(values.as_ref())
+        .ok_or_else(|| wrong_type(VALUE_COLUMN, &T::DATA_TYPE, values))?;
+
+    Ok(point_ids.values().iter().copied().zip(values).collect())
+}
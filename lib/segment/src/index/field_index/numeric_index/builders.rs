@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::marker::PhantomData;
 use std::path::PathBuf;
 
@@ -10,19 +11,61 @@ use serde_json::Value;
 
 use super::mutable_numeric_index::InMemoryNumericIndex;
 use super::storage::NumericIndexInner;
-use super::universal_numeric_index::UniversalNumericIndex;
-use super::{Encodable, NumericIndex, NumericIndexIntoInnerValue};
+use super::universal_numeric_index::{
+    BuildOptions, ExternalNumericIndexBuild, UniversalNumericIndex,
+};
+use super::{Encodable, KeyOrder, NumericIndex, NumericIndexIntoInnerValue, OutOfRangePolicy};
 use crate::common::operation_error::{OperationError, OperationResult};
 use crate::index::field_index::numeric_point::Numericable;
 use crate::index::field_index::stored_point_to_values::StoredValue;
 use crate::index::field_index::{FieldIndexBuilderTrait, ValueIndexer};
+use crate::json_path::JsonPath;
 
-pub struct NumericIndexBuilder<T: Encodable + Numericable + StoredValue + Send + Sync + Default, P>(
-    NumericIndex<T, P>,
-)
+/// Selects the values to index from a payload value of the indexed field, e.g. the leaves of a
+/// nested field. See [`json_path_extractor`].
+///
+/// Extractors are only applied by the builders, they are not stored in the built index. Values
+/// added to a mutable index later on, with [`NumericIndex::add_point`], must be extracted by the
+/// caller.
+pub type ValueExtractor = Box<dyn for<'a> Fn(&'a Value) -> Vec<&'a Value> + Send + Sync>;
+
+/// [`ValueExtractor`] selecting the values at `path` inside the indexed objects. Arrays are
+/// searched element-wise.
+pub fn json_path_extractor(path: JsonPath) -> ValueExtractor {
+    Box::new(move |value| json_path_values(&path, value))
+}
+
+fn json_path_values<'a>(path: &JsonPath, value: &'a Value) -> Vec<&'a Value> {
+    match value {
+        Value::Object(map) => path.value_get(map).into_iter().collect(),
+        Value::Array(values) => values
+            .iter()
+            .flat_map(|value| json_path_values(path, value))
+            .collect(),
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => Vec::new(),
+    }
+}
+
+pub struct NumericIndexBuilder<T: Encodable + Numericable + StoredValue + Send + Sync + Default, P>
 where
     NumericIndex<T, P>: ValueIndexer<ValueType = P>,
-    Vec<T>: Blob;
+    Vec<T>: Blob,
+{
+    index: NumericIndex<T, P>,
+    value_extractor: Option<ValueExtractor>,
+}
+
+impl<T: Encodable + Numericable + StoredValue + Send + Sync + Default, P> NumericIndexBuilder<T, P>
+where
+    NumericIndex<T, P>: ValueIndexer<ValueType = P>,
+    Vec<T>: Blob,
+{
+    /// See [`NumericIndexMmapBuilder::with_value_extractor`].
+    pub fn with_value_extractor(mut self, extractor: ValueExtractor) -> Self {
+        self.value_extractor = Some(extractor);
+        self
+    }
+}
 
 impl<T: Encodable + Numericable + StoredValue + Send + Sync + Default, P> FieldIndexBuilderTrait
     for NumericIndexBuilder<T, P>
@@ -33,7 +76,7 @@ where
     type FieldIndexType = NumericIndex<T, P>;
 
     fn init(&mut self) -> OperationResult<()> {
-        match &mut self.0.inner {
+        match &mut self.index.inner {
             NumericIndexInner::Mutable(index) => index.clear(),
             NumericIndexInner::Immutable(_) => unreachable!(),
             NumericIndexInner::Mmap(_) => unreachable!(),
@@ -46,12 +89,13 @@ where
         payload: &[&Value],
         hw_counter: &HardwareCounterCell,
     ) -> OperationResult<()> {
-        self.0.add_point(id, payload, hw_counter)
+        let payload = extract_payload(self.value_extractor.as_ref(), payload);
+        self.index.add_point(id, &payload, hw_counter)
     }
 
     fn finalize(self) -> OperationResult<Self::FieldIndexType> {
-        self.0.inner.flusher()()?;
-        Ok(self.0)
+        self.index.inner.flusher()()?;
+        Ok(self.index)
     }
 }
 
@@ -65,6 +109,13 @@ where
     in_memory_index: InMemoryNumericIndex<T>,
     is_on_disk: bool,
     deleted_points: BitVec,
+    out_of_range_policy: OutOfRangePolicy,
+    sort_values: bool,
+    strict_point_ids: bool,
+    key_order: KeyOrder,
+    /// Points added so far, to detect repeated ids
+    added_points: BitVec,
+    value_extractor: Option<ValueExtractor>,
     _phantom: PhantomData<P>,
 }
 
@@ -80,9 +131,55 @@ where
             in_memory_index: InMemoryNumericIndex::default(),
             is_on_disk,
             deleted_points,
+            out_of_range_policy: OutOfRangePolicy::default(),
+            sort_values: false,
+            strict_point_ids: false,
+            key_order: KeyOrder::default(),
+            added_points: BitVec::new(),
+            value_extractor: None,
             _phantom: PhantomData,
         }
     }
+
+    pub fn with_out_of_range_policy(mut self, policy: OutOfRangePolicy) -> Self {
+        self.out_of_range_policy = policy;
+        self
+    }
+
+    /// Sort and deduplicate the values of each point before indexing them.
+    ///
+    /// The built files then only depend on the set of values of each point,
+    /// not on their order in the payload, which makes rebuilds reproducible.
+    pub fn with_sorted_values(mut self, sort_values: bool) -> Self {
+        self.sort_values = sort_values;
+        self
+    }
+
+    /// Reject adding the same point twice in one build.
+    ///
+    /// Otherwise the last values of a point win, and a warning is logged for
+    /// the values that are dropped.
+    pub fn with_strict_point_ids(mut self, strict_point_ids: bool) -> Self {
+        self.strict_point_ids = strict_point_ids;
+        self
+    }
+
+    /// Store the keys in `order`, so that reads in that direction scan the index forward.
+    ///
+    /// The order is persisted, reads in both directions return the same results either way.
+    pub fn with_key_order(mut self, order: KeyOrder) -> Self {
+        self.key_order = order;
+        self
+    }
+
+    /// Index the values selected by `extractor` from the added payload, instead of the payload
+    /// itself.
+    ///
+    /// Only applies while building, see [`ValueExtractor`].
+    pub fn with_value_extractor(mut self, extractor: ValueExtractor) -> Self {
+        self.value_extractor = Some(extractor);
+        self
+    }
 }
 
 impl<T: Encodable + Numericable + StoredValue + Send + Sync + Default, P> FieldIndexBuilderTrait
@@ -103,35 +200,166 @@ where
         payload: &[&Value],
         hw_counter: &HardwareCounterCell,
     ) -> OperationResult<()> {
+        let payload = extract_payload(self.value_extractor.as_ref(), payload);
+        NumericIndex::<T, P>::check_representable(id, &payload, self.out_of_range_policy)?;
+
+        if self.added_points.len() <= id as usize {
+            self.added_points.resize(id as usize + 1, false);
+        }
+        if self.added_points.replace(id as usize, true) {
+            if self.strict_point_ids {
+                return Err(OperationError::service_error(format!(
+                    "Point {id} is added twice while building numeric index",
+                )));
+            }
+            log::warn!(
+                "Point {id} is added twice while building numeric index, dropping its previous values",
+            );
+        }
+
         self.in_memory_index.remove_point(id);
-        let mut flatten_values: Vec<_> = vec![];
-        for value in payload {
-            let payload_values = <NumericIndex<T, P> as ValueIndexer>::get_values(value);
-            flatten_values.extend(payload_values);
+        let mut flatten_values = payload_values::<T, P>(&payload);
+
+        if self.sort_values {
+            flatten_values.sort_unstable_by(|a, b| a.cmp_encoded(b));
+            flatten_values.dedup_by(|a, b| a.cmp_encoded(b).is_eq());
         }
-        let flatten_values = flatten_values
-            .into_iter()
-            .map(NumericIndex::into_inner_value)
-            .collect();
 
         hw_counter
             .payload_index_io_write_counter()
-            .incr_delta(size_of_val(&flatten_values));
+            .incr_delta(size_of_val(flatten_values.as_slice()));
 
         self.in_memory_index.add_many_to_list(id, flatten_values);
         Ok(())
     }
 
     fn finalize(self) -> OperationResult<Self::FieldIndexType> {
-        let inner = UniversalNumericIndex::build(
+        let inner = UniversalNumericIndex::build_with_options(
             &MmapFs,
             self.in_memory_index,
             &self.path,
             self.is_on_disk,
             &self.deleted_points,
+            BuildOptions {
+                order: self.key_order,
+                datetime_precision: None,
+            },
         )?;
         Ok(NumericIndex {
             inner: NumericIndexInner::Mmap(inner),
+            out_of_range_policy: self.out_of_range_policy,
+            cardinality_feedback: Default::default(),
+            _phantom: PhantomData,
+        })
+    }
+}
+
+/// Builds a [`NumericIndexInner::Mmap`] index with bounded memory usage, by spilling sorted runs
+/// to disk. See [`ExternalNumericIndexBuild`].
+///
+/// Points must be added in ascending id order.
+pub struct NumericIndexExternalMmapBuilder<T, P>
+where
+    T: Encodable + Numericable + StoredValue + Send + Sync + Default,
+    NumericIndex<T, P>: ValueIndexer<ValueType = P> + NumericIndexIntoInnerValue<T, P>,
+    Vec<T>: Blob,
+{
+    path: PathBuf,
+    memory_budget_bytes: usize,
+    build: Option<ExternalNumericIndexBuild<T>>,
+    is_on_disk: bool,
+    deleted_points: BitVec,
+    out_of_range_policy: OutOfRangePolicy,
+    value_extractor: Option<ValueExtractor>,
+    _phantom: PhantomData<P>,
+}
+
+impl<T, P> NumericIndexExternalMmapBuilder<T, P>
+where
+    T: Encodable + Numericable + StoredValue + Send + Sync + Default,
+    NumericIndex<T, P>: ValueIndexer<ValueType = P> + NumericIndexIntoInnerValue<T, P>,
+    Vec<T>: Blob,
+{
+    pub(super) fn new(
+        path: PathBuf,
+        is_on_disk: bool,
+        deleted_points: BitVec,
+        memory_budget_bytes: usize,
+    ) -> Self {
+        Self {
+            path,
+            memory_budget_bytes,
+            build: None,
+            is_on_disk,
+            deleted_points,
+            out_of_range_policy: OutOfRangePolicy::default(),
+            value_extractor: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn with_out_of_range_policy(mut self, policy: OutOfRangePolicy) -> Self {
+        self.out_of_range_policy = policy;
+        self
+    }
+
+    /// See [`NumericIndexMmapBuilder::with_value_extractor`].
+    pub fn with_value_extractor(mut self, extractor: ValueExtractor) -> Self {
+        self.value_extractor = Some(extractor);
+        self
+    }
+}
+
+impl<T: Encodable + Numericable + StoredValue + Send + Sync + Default, P> FieldIndexBuilderTrait
+    for NumericIndexExternalMmapBuilder<T, P>
+where
+    NumericIndex<T, P>: ValueIndexer<ValueType = P> + NumericIndexIntoInnerValue<T, P>,
+    Vec<T>: Blob,
+{
+    type FieldIndexType = NumericIndex<T, P>;
+
+    fn init(&mut self) -> OperationResult<()> {
+        self.build = Some(ExternalNumericIndexBuild::new(
+            &self.path,
+            self.memory_budget_bytes,
+        )?);
+        Ok(())
+    }
+
+    fn add_point(
+        &mut self,
+        id: PointOffsetType,
+        payload: &[&Value],
+        hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<()> {
+        let Some(build) = &mut self.build else {
+            return Err(OperationError::service_error(
+                "NumericIndexExternalMmapBuilder: index must be initialized before adding points",
+            ));
+        };
+
+        let payload = extract_payload(self.value_extractor.as_ref(), payload);
+        NumericIndex::<T, P>::check_representable(id, &payload, self.out_of_range_policy)?;
+        let values = payload_values::<T, P>(&payload);
+
+        hw_counter
+            .payload_index_io_write_counter()
+            .incr_delta(size_of_val(values.as_slice()));
+
+        build.add_point(id, &values)
+    }
+
+    fn finalize(self) -> OperationResult<Self::FieldIndexType> {
+        let Some(build) = self.build else {
+            return Err(OperationError::service_error(
+                "NumericIndexExternalMmapBuilder: index must be initialized to finalize",
+            ));
+        };
+        let inner = build.finish(&self.path, self.is_on_disk, &self.deleted_points)?;
+        Ok(NumericIndex {
+            inner: NumericIndexInner::Mmap(inner),
+            out_of_range_policy: self.out_of_range_policy,
+            cardinality_feedback: Default::default(),
             _phantom: PhantomData,
         })
     }
@@ -146,6 +374,8 @@ pub struct NumericIndexGridstoreBuilder<
 {
     dir: PathBuf,
     index: Option<NumericIndex<T, P>>,
+    out_of_range_policy: OutOfRangePolicy,
+    value_extractor: Option<ValueExtractor>,
 }
 
 impl<T: Encodable + Numericable + StoredValue + Send + Sync + Default, P>
@@ -155,7 +385,23 @@ where
     Vec<T>: Blob,
 {
     pub(super) fn new(dir: PathBuf) -> Self {
-        Self { dir, index: None }
+        Self {
+            dir,
+            index: None,
+            out_of_range_policy: OutOfRangePolicy::default(),
+            value_extractor: None,
+        }
+    }
+
+    pub fn with_out_of_range_policy(mut self, policy: OutOfRangePolicy) -> Self {
+        self.out_of_range_policy = policy;
+        self
+    }
+
+    /// See [`NumericIndexMmapBuilder::with_value_extractor`].
+    pub fn with_value_extractor(mut self, extractor: ValueExtractor) -> Self {
+        self.value_extractor = Some(extractor);
+        self
     }
 }
 
@@ -172,11 +418,11 @@ where
             self.index.is_none(),
             "index must be initialized exactly once",
         );
-        self.index.replace(
-            NumericIndex::new_gridstore(self.dir.clone(), true)?
-                // unwrap safety: cannot fail because create_if_missing is true
-                .unwrap(),
-        );
+        let mut index = NumericIndex::new_gridstore(self.dir.clone(), true)?
+            // unwrap safety: cannot fail because create_if_missing is true
+            .unwrap();
+        index.set_out_of_range_policy(self.out_of_range_policy);
+        self.index.replace(index);
         Ok(())
     }
 
@@ -191,7 +437,8 @@ where
                 "NumericIndexGridstoreBuilder: index must be initialized before adding points",
             ));
         };
-        index.add_point(id, payload, hw_counter)
+        let payload = extract_payload(self.value_extractor.as_ref(), payload);
+        index.add_point(id, &payload, hw_counter)
     }
 
     fn finalize(mut self) -> OperationResult<Self::FieldIndexType> {
@@ -204,3 +451,28 @@ where
         Ok(index)
     }
 }
+
+/// Values selected by `extractor` from all `payload` entries, or `payload` itself without one.
+fn extract_payload<'p, 'v>(
+    extractor: Option<&ValueExtractor>,
+    payload: &'p [&'v Value],
+) -> Cow<'p, [&'v Value]> {
+    match extractor {
+        Some(extractor) => Cow::Owned(payload.iter().flat_map(|value| extractor(*value)).collect()),
+        None => Cow::Borrowed(payload),
+    }
+}
+
+/// Values of all `payload` entries, converted to the representation stored in the index.
+fn payload_values<T, P>(payload: &[&Value]) -> Vec<T>
+where
+    T: Encodable + Numericable + StoredValue + Send + Sync + Default,
+    NumericIndex<T, P>: ValueIndexer<ValueType = P> + NumericIndexIntoInnerValue<T, P>,
+    Vec<T>: Blob,
+{
+    payload
+        .iter()
+        .flat_map(|value| <NumericIndex<T, P> as ValueIndexer>::get_values(value))
+        .map(NumericIndex::into_inner_value)
+        .collect()
+}
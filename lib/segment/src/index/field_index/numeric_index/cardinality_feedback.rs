@@ -0,0 +1,34 @@
+//! Realized cardinalities reported back to the index, to detect stale histograms.
+
+use parking_lot::Mutex;
+
+/// Running error of cardinality estimations against the counts queries actually matched.
+///
+/// The error of one estimation is the absolute `log2` ratio of the realized and estimated counts,
+/// so being off by a factor of 2 in either direction counts as 1.
+#[derive(Default)]
+pub(super) struct CardinalityFeedback {
+    stats: Mutex<FeedbackStats>,
+}
+
+#[derive(Default)]
+struct FeedbackStats {
+    samples: usize,
+    log_error_sum: f64,
+}
+
+impl CardinalityFeedback {
+    pub fn record(&self, estimated: usize, actual: usize) {
+        // Shift by one, so empty estimates and results don't diverge to infinity
+        let ratio = (actual as f64 + 1.0) / (estimated as f64 + 1.0);
+        let mut stats = self.stats.lock();
+        stats.samples += 1;
+        stats.log_error_sum += ratio.log2().abs();
+    }
+
+    /// Mean error of the recorded estimations, `None` if nothing was recorded.
+    pub fn mean_error(&self) -> Option<f64> {
+        let stats = self.stats.lock();
+        (stats.samples > 0).then(|| stats.log_error_sum / stats.samples as f64)
+    }
+}
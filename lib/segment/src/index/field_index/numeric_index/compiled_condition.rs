@@ -0,0 +1,61 @@
+//! [`CompiledNumericCondition`]: a field condition converted to numeric index keys once, to
+//! query the indexes of many segments without parsing it again for each of them.
+
+use std::str::FromStr;
+
+use uuid::Uuid;
+
+use super::query::{match_any_keys, typed_range};
+use crate::index::field_index::numeric_point::Numericable;
+use crate::types::{
+    AnyVariants, FieldCondition, Match, MatchAny, MatchValue, Range, ValueVariants,
+};
+
+/// A `match`/`range` field condition in the key type `T` of a numeric index.
+///
+/// Made by [`NumericIndexInner::compile_condition`], served by
+/// [`NumericIndexInner::filter_compiled`] and [`NumericIndexInner::estimate_compiled`] with the
+/// same results as the condition itself.
+///
+/// [`NumericIndexInner::compile_condition`]: super::NumericIndexInner::compile_condition
+/// [`NumericIndexInner::filter_compiled`]: super::NumericIndexInner::filter_compiled
+/// [`NumericIndexInner::estimate_compiled`]: super::NumericIndexInner::estimate_compiled
+pub struct CompiledNumericCondition<T: Numericable> {
+    pub(super) keys: CompiledKeys<T>,
+    /// Reported as primary clause of cardinality estimations
+    pub(super) condition: FieldCondition,
+}
+
+pub(super) enum CompiledKeys<T: Numericable> {
+    /// Points with this key, from a UUID `match`
+    Value(T),
+    /// Points with any of these distinct keys, ascending, from a `match any` of integers on an
+    /// integer-keyed index
+    Any(Vec<T>),
+    /// Points with a key in the range
+    Range(Range<T>),
+}
+
+impl<T: Numericable> CompiledNumericCondition<T> {
+    /// `None` if the condition is not one a numeric index can serve.
+    pub fn compile(condition: &FieldCondition) -> Option<Self> {
+        let keys = if let Some(Match::Value(MatchValue {
+            value: ValueVariants::String(keyword),
+        })) = &condition.r#match
+            && let Ok(uuid) = Uuid::from_str(keyword.as_str())
+        {
+            CompiledKeys::Value(T::from_u128(uuid.as_u128()))
+        } else if let Some(Match::Any(MatchAny {
+            any: AnyVariants::Integers(integers),
+        })) = &condition.r#match
+        {
+            CompiledKeys::Any(match_any_keys(integers)?)
+        } else {
+            CompiledKeys::Range(typed_range(condition.range.as_ref()?))
+        };
+        Some(Self {
+            keys,
+            condition: condition.clone(),
+        })
+    }
+}
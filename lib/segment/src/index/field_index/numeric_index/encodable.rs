@@ -1,10 +1,11 @@
 //! The [`Encodable`] key-format trait: on-disk key encoding/decoding and
-//! encoded-order comparison shared by every numeric-index storage variant.
+//! encoded-order comparison shared by every numeric-index storage variant,
+//! and the [`KeyOrder`] an index stores its keys in.
 
 use chrono::DateTime;
 use common::types::PointOffsetType;
-use serde::Serialize;
 use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 
 use crate::index::key_encoding::{
     decode_f64_key_ascending, decode_i64_key_ascending, decode_u128_key_ascending,
@@ -12,6 +13,28 @@ use crate::index::key_encoding::{
 };
 use crate::types::{DateTimePayloadType, FloatPayloadType, IntPayloadType};
 
+/// Order in which an index stores its `(value, point)` keys, chosen at build time.
+///
+/// Reads in either direction are served by both, but only the stored order is a forward scan.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyOrder {
+    #[default]
+    Ascending,
+    /// For fields mostly read in descending order, e.g. "newest first" timestamps
+    Descending,
+}
+
+impl KeyOrder {
+    /// Compare `a` and `b` by their position in storage of this order.
+    pub fn cmp_stored<K: Ord>(self, a: &K, b: &K) -> std::cmp::Ordering {
+        match self {
+            KeyOrder::Ascending => a.cmp(b),
+            KeyOrder::Descending => b.cmp(a),
+        }
+    }
+}
+
 pub trait Encodable: Copy + Serialize + DeserializeOwned + 'static {
     fn encode_key(&self, id: PointOffsetType) -> Vec<u8>;
 
@@ -79,13 +102,11 @@ impl Encodable for DateTimePayloadType {
 
     fn decode_key(key: &[u8]) -> (PointOffsetType, Self) {
         let (id, timestamp) = decode_i64_key_ascending(key);
-        let datetime =
-            DateTime::from_timestamp(timestamp / 1000, (timestamp % 1000) as u32 * 1_000_000)
-                .unwrap_or_else(|| {
-                    log::warn!("Failed to decode timestamp {timestamp}, fallback to UNIX_EPOCH");
-                    DateTime::UNIX_EPOCH
-                });
-        (id, datetime.into())
+        let datetime = DateTimePayloadType::from_timestamp(timestamp).unwrap_or_else(|| {
+            log::warn!("Failed to decode timestamp {timestamp}, fallback to UNIX_EPOCH");
+            DateTime::UNIX_EPOCH.into()
+        });
+        (id, datetime)
     }
 
     fn cmp_encoded(&self, other: &Self) -> std::cmp::Ordering {
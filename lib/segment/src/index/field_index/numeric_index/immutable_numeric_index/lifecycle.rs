@@ -84,15 +84,35 @@ where
     }
 
     pub(in super::super) fn remove_point(&mut self, idx: PointOffsetType) {
+        if self.remove_point_values(idx) {
+            // Update persisted storage
+            self.storage.remove_point(idx);
+        }
+    }
+
+    /// Batch version of [`Self::remove_point`]. `ids` must be ascending.
+    pub(in super::super) fn remove_points(&mut self, ids: &[PointOffsetType]) {
+        for &idx in ids {
+            self.remove_point_values(idx);
+        }
+        // Update persisted storage
+        self.storage.remove_points(ids);
+    }
+
+    /// Replace the histogram, e.g. with one rebuilt from the live pairs.
+    pub(in super::super) fn set_histogram(&mut self, histogram: Histogram<T>) {
+        self.histogram = histogram;
+        self.cached_ram_usage_bytes = self.compute_ram_usage_bytes();
+    }
+
+    /// Remove the values of point `idx` from the in-memory structures. Returns whether the point
+    /// had any values.
+    fn remove_point_values(&mut self, idx: PointOffsetType) -> bool {
+        let mut removed_count = 0;
         if let Some(removed_values) = self.point_to_values.get_values(idx) {
-            let mut removed_count = 0;
             for value in removed_values {
                 let key = Point::new(*value, idx);
                 Self::remove_from_map(&mut self.map, &mut self.histogram, &key);
-
-                // Update persisted storage
-                self.storage.remove_point(idx);
-
                 removed_count += 1;
             }
             if removed_count > 0 {
@@ -100,6 +120,7 @@ where
             }
         }
         self.point_to_values.remove_point(idx);
+        removed_count > 0
     }
 
     fn remove_from_map(
@@ -3,7 +3,9 @@ use std::ops::Bound;
 use common::bitvec::{BitSliceExt as _, BitVec};
 
 use super::Encodable;
+use super::integrity::{check_deleted_count, check_sorted_pairs, integrity_error};
 use super::universal_numeric_index::UniversalNumericIndex;
+use crate::common::operation_error::OperationResult;
 use crate::index::field_index::histogram::Histogram;
 use crate::index::field_index::immutable_point_to_values::ImmutablePointToValues;
 use crate::index::field_index::numeric_point::{Numericable, Point};
@@ -119,6 +121,30 @@ impl<T: Encodable + Numericable> NumericKeySortedVec<T> {
     }
 }
 
+impl<T: Encodable + Numericable> NumericKeySortedVec<T> {
+    /// Check that keys are sorted and refer to points below `points_count`, and that the deleted
+    /// flags match. Returns the number of live keys.
+    pub(super) fn verify_integrity(&self, points_count: usize) -> OperationResult<usize> {
+        if self.deleted.len() != self.data.len() {
+            return Err(integrity_error(format_args!(
+                "{} deleted flags for {} keys",
+                self.deleted.len(),
+                self.data.len(),
+            )));
+        }
+        check_deleted_count(&self.deleted, self.deleted_count)?;
+        check_sorted_pairs(self.data.iter().copied(), points_count)?;
+        Ok(self.len())
+    }
+}
+
+impl<T: Encodable + Numericable> NumericKeySortedVecIterator<'_, T> {
+    /// Number of remaining non-deleted keys, without iterating over them.
+    pub(super) fn live_len(&self) -> usize {
+        self.set.deleted[self.start_index..self.end_index].count_zeros()
+    }
+}
+
 impl<T: Encodable + Numericable> Iterator for NumericKeySortedVecIterator<'_, T> {
     type Item = Point<T>;
 
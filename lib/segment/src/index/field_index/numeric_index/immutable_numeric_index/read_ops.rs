@@ -1,10 +1,12 @@
 use std::ops::Bound;
 
+use common::bitvec::BitVec;
 use common::counter::hardware_counter::HardwareCounterCell;
 use common::types::PointOffsetType;
 use gridstore::Blob;
 
 use super::super::Encodable;
+use super::super::integrity::{check_pairs_count, distinct_values_count, integrity_error};
 use super::super::numeric_index_read::NumericIndexRead;
 use super::ImmutableNumericIndex;
 use crate::common::operation_error::OperationResult;
@@ -27,6 +29,67 @@ impl<T: Encodable + Numericable + StoredValue + Default> ImmutableNumericIndex<T
 
         map.ram_usage_bytes() + histogram.ram_usage_bytes() + point_to_values.ram_usage_bytes()
     }
+
+    /// Call `f` with the values of every live point that has any, in ascending point order.
+    ///
+    /// Values are borrowed straight from the in-memory container.
+    pub fn for_each_point_values(&self, mut f: impl FnMut(PointOffsetType, &[T])) {
+        let stored_points_count = self.storage.storage.point_to_values.len();
+        for idx in 0..stored_points_count as PointOffsetType {
+            match self.point_to_values.get_values_slice(idx) {
+                Some(values) if !values.is_empty() => f(idx, values),
+                Some(_) | None => {}
+            }
+        }
+    }
+
+    /// Check the invariants of the in-memory structures and of the backing storage, see
+    /// [`NumericIndexInner::verify_integrity`].
+    ///
+    /// [`NumericIndexInner::verify_integrity`]: super::super::NumericIndexInner::verify_integrity
+    pub fn verify_integrity(&self) -> OperationResult<()> {
+        let stored_points_count = self.storage.storage.point_to_values.len();
+        let live_pairs_count = self.map.verify_integrity(stored_points_count)?;
+
+        let mut values_count = 0;
+        let mut points_with_values = 0;
+        for idx in 0..stored_points_count as PointOffsetType {
+            let Some(values) = self.point_to_values.get_values(idx) else {
+                continue;
+            };
+            let count = distinct_values_count(values.copied());
+            if count > 0 {
+                points_with_values += 1;
+            }
+            values_count += count;
+        }
+        check_pairs_count(live_pairs_count, values_count)?;
+        if points_with_values != self.points_count {
+            return Err(integrity_error(format_args!(
+                "points count is {}, but {points_with_values} points have values",
+                self.points_count,
+            )));
+        }
+
+        self.storage.verify_integrity()
+    }
+
+    /// See [`NumericIndexInner::has_value_bitmap`].
+    ///
+    /// Removals of points with values are forwarded to the backing storage, which also knows
+    /// the points without values.
+    ///
+    /// [`NumericIndexInner::has_value_bitmap`]: super::super::NumericIndexInner::has_value_bitmap
+    pub fn has_value_bitmap(&self) -> BitVec {
+        self.storage.has_value_bitmap()
+    }
+
+    /// See [`UniversalNumericIndex::deleted_ratio`].
+    ///
+    /// [`UniversalNumericIndex::deleted_ratio`]: super::super::universal_numeric_index::UniversalNumericIndex::deleted_ratio
+    pub fn deleted_ratio(&self) -> f64 {
+        self.storage.deleted_ratio()
+    }
 }
 
 impl<T: Encodable + Numericable + StoredValue + Send + Sync + Default> NumericIndexRead<T>
@@ -91,6 +154,16 @@ where
         Ok(iterator.end_index - iterator.start_index)
     }
 
+    /// Boundary search, minus the deleted flags set within the boundaries.
+    fn live_values_range_size(
+        &self,
+        start_bound: Bound<Point<T>>,
+        end_bound: Bound<Point<T>>,
+        _hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<usize> {
+        Ok(self.map.values_range(start_bound, end_bound).live_len())
+    }
+
     fn get_histogram(&self) -> &Histogram<T> {
         &self.histogram
     }
@@ -0,0 +1,74 @@
+//! Helpers for `verify_integrity` of the numeric index variants.
+
+use std::fmt::Display;
+
+use common::bitvec::BitSlice;
+use itertools::Itertools;
+
+use super::Encodable;
+use crate::common::operation_error::{OperationError, OperationResult};
+use crate::index::field_index::numeric_point::{Numericable, Point};
+
+pub(super) fn integrity_error(description: impl Display) -> OperationError {
+    OperationError::inconsistent_storage(format!(
+        "Numeric index integrity check failed: {description}"
+    ))
+}
+
+/// Check that `pairs` are strictly ascending by value, then by point id, and only refer to
+/// points below `points_count`. Returns the number of pairs.
+pub(super) fn check_sorted_pairs<T: Encodable + Numericable>(
+    pairs: impl IntoIterator<Item = Point<T>>,
+    points_count: usize,
+) -> OperationResult<usize> {
+    let mut count = 0;
+    let mut previous: Option<(T, u32)> = None;
+    for Point { val, idx, .. } in pairs {
+        if idx as usize >= points_count {
+            return Err(integrity_error(format_args!(
+                "pair {count} refers to point {idx}, but there are only {points_count} points",
+            )));
+        }
+        if let Some((previous_val, previous_idx)) = previous
+            && previous_val
+                .cmp_encoded(&val)
+                .then(previous_idx.cmp(&idx))
+                .is_ge()
+        {
+            return Err(integrity_error(format_args!(
+                "pairs are not sorted, pair {count} of point {idx} is not after pair of point {previous_idx}",
+            )));
+        }
+        previous = Some((val, idx));
+        count += 1;
+    }
+    Ok(count)
+}
+
+pub(super) fn check_deleted_count(deleted: &BitSlice, deleted_count: usize) -> OperationResult<()> {
+    let marked_deleted = deleted.count_ones();
+    if marked_deleted != deleted_count {
+        return Err(integrity_error(format_args!(
+            "deleted count is {deleted_count}, but {marked_deleted} entries are marked deleted",
+        )));
+    }
+    Ok(())
+}
+
+/// Number of distinct values, which is the number of pairs a point has in the index.
+pub(super) fn distinct_values_count<T: Encodable>(values: impl IntoIterator<Item = T>) -> usize {
+    values
+        .into_iter()
+        .sorted_unstable_by(|a, b| a.cmp_encoded(b))
+        .dedup_by(|a, b| a.cmp_encoded(b).is_eq())
+        .count()
+}
+
+pub(super) fn check_pairs_count(pairs_count: usize, values_count: usize) -> OperationResult<()> {
+    if pairs_count != values_count {
+        return Err(integrity_error(format_args!(
+            "index has {pairs_count} pairs, but points have {values_count} distinct values",
+        )));
+    }
+    Ok(())
+}
@@ -12,10 +12,10 @@ use gridstore::Blob;
 use super::numeric_index_read::NumericIndexRead;
 use super::storage::NumericIndexInner;
 use super::{
-    Encodable, NumericIndex, NumericIndexGridstoreBuilder, NumericIndexIntoInnerValue,
-    NumericIndexMmapBuilder,
+    Encodable, NumericIndex, NumericIndexExternalMmapBuilder, NumericIndexGridstoreBuilder,
+    NumericIndexIntoInnerValue, NumericIndexMmapBuilder, OutOfRangePolicy,
 };
-use crate::common::operation_error::OperationResult;
+use crate::common::operation_error::{OperationError, OperationResult};
 use crate::index::field_index::numeric_point::Numericable;
 use crate::index::field_index::stored_point_to_values::StoredValue;
 use crate::index::field_index::{PayloadFieldIndex, ValueIndexer};
@@ -39,6 +39,8 @@ where
 
         Ok(index.map(|inner| Self {
             inner,
+            out_of_range_policy: OutOfRangePolicy::default(),
+            cardinality_feedback: Default::default(),
             _phantom: PhantomData,
         }))
     }
@@ -48,6 +50,8 @@ where
 
         Ok(index.map(|inner| Self {
             inner,
+            out_of_range_policy: OutOfRangePolicy::default(),
+            cardinality_feedback: Default::default(),
             _phantom: PhantomData,
         }))
     }
@@ -63,6 +67,25 @@ where
         NumericIndexMmapBuilder::new(path.to_owned(), is_on_disk, deleted_points.to_owned())
     }
 
+    /// Like [`Self::builder_mmap`], but keeps at most about `memory_budget_bytes` of indexed
+    /// values in memory while building. Points must be added in ascending id order.
+    pub fn builder_mmap_external(
+        path: &Path,
+        is_on_disk: bool,
+        deleted_points: &BitSlice,
+        memory_budget_bytes: usize,
+    ) -> NumericIndexExternalMmapBuilder<T, P>
+    where
+        Self: ValueIndexer<ValueType = P> + NumericIndexIntoInnerValue<T, P>,
+    {
+        NumericIndexExternalMmapBuilder::new(
+            path.to_owned(),
+            is_on_disk,
+            deleted_points.to_owned(),
+            memory_budget_bytes,
+        )
+    }
+
     pub fn builder_gridstore(dir: PathBuf) -> NumericIndexGridstoreBuilder<T, P>
     where
         Self: ValueIndexer<ValueType = P>,
@@ -78,6 +101,12 @@ where
         &mut self.inner
     }
 
+    /// How payload values that can't be represented by the index are handled
+    /// on insertion. See [`OutOfRangePolicy`].
+    pub fn set_out_of_range_policy(&mut self, policy: OutOfRangePolicy) {
+        self.out_of_range_policy = policy;
+    }
+
     pub fn get_mutability_type(&self) -> IndexMutability {
         match &self.inner {
             NumericIndexInner::Mutable(_) => IndexMutability::Mutable,
@@ -110,7 +139,10 @@ where
     }
 
     pub fn get_telemetry_data(&self) -> PayloadIndexTelemetry {
-        self.inner.get_telemetry_data()
+        PayloadIndexTelemetry {
+            cardinality_estimation_error: self.cardinality_feedback.mean_error(),
+            ..self.inner.get_telemetry_data()
+        }
     }
 
     pub fn values_count(&self, idx: PointOffsetType) -> usize {
@@ -129,6 +161,51 @@ where
         self.inner.is_on_disk()
     }
 
+    /// Remove a batch of points at once. `ids` must be ascending.
+    pub fn remove_points(&mut self, ids: &[PointOffsetType]) -> OperationResult<()> {
+        self.inner.remove_points(ids)
+    }
+
+    /// See [`NumericIndexInner::set_on_disk`].
+    pub fn set_on_disk(&mut self, on_disk: bool) -> OperationResult<()> {
+        self.inner.set_on_disk(on_disk)
+    }
+
+    /// Serve the index from its mmap files, see [`NumericIndexInner::into_mmap`].
+    ///
+    /// Takes the index by value to move its storage over without reopening it. On failure the
+    /// index is handed back unchanged with the error.
+    pub fn evict_to_mmap(self) -> Result<Self, (OperationError, Box<Self>)> {
+        self.convert_inner(NumericIndexInner::into_mmap)
+    }
+
+    /// Load the index into RAM, see [`NumericIndexInner::into_immutable`]. Inverse of
+    /// [`Self::evict_to_mmap`].
+    pub fn promote_to_ram(self) -> Result<Self, (OperationError, Box<Self>)> {
+        self.convert_inner(NumericIndexInner::into_immutable)
+    }
+
+    fn convert_inner(
+        mut self,
+        convert: impl FnOnce(
+            NumericIndexInner<T>,
+        ) -> Result<
+            NumericIndexInner<T>,
+            (OperationError, Box<NumericIndexInner<T>>),
+        >,
+    ) -> Result<Self, (OperationError, Box<Self>)> {
+        match convert(self.inner) {
+            Ok(inner) => {
+                self.inner = inner;
+                Ok(self)
+            }
+            Err((err, inner)) => {
+                self.inner = *inner;
+                Err((err, Box::new(self)))
+            }
+        }
+    }
+
     pub fn populate(&self) -> OperationResult<()> {
         self.inner.populate()
     }
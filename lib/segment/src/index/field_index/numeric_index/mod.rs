@@ -1,6 +1,11 @@
+#[cfg(feature = "arrow")]
+mod arrow_export;
 mod builders;
+mod cardinality_feedback;
+mod compiled_condition;
 mod encodable;
 pub mod immutable_numeric_index;
+mod integrity;
 mod lifecycle;
 pub mod mutable_numeric_index;
 mod numeric_field_index;
@@ -10,24 +15,38 @@ mod read_only;
 mod read_ops;
 mod storage;
 pub mod universal_numeric_index;
+mod validation;
 mod value_indexer;
 
 use std::marker::PhantomData;
 
-pub use builders::{NumericIndexBuilder, NumericIndexGridstoreBuilder, NumericIndexMmapBuilder};
-pub use encodable::Encodable;
+#[cfg(feature = "arrow")]
+pub use arrow_export::{ArrowValue, pairs_from_record_batch};
+pub use builders::{
+    NumericIndexBuilder, NumericIndexExternalMmapBuilder, NumericIndexGridstoreBuilder,
+    NumericIndexMmapBuilder, ValueExtractor, json_path_extractor,
+};
+use common::types::PointOffsetType;
+pub use compiled_condition::CompiledNumericCondition;
+pub use encodable::{Encodable, KeyOrder};
 use gridstore::Blob;
 pub use numeric_field_index::{
     NumericFieldIndex, NumericFieldIndexRead, NumericFieldIndexView, ReadOnlyNumericFieldIndex,
 };
 pub use numeric_index_read::NumericIndexRead;
 pub use read_only::{NumericValueToJson, ReadOnlyNumericIndex};
-pub use read_ops::StreamRange;
+pub use read_ops::{StreamCursor, StreamRange, ThreeValuedFilter};
+use serde_json::Value;
 pub use storage::NumericIndexInner;
 pub use storage::read_only::ReadOnlyNumericIndexInner;
+pub use validation::FilterError;
+pub use value_indexer::OutOfRangePolicy;
 
+use self::cardinality_feedback::CardinalityFeedback;
+use crate::common::operation_error::OperationResult;
 use crate::index::field_index::numeric_point::Numericable;
 use crate::index::field_index::stored_point_to_values::StoredValue;
+use crate::types::ValueVariants;
 
 #[cfg(test)]
 mod tests;
@@ -37,9 +56,46 @@ where
     Vec<T>: Blob,
 {
     inner: NumericIndexInner<T>,
+    out_of_range_policy: OutOfRangePolicy,
+    /// See [`NumericIndex::record_actual_cardinality`]
+    cardinality_feedback: CardinalityFeedback,
     _phantom: PhantomData<P>,
 }
 
 pub trait NumericIndexIntoInnerValue<T, P> {
     fn into_inner_value(value: P) -> T;
+
+    /// Index key to look up for an exact `match` on `value`, for payload types
+    /// whose values aren't numbers themselves, e.g. `bool` stored as 0/1.
+    fn match_value_key(_value: &ValueVariants) -> Option<T> {
+        None
+    }
+
+    /// Whether ranges on `P` are given as datetimes rather than numbers, see
+    /// [`NumericIndex::range_query`].
+    fn datetime_ranges() -> bool {
+        false
+    }
+
+    /// Whether values of `P` are integers stored as index keys as they are, so a `match any` of
+    /// integers gives the same points as the payload check. Other indexes don't serve it.
+    fn integer_keys() -> bool {
+        false
+    }
+
+    /// Whether `match` keywords on `P` must be UUIDs, see
+    /// [`NumericIndex::validate_condition`].
+    fn uuid_keywords() -> bool {
+        false
+    }
+
+    /// Apply `policy` to payload values which look like `P`, but can't be
+    /// represented by the index. Such values are never indexed.
+    fn check_representable(
+        _id: PointOffsetType,
+        _payload: &[&Value],
+        _policy: OutOfRangePolicy,
+    ) -> OperationResult<()> {
+        Ok(())
+    }
 }
@@ -1,6 +1,6 @@
 use std::collections::BTreeSet;
 use std::ops::Bound::{Excluded, Unbounded};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use common::counter::hardware_counter::HardwareCounterCell;
 use common::types::PointOffsetType;
@@ -11,6 +11,7 @@ use super::super::Encodable;
 use super::super::lifecycle::{HISTOGRAM_MAX_BUCKET_SIZE, HISTOGRAM_PRECISION};
 use super::super::numeric_index_read::NumericIndexRead;
 use super::super::universal_numeric_index::UniversalNumericIndex;
+use super::wal::{NumericIndexWal, WalOperation};
 use super::{InMemoryNumericIndex, MutableNumericIndex, default_gridstore_options};
 use crate::common::Flusher;
 use crate::common::operation_error::{OperationError, OperationResult};
@@ -25,6 +26,7 @@ impl<T: Encodable + Numericable> Default for InMemoryNumericIndex<T> {
             histogram: Histogram::new(HISTOGRAM_MAX_BUCKET_SIZE, HISTOGRAM_PRECISION),
             points_count: 0,
             max_values_per_point: 0,
+            points_per_values_count: Vec::new(),
             point_to_values: Default::default(),
         }
     }
@@ -49,12 +51,11 @@ impl<T: Encodable + Numericable + Default> FromIterator<(PointOffsetType, T)>
             let key = Point::new(value, idx);
             InMemoryNumericIndex::add_to_map(&mut index.map, &mut index.histogram, key);
         }
-        for values in &index.point_to_values {
-            if !values.is_empty() {
-                index.points_count += 1;
-                index.max_values_per_point = index.max_values_per_point.max(values.len());
-            }
+        let point_to_values = std::mem::take(&mut index.point_to_values);
+        for values in &point_to_values {
+            index.count_point_values(values.len());
         }
+        index.point_to_values = point_to_values;
         index
     }
 }
@@ -84,23 +85,50 @@ impl<T: Encodable + Numericable + Default> InMemoryNumericIndex<T> {
             let key = Point::new(*value, idx);
             Self::add_to_map(&mut self.map, &mut self.histogram, key);
         }
-        if !values.is_empty() {
-            self.points_count += 1;
-            self.max_values_per_point = self.max_values_per_point.max(values.len());
-        }
+        self.count_point_values(values.len());
         self.point_to_values[idx as usize] = values;
     }
 
     pub fn remove_point(&mut self, idx: PointOffsetType) {
         if let Some(values) = self.point_to_values.get_mut(idx as usize) {
-            if !values.is_empty() {
-                self.points_count = self.points_count.saturating_sub(1);
-            }
             for value in values.iter() {
                 let key = Point::new(*value, idx);
                 Self::remove_from_map(&mut self.map, &mut self.histogram, key);
             }
+            let removed_count = values.len();
             *values = Default::default();
+            self.uncount_point_values(removed_count);
+        }
+    }
+
+    /// Account a point added with `count` values in `points_count` and `max_values_per_point`.
+    fn count_point_values(&mut self, count: usize) {
+        if count == 0 {
+            return;
+        }
+        self.points_count += 1;
+        if self.points_per_values_count.len() <= count {
+            self.points_per_values_count.resize(count + 1, 0);
+        }
+        self.points_per_values_count[count] += 1;
+        self.max_values_per_point = self.max_values_per_point.max(count);
+    }
+
+    /// Inverse of [`Self::count_point_values`]. Lowering the maximum walks down the counts,
+    /// which is bounded by the maximum rather than by the number of points.
+    fn uncount_point_values(&mut self, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let Some(points) = self.points_per_values_count.get_mut(count) else {
+            return;
+        };
+        *points = points.saturating_sub(1);
+        self.points_count = self.points_count.saturating_sub(1);
+        while self.max_values_per_point > 0
+            && self.points_per_values_count[self.max_values_per_point] == 0
+        {
+            self.max_values_per_point -= 1;
         }
     }
 
@@ -152,15 +180,33 @@ where
     /// not exist. If false and files don't exist, this will return `None` to indicate nothing
     /// could be loaded.
     pub fn open_gridstore(path: PathBuf, create_if_missing: bool) -> OperationResult<Option<Self>> {
+        Self::open_gridstore_impl(path, create_if_missing, false)
+    }
+
+    /// Same as [`Self::open_gridstore`], but also records every update in a write-ahead log next
+    /// to the Gridstore files. Updates since the last flush are then recovered on open after a
+    /// crash, without relying on a higher-level WAL to replay them.
+    pub fn open_gridstore_with_wal(
+        path: PathBuf,
+        create_if_missing: bool,
+    ) -> OperationResult<Option<Self>> {
+        Self::open_gridstore_impl(path, create_if_missing, true)
+    }
+
+    fn open_gridstore_impl(
+        path: PathBuf,
+        create_if_missing: bool,
+        with_wal: bool,
+    ) -> OperationResult<Option<Self>> {
         let store = if create_if_missing {
             let options = default_gridstore_options::<T>();
-            Gridstore::open_or_create(path, options).map_err(|err| {
+            Gridstore::open_or_create(path.clone(), options).map_err(|err| {
                 OperationError::service_error(format!(
                     "failed to open mutable numeric index on gridstore: {err}"
                 ))
             })?
         } else if path.exists() {
-            Gridstore::open(path).map_err(|err| {
+            Gridstore::open(path.clone()).map_err(|err| {
                 OperationError::service_error(format!(
                     "failed to open mutable numeric index on gridstore: {err}"
                 ))
@@ -185,10 +231,36 @@ where
             // unwrap safety: never returns an error
             .unwrap();
 
-        Ok(Some(Self {
+        let mut index = Self {
             storage: store,
             in_memory_index,
-        }))
+            wal: None,
+        };
+        // A log is left over if we crashed before a flush, regardless of whether we use one now
+        index.recover_from_wal(&path)?;
+        if with_wal {
+            index.wal = Some(NumericIndexWal::create(&path)?);
+        }
+
+        Ok(Some(index))
+    }
+
+    /// Replay the updates of a write-ahead log left in `path`, persist them and remove the log.
+    fn recover_from_wal(&mut self, path: &Path) -> OperationResult<()> {
+        let operations = NumericIndexWal::<T>::read_operations(path)?;
+        if !operations.is_empty() {
+            let hw_counter = HardwareCounterCell::disposable();
+            for operation in operations {
+                match operation {
+                    WalOperation::SetValues { idx, values } => {
+                        self.add_many_to_list(idx, values, &hw_counter)?;
+                    }
+                    WalOperation::RemovePoint { idx } => self.remove_point(idx)?,
+                }
+            }
+            self.storage.flusher()().map_err(OperationError::from)?;
+        }
+        NumericIndexWal::<T>::remove(path)
     }
 
     pub fn into_in_memory_index(self) -> InMemoryNumericIndex<T> {
@@ -199,7 +271,11 @@ where
     pub(in super::super) fn clear(&mut self) -> OperationResult<()> {
         self.storage.clear().map_err(|err| {
             OperationError::service_error(format!("Failed to clear mutable numeric index: {err}",))
-        })
+        })?;
+        if let Some(wal) = &self.wal {
+            wal.clear()?;
+        }
+        Ok(())
     }
 
     #[inline]
@@ -223,21 +299,41 @@ where
 
     #[inline]
     pub(in super::super) fn files(&self) -> Vec<PathBuf> {
-        self.storage.files()
+        let mut files = self.storage.files();
+        if let Some(wal) = &self.wal {
+            files.push(wal.file_path());
+        }
+        files
     }
 
     #[inline]
     pub(in super::super) fn flusher(&self) -> Flusher {
+        // Take the log position first, so it never covers updates the storage flush misses
+        let wal_truncator = self.wal.as_ref().map(NumericIndexWal::truncator);
         let storage_flusher = self.storage.flusher();
-        Box::new(move || storage_flusher().map_err(OperationError::from))
+        Box::new(move || {
+            storage_flusher().map_err(OperationError::from)?;
+            if let Some(truncate_wal) = wal_truncator {
+                truncate_wal()?;
+            }
+            Ok(())
+        })
     }
 
+    /// Replace the values of point `idx`.
+    ///
+    /// Bytes written to gridstore are charged to the payload index IO write
+    /// counter of `hw_counter`.
     pub fn add_many_to_list(
         &mut self,
         idx: PointOffsetType,
         values: Vec<T>,
         hw_counter: &HardwareCounterCell,
     ) -> OperationResult<()> {
+        if let Some(wal) = &self.wal {
+            wal.append_set_values(idx, &values)?;
+        }
+
         // Update persisted storage
         if values.is_empty() {
             // We cannot store empty value, then delete instead
@@ -258,6 +354,10 @@ where
     }
 
     pub fn remove_point(&mut self, idx: PointOffsetType) -> OperationResult<()> {
+        if let Some(wal) = &self.wal {
+            wal.append_remove_point(idx)?;
+        }
+
         // Update persisted storage
         self.storage.delete_value(idx)?;
 
@@ -10,6 +10,7 @@ use crate::index::field_index::numeric_point::{Numericable, Point};
 mod lifecycle;
 pub mod read_only;
 mod read_ops;
+mod wal;
 
 /// Default options for Gridstore storage
 pub(super) const fn default_gridstore_options<T: Sized>() -> StorageOptions {
@@ -32,6 +33,8 @@ where
     // Backing storage, source of state, persists deletions
     pub(super) storage: Gridstore<Vec<T>>,
     pub(super) in_memory_index: InMemoryNumericIndex<T>,
+    /// Optional write-ahead log of updates since the last flush
+    wal: Option<wal::NumericIndexWal<T>>,
 }
 
 // Numeric Index with insertions and deletions without persistence
@@ -40,5 +43,8 @@ pub struct InMemoryNumericIndex<T: Encodable + Numericable> {
     pub histogram: Histogram<T>,
     pub points_count: usize,
     pub max_values_per_point: usize,
+    /// Number of points by their count of values, to lower `max_values_per_point` on removal
+    /// without scanning all points
+    pub(super) points_per_values_count: Vec<usize>,
     pub point_to_values: Vec<Vec<T>>,
 }
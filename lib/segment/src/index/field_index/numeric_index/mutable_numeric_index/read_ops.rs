@@ -1,16 +1,21 @@
 use std::ops::Bound;
 
+use common::bitvec::BitVec;
 use common::counter::hardware_counter::HardwareCounterCell;
 use common::types::PointOffsetType;
 use gridstore::Blob;
 
 use super::super::Encodable;
+use super::super::integrity::{
+    check_pairs_count, check_sorted_pairs, distinct_values_count, integrity_error,
+};
 use super::super::numeric_index_read::NumericIndexRead;
 use super::{InMemoryNumericIndex, MutableNumericIndex};
 use crate::common::operation_error::OperationResult;
 use crate::index::field_index::histogram::Histogram;
 use crate::index::field_index::numeric_point::{Numericable, Point};
 use crate::index::field_index::stored_point_to_values::StoredValue;
+use crate::index::field_index::utils::check_boundaries;
 use crate::index::payload_config::StorageType;
 
 impl<T: Encodable + Numericable + Default> InMemoryNumericIndex<T> {
@@ -47,6 +52,21 @@ impl<T: Encodable + Numericable + Default> InMemoryNumericIndex<T> {
             .map(|point| point.idx)
     }
 
+    /// Number of `(value, point)` pairs in the given range.
+    ///
+    /// Counts the entries of the range, there are no pairs of deleted points to account for.
+    /// Empty or inverted bounds count zero.
+    pub fn values_range_size(
+        &self,
+        start_bound: Bound<Point<T>>,
+        end_bound: Bound<Point<T>>,
+    ) -> usize {
+        if !check_boundaries(&start_bound, &end_bound) {
+            return 0;
+        }
+        self.map.range((start_bound, end_bound)).count()
+    }
+
     pub fn orderable_values_range(
         &self,
         start_bound: Bound<Point<T>>,
@@ -78,6 +98,7 @@ impl<T: Encodable + Numericable> InMemoryNumericIndex<T> {
             histogram,
             points_count: _,         // scalar
             max_values_per_point: _, // scalar
+            points_per_values_count,
             point_to_values,
         } = self;
 
@@ -90,7 +111,55 @@ impl<T: Encodable + Numericable> InMemoryNumericIndex<T> {
                 .iter()
                 .map(|v| v.capacity() * std::mem::size_of::<T>())
                 .sum::<usize>();
-        map_bytes + histogram_bytes + ptv_bytes
+        let counts_bytes = points_per_values_count.capacity() * std::mem::size_of::<usize>();
+        map_bytes + histogram_bytes + ptv_bytes + counts_bytes
+    }
+
+    /// See [`NumericIndexInner::has_value_bitmap`].
+    ///
+    /// [`NumericIndexInner::has_value_bitmap`]: super::super::NumericIndexInner::has_value_bitmap
+    pub fn has_value_bitmap(&self) -> BitVec {
+        self.point_to_values
+            .iter()
+            .map(|values| !values.is_empty())
+            .collect()
+    }
+
+    /// Call `f` with the values of every point that has any, in ascending point order.
+    pub fn for_each_point_values(&self, mut f: impl FnMut(PointOffsetType, &[T])) {
+        for (idx, values) in self.point_to_values.iter().enumerate() {
+            if !values.is_empty() {
+                f(idx as PointOffsetType, values);
+            }
+        }
+    }
+
+    /// Check the invariants of the in-memory structures, see
+    /// [`NumericIndexInner::verify_integrity`].
+    ///
+    /// [`NumericIndexInner::verify_integrity`]: super::super::NumericIndexInner::verify_integrity
+    pub fn verify_integrity(&self) -> OperationResult<()> {
+        let pairs_count = check_sorted_pairs(self.map.iter().copied(), self.point_to_values.len())?;
+
+        let values_count = self
+            .point_to_values
+            .iter()
+            .map(|values| distinct_values_count(values.iter().copied()))
+            .sum();
+        check_pairs_count(pairs_count, values_count)?;
+
+        let points_with_values = self
+            .point_to_values
+            .iter()
+            .filter(|values| !values.is_empty())
+            .count();
+        if points_with_values != self.points_count {
+            return Err(integrity_error(format_args!(
+                "points count is {}, but {points_with_values} points have values",
+                self.points_count,
+            )));
+        }
+        Ok(())
     }
 }
 
@@ -129,6 +198,29 @@ where
         Ok(self.in_memory_index.values_range(start_bound, end_bound))
     }
 
+    fn values_range_size(
+        &self,
+        start_bound: Bound<Point<T>>,
+        end_bound: Bound<Point<T>>,
+        _hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<usize> {
+        Ok(self
+            .in_memory_index
+            .values_range_size(start_bound, end_bound))
+    }
+
+    /// Removed points leave no pairs behind, so all pairs are live.
+    fn live_values_range_size(
+        &self,
+        start_bound: Bound<Point<T>>,
+        end_bound: Bound<Point<T>>,
+        _hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<usize> {
+        Ok(self
+            .in_memory_index
+            .values_range_size(start_bound, end_bound))
+    }
+
     fn orderable_values_range(
         &self,
         start_bound: Bound<Point<T>>,
@@ -0,0 +1,212 @@
+//! Append-only write-ahead log of a [`MutableNumericIndex`].
+//!
+//! Gridstore only persists updates on flush. The log records every update
+//! before it is applied, so updates since the last flush can be replayed on
+//! open. A flush drops the part of the log it made redundant.
+//!
+//! Records are appended with plain writes, which protects against process
+//! crashes. The log isn't synced to disk until a flush.
+//!
+//! [`MutableNumericIndex`]: super::MutableNumericIndex
+
+use std::io::{ErrorKind, Read, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+
+use common::types::PointOffsetType;
+use fs_err as fs;
+use fs_err::{File, OpenOptions};
+use parking_lot::Mutex;
+
+use crate::common::operation_error::{OperationError, OperationResult};
+
+const WAL_PATH: &str = "numeric_index.wal";
+const WAL_TMP_PATH: &str = "numeric_index.wal.tmp";
+
+const OP_SET_VALUES: u8 = 0;
+const OP_REMOVE_POINT: u8 = 1;
+
+/// Operation byte, point id and number of values
+const RECORD_HEADER_SIZE: usize = 1 + size_of::<u32>() + size_of::<u32>();
+
+pub(super) enum WalOperation<T> {
+    /// Replace the values of a point, see [`super::MutableNumericIndex::add_many_to_list`]
+    SetValues {
+        idx: PointOffsetType,
+        values: Vec<T>,
+    },
+    /// See [`super::MutableNumericIndex::remove_point`]
+    RemovePoint { idx: PointOffsetType },
+}
+
+struct WalFile {
+    file: File,
+    /// Bytes written so far
+    len: u64,
+}
+
+pub(super) struct NumericIndexWal<T> {
+    dir: PathBuf,
+    file: Arc<Mutex<WalFile>>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> NumericIndexWal<T> {
+    pub fn path(dir: &Path) -> PathBuf {
+        dir.join(WAL_PATH)
+    }
+
+    pub fn file_path(&self) -> PathBuf {
+        Self::path(&self.dir)
+    }
+
+    /// Create an empty log in `dir`, replacing any existing one.
+    pub fn create(dir: &Path) -> OperationResult<Self> {
+        // Append mode, so writes keep going to the end after truncation
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(Self::path(dir))?;
+        file.set_len(0)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            file: Arc::new(Mutex::new(WalFile { file, len: 0 })),
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Read all complete records of the log in `dir`, if there is one.
+    ///
+    /// A trailing partial record, left by a crash in the middle of an append,
+    /// is ignored.
+    pub fn read_operations(dir: &Path) -> OperationResult<Vec<WalOperation<T>>> {
+        let mut bytes = Vec::new();
+        match File::open(Self::path(dir)) {
+            Ok(mut file) => file.read_to_end(&mut bytes)?,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut operations = Vec::new();
+        let mut rest = bytes.as_slice();
+        while rest.len() >= RECORD_HEADER_SIZE {
+            let (header, body) = rest.split_at(RECORD_HEADER_SIZE);
+            let op = header[0];
+            let idx = u32::from_le_bytes(header[1..5].try_into().unwrap());
+            let count = u32::from_le_bytes(header[5..9].try_into().unwrap()) as usize;
+
+            let values_size = count * size_of::<T>();
+            if body.len() < values_size {
+                break;
+            }
+            let (values, body) = body.split_at(values_size);
+
+            let operation = match op {
+                OP_SET_VALUES => WalOperation::SetValues {
+                    idx,
+                    values: bytemuck::pod_collect_to_vec(values),
+                },
+                OP_REMOVE_POINT => WalOperation::RemovePoint { idx },
+                _ => {
+                    return Err(OperationError::service_error(format!(
+                        "Corrupted numeric index WAL at {}: unknown operation {op}",
+                        Self::path(dir).display(),
+                    )));
+                }
+            };
+            operations.push(operation);
+            rest = body;
+        }
+        Ok(operations)
+    }
+
+    /// Remove the log in `dir`, if there is one.
+    pub fn remove(dir: &Path) -> OperationResult<()> {
+        match fs::remove_file(Self::path(dir)) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn append_set_values(&self, idx: PointOffsetType, values: &[T]) -> OperationResult<()> {
+        self.append(OP_SET_VALUES, idx, values)
+    }
+
+    pub fn append_remove_point(&self, idx: PointOffsetType) -> OperationResult<()> {
+        self.append(OP_REMOVE_POINT, idx, &[])
+    }
+
+    fn append(&self, op: u8, idx: PointOffsetType, values: &[T]) -> OperationResult<()> {
+        let mut record = Vec::with_capacity(RECORD_HEADER_SIZE + size_of_val(values));
+        record.push(op);
+        record.extend_from_slice(&idx.to_le_bytes());
+        record.extend_from_slice(&(values.len() as u32).to_le_bytes());
+        record.extend_from_slice(bytemuck::cast_slice(values));
+
+        let mut wal = self.file.lock();
+        wal.file.write_all(&record)?;
+        wal.len += record.len() as u64;
+        Ok(())
+    }
+
+    /// Drop all records.
+    pub fn clear(&self) -> OperationResult<()> {
+        let mut wal = self.file.lock();
+        wal.file.set_len(0)?;
+        wal.len = 0;
+        Ok(())
+    }
+
+    /// Returns a function dropping the records written up to now. To be
+    /// called once these records are persisted by a flush of the storage.
+    pub fn truncator(&self) -> impl FnOnce() -> OperationResult<()> + Send + use<T> {
+        let flushed_len = self.file.lock().len;
+        let dir = self.dir.clone();
+        let file = Arc::downgrade(&self.file);
+        move || truncate_flushed(&dir, &file, flushed_len)
+    }
+}
+
+/// Drop the first `flushed_len` bytes of the log.
+fn truncate_flushed(
+    dir: &Path,
+    file: &Weak<Mutex<WalFile>>,
+    flushed_len: u64,
+) -> OperationResult<()> {
+    // Index is gone already, nothing to truncate
+    let Some(file) = file.upgrade() else {
+        return Ok(());
+    };
+    let mut wal = file.lock();
+
+    if wal.len == flushed_len {
+        wal.file.set_len(0)?;
+        wal.len = 0;
+        return Ok(());
+    }
+
+    // Records were appended after the flush started, they must survive. Swap in
+    // a new log holding only them, so a crash in between keeps either version.
+    let path = dir.join(WAL_PATH);
+    let tmp_path = dir.join(WAL_TMP_PATH);
+    let mut bytes = Vec::new();
+    File::open(&path)?.read_to_end(&mut bytes)?;
+    let tail = bytes.get(flushed_len as usize..).ok_or_else(|| {
+        OperationError::service_error(format!(
+            "Numeric index WAL at {} is shorter than expected",
+            path.display(),
+        ))
+    })?;
+
+    let mut tmp_file = File::create(&tmp_path)?;
+    tmp_file.write_all(tail)?;
+    tmp_file.sync_all()?;
+    drop(tmp_file);
+    fs::rename(&tmp_path, &path)?;
+
+    wal.file = OpenOptions::new().append(true).open(&path)?;
+    wal.len = tail.len() as u64;
+    Ok(())
+}
@@ -2,7 +2,7 @@ use common::types::PointOffsetType;
 use itertools::Either;
 
 use super::numeric_index_read::NumericIndexRead;
-use super::{NumericIndexInner, ReadOnlyNumericIndexInner, StreamRange};
+use super::{NumericIndexInner, ReadOnlyNumericIndexInner, StreamCursor, StreamRange};
 use crate::common::operation_error::OperationResult;
 use crate::data_types::order_by::OrderValue;
 use crate::types::{FloatPayloadType, IntPayloadType, RangeInterface};
@@ -55,6 +55,27 @@ where
             ),
         })
     }
+
+    fn stream_range_page(
+        &self,
+        range: &RangeInterface,
+        cursor: Option<&StreamCursor>,
+        limit: usize,
+    ) -> OperationResult<(Vec<(OrderValue, PointOffsetType)>, Option<StreamCursor>)> {
+        let (page, cursor) = match self {
+            NumericFieldIndexView::IntIndex(index) => {
+                let (page, cursor) = index.stream_range_page(range, cursor, limit)?;
+                let page = page.into_iter().map(|(v, p)| (OrderValue::from(v), p));
+                (page.collect(), cursor)
+            }
+            NumericFieldIndexView::FloatIndex(index) => {
+                let (page, cursor) = index.stream_range_page(range, cursor, limit)?;
+                let page = page.into_iter().map(|(v, p)| (OrderValue::from(v), p));
+                (page.collect(), cursor)
+            }
+        };
+        Ok((page, cursor))
+    }
 }
 
 impl<'a, I, F> NumericFieldIndexView<'a, I, F>
@@ -100,6 +121,14 @@ pub trait NumericFieldIndexRead {
         &self,
         range: &RangeInterface,
     ) -> OperationResult<impl DoubleEndedIterator<Item = (OrderValue, PointOffsetType)> + '_>;
+
+    /// See [`StreamRange::stream_range_page`].
+    fn stream_range_page(
+        &self,
+        range: &RangeInterface,
+        cursor: Option<&StreamCursor>,
+        limit: usize,
+    ) -> OperationResult<(Vec<(OrderValue, PointOffsetType)>, Option<StreamCursor>)>;
 }
 
 impl<'a, I, F> NumericFieldIndexRead for NumericFieldIndexView<'a, I, F>
@@ -117,4 +146,13 @@ where
     ) -> OperationResult<impl DoubleEndedIterator<Item = (OrderValue, PointOffsetType)> + '_> {
         StreamRange::stream_range(self, range)
     }
+
+    fn stream_range_page(
+        &self,
+        range: &RangeInterface,
+        cursor: Option<&StreamCursor>,
+        limit: usize,
+    ) -> OperationResult<(Vec<(OrderValue, PointOffsetType)>, Option<StreamCursor>)> {
+        StreamRange::stream_range_page(self, range, cursor, limit)
+    }
 }
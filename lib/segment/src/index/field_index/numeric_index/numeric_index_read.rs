@@ -1,3 +1,4 @@
+use std::cell::Cell;
 use std::ops::Bound;
 
 use common::counter::hardware_counter::HardwareCounterCell;
@@ -47,6 +48,9 @@ pub trait NumericIndexRead<T: Encodable + Numericable + Default + StoredValue> {
         hw_counter: &'a HardwareCounterCell,
     ) -> OperationResult<impl Iterator<Item = PointOffsetType> + 'a>;
 
+    /// `(value, point_id)` pairs of the range, ordered by `Point` (value, then
+    /// point id). Every variant must yield exactly this order, see
+    /// [`super::StreamRange::stream_range`].
     fn orderable_values_range(
         &self,
         start_bound: Bound<Point<T>>,
@@ -57,7 +61,8 @@ pub trait NumericIndexRead<T: Encodable + Numericable + Default + StoredValue> {
     ///
     /// The default counts [`Self::values_range`]; variants with a
     /// precomputed sorted container (`Immutable` / `Mmap`) override it with
-    /// an `O(log n)` boundary search.
+    /// an `O(log n)` boundary search, `Mutable` counts its tree range without
+    /// going through point ids.
     fn values_range_size(
         &self,
         start_bound: Bound<Point<T>>,
@@ -69,6 +74,23 @@ pub trait NumericIndexRead<T: Encodable + Numericable + Default + StoredValue> {
             .count())
     }
 
+    /// Number of `(value, point)` pairs of non-deleted points in the given range.
+    ///
+    /// Unlike [`Self::values_range_size`], pairs of deleted points are never
+    /// counted. The default counts [`Self::values_range`]; sorted variants
+    /// (`Immutable` / `Mmap`) start from the boundary search and only adjust
+    /// for deleted points.
+    fn live_values_range_size(
+        &self,
+        start_bound: Bound<Point<T>>,
+        end_bound: Bound<Point<T>>,
+        hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<usize> {
+        Ok(self
+            .values_range(start_bound, end_bound, hw_counter)?
+            .count())
+    }
+
     fn get_histogram(&self) -> &Histogram<T>;
 
     fn get_points_count(&self) -> usize;
@@ -92,12 +114,41 @@ pub trait NumericIndexRead<T: Encodable + Numericable + Default + StoredValue> {
         self.values_count(idx).unwrap_or(0) == 0
     }
 
+    /// Evaluate several predicates over the values of one point, reading the
+    /// values only once. Returns, per predicate, whether any value matched it,
+    /// same as one [`Self::check_values_any`] call per predicate would.
+    fn check_values_all_predicates(
+        &self,
+        idx: PointOffsetType,
+        predicates: &[&dyn Fn(&T) -> bool],
+        hw_counter: &HardwareCounterCell,
+    ) -> Vec<bool> {
+        let matched = vec![Cell::new(false); predicates.len()];
+        let remaining = Cell::new(predicates.len());
+        self.check_values_any(
+            idx,
+            |value| {
+                for (predicate, matched) in predicates.iter().zip(&matched) {
+                    if !matched.get() && predicate(value) {
+                        matched.set(true);
+                        remaining.set(remaining.get() - 1);
+                    }
+                }
+                // Stop reading values once every predicate is satisfied
+                remaining.get() == 0
+            },
+            hw_counter,
+        );
+        matched.into_iter().map(Cell::into_inner).collect()
+    }
+
     fn get_telemetry_data(&self) -> PayloadIndexTelemetry {
         PayloadIndexTelemetry {
             field_name: None,
             points_count: self.get_points_count(),
             points_values_count: self.get_histogram().get_total_count(),
             histogram_bucket_size: Some(self.get_histogram().current_bucket_size()),
+            cardinality_estimation_error: None,
             index_type: self.telemetry_index_type(),
         }
     }
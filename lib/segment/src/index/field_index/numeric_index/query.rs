@@ -10,32 +10,74 @@
 use std::cmp::{max, min};
 use std::ops::Bound;
 use std::ops::Bound::{Excluded, Included, Unbounded};
-use std::str::FromStr;
 
 use common::counter::hardware_accumulator::HwMeasurementAcc;
 use common::counter::hardware_counter::HardwareCounterCell;
 use common::types::PointOffsetType;
-use itertools::Either;
+use fnv::FnvBuildHasher;
+use indexmap::IndexSet;
+use itertools::{Either, Itertools};
 use ordered_float::OrderedFloat;
-use uuid::Uuid;
 
 use super::Encodable;
+use super::compiled_condition::{CompiledKeys, CompiledNumericCondition};
 use super::numeric_index_read::NumericIndexRead;
-use crate::common::operation_error::OperationResult;
+use super::read_ops::StreamCursor;
+use crate::common::operation_error::{OperationError, OperationResult};
 use crate::index::field_index::numeric_point::{Numericable, Point};
 use crate::index::field_index::stat_tools::estimate_multi_value_selection_cardinality;
 use crate::index::field_index::stored_point_to_values::StoredValue;
 use crate::index::field_index::utils::check_boundaries;
 use crate::index::field_index::{CardinalityEstimation, PayloadBlockCondition, PrimaryCondition};
+use crate::index::query_estimator::combine_should_estimations;
 use crate::index::query_optimization::optimized_filter::ConditionCheckerFn;
-use crate::types::{
-    FieldCondition, Match, MatchValue, PayloadKeyType, Range, RangeInterface, ValueVariants,
-};
+use crate::types::{FieldCondition, IntPayloadType, PayloadKeyType, Range, RangeInterface};
+
+/// Upper bound on how many `Match::Any` elements are charged to the hardware
+/// counter during cardinality estimation. Elements past this limit are still
+/// estimated, but their binary-search cost is not accounted for, so very large
+/// sets don't blow up the reported IO of the query planning phase.
+const MATCH_ANY_MAX_CHARGED_ELEMENTS: usize = 64;
+
+/// Indexes with at most this many unique values are scanned during range
+/// cardinality estimation instead of consulting the histogram, so the
+/// estimation is exact.
+const EXACT_RANGE_CARDINALITY_MAX_VALUES: usize = 256;
+
+/// Convert range bounds into the index's key type `T`.
+///
+/// `T::from_f64_range` / `T::from_u128` are total functions provided by
+/// `Numericable`, so every numeric variant (Int / Float / Datetime / Uuid) can
+/// serve any `RangeInterface` shape. For integer `T`, the float-range
+/// conversion rounds each bound *away* from the matching set so fractional
+/// bounds keep their `f64`-comparison semantics.
+pub(super) fn typed_range<T: Numericable>(range: &RangeInterface) -> Range<T> {
+    match range {
+        RangeInterface::Float(float_range) => T::from_f64_range(*float_range),
+        RangeInterface::DateTime(datetime_range) => {
+            datetime_range.map(|dt| T::from_u128(dt.timestamp() as u128))
+        }
+    }
+}
 
 /// Histogram-driven cardinality estimation for a range condition.
 pub(super) fn range_cardinality<T, I>(
     index: &I,
     range: &RangeInterface,
+    hw_counter: &HardwareCounterCell,
+) -> OperationResult<CardinalityEstimation>
+where
+    T: Encodable + Numericable + StoredValue + Send + Sync + Default,
+    I: NumericIndexRead<T>,
+{
+    typed_range_cardinality(index, &typed_range(range), hw_counter)
+}
+
+/// Same as [`range_cardinality`], for a range already in the key type `T`.
+fn typed_range_cardinality<T, I>(
+    index: &I,
+    range: &Range<T>,
+    hw_counter: &HardwareCounterCell,
 ) -> OperationResult<CardinalityEstimation>
 where
     T: Encodable + Numericable + StoredValue + Send + Sync + Default,
@@ -46,12 +88,18 @@ where
         return Ok(CardinalityEstimation::exact(0));
     }
 
-    let range = match range {
-        RangeInterface::Float(float_range) => T::from_f64_range(*float_range),
-        RangeInterface::DateTime(datetime_range) => {
-            datetime_range.map(|dt| T::from_u128(dt.timestamp() as u128))
+    let total_values = index.total_unique_values_count()?;
+    if total_values <= EXACT_RANGE_CARDINALITY_MAX_VALUES {
+        let (start_bound, end_bound) = range.as_index_key_bounds();
+        if !check_boundaries(&start_bound, &end_bound) {
+            return Ok(CardinalityEstimation::exact(0));
         }
-    };
+        let count = index
+            .values_range(start_bound, end_bound, hw_counter)?
+            .unique()
+            .count();
+        return Ok(CardinalityEstimation::exact(count));
+    }
 
     let lbound = if let Some(lte) = range.lte {
         Included(lte)
@@ -73,7 +121,6 @@ where
     let min_estimation = histogram_estimation.0;
     let max_estimation = histogram_estimation.2;
 
-    let total_values = index.total_unique_values_count()?;
     // Note: max_values_per_point is never zero here because we check it above
     let expected_min = max(
         min_estimation / max_values_per_point,
@@ -99,6 +146,91 @@ where
     })
 }
 
+/// Exact number of points with a value in `range`, without materializing ids.
+///
+/// If no point holds more than one value, pairs and points are the same, and
+/// the count comes from [`NumericIndexRead::live_values_range_size`]. Otherwise
+/// a point may hold several values in the range, so its ids are deduplicated.
+/// This fallback reads every pair in the range and keeps a set of the ids seen,
+/// `O(k)` in time and memory for `k` pairs in the range.
+pub(super) fn count_in_range<T, I>(
+    index: &I,
+    range: &RangeInterface,
+    hw_counter: &HardwareCounterCell,
+) -> OperationResult<usize>
+where
+    T: Encodable + Numericable + StoredValue + Send + Sync + Default,
+    I: NumericIndexRead<T>,
+{
+    let (start_bound, end_bound) = typed_range::<T>(range).as_index_key_bounds();
+    if !check_boundaries(&start_bound, &end_bound) {
+        return Ok(0);
+    }
+
+    if index.get_max_values_per_point() <= 1 {
+        return index.live_values_range_size(start_bound, end_bound, hw_counter);
+    }
+    Ok(index
+        .values_range(start_bound, end_bound, hw_counter)?
+        .unique()
+        .count())
+}
+
+/// Expected number of `(value, point)` pairs in `range` to read in sorted order
+/// before `limit` of them belong to live points.
+///
+/// Pairs of deleted points stay in sorted storage until a rebuild. They are
+/// assumed to be spread evenly over the range, in the proportion found by
+/// counting the live pairs in it. Never more than the number of pairs in the
+/// range.
+pub(super) fn estimate_scan_depth<T, I>(
+    index: &I,
+    range: &RangeInterface,
+    limit: usize,
+    hw_counter: &HardwareCounterCell,
+) -> OperationResult<usize>
+where
+    T: Encodable + Numericable + StoredValue + Send + Sync + Default,
+    I: NumericIndexRead<T>,
+{
+    let (start_bound, end_bound) = typed_range::<T>(range).as_index_key_bounds();
+    if limit == 0 || !check_boundaries(&start_bound, &end_bound) {
+        return Ok(0);
+    }
+
+    let pairs_count = index.values_range_size(start_bound, end_bound, hw_counter)?;
+    let live_pairs_count = index.live_values_range_size(start_bound, end_bound, hw_counter)?;
+    if live_pairs_count == 0 {
+        return Ok(pairs_count);
+    }
+    Ok(min(
+        limit.saturating_mul(pairs_count).div_ceil(live_pairs_count),
+        pairs_count,
+    ))
+}
+
+/// Points with a value in `range`, once for each of their values in it.
+pub(super) fn range_points<'a, T, I>(
+    index: &'a I,
+    range: &RangeInterface,
+    hw_counter: &'a HardwareCounterCell,
+) -> OperationResult<Box<dyn Iterator<Item = PointOffsetType> + 'a>>
+where
+    T: Encodable + Numericable + StoredValue + Send + Sync + Default,
+    I: NumericIndexRead<T>,
+{
+    let (start_bound, end_bound) = typed_range::<T>(range).as_index_key_bounds();
+    if !check_boundaries(&start_bound, &end_bound) {
+        return Ok(Box::new(std::iter::empty()));
+    }
+
+    Ok(Box::new(index.values_range(
+        start_bound,
+        end_bound,
+        hw_counter,
+    )?))
+}
+
 /// Estimate the number of points carrying exactly `value`.
 pub(super) fn estimate_points<T, I>(
     index: &I,
@@ -109,13 +241,36 @@ where
     T: Encodable + Numericable + StoredValue + Send + Sync + Default,
     I: NumericIndexRead<T>,
 {
-    let start = Bound::Included(Point::new(*value, PointOffsetType::MIN));
-    let end = Bound::Included(Point::new(*value, PointOffsetType::MAX));
+    charge_point_lookup(index, hw_counter)?;
+    estimate_points_uncharged(index, value, hw_counter)
+}
 
+/// Charge the cost of looking up a single value to the hardware counter.
+fn charge_point_lookup<T, I>(index: &I, hw_counter: &HardwareCounterCell) -> OperationResult<()>
+where
+    T: Encodable + Numericable + StoredValue + Send + Sync + Default,
+    I: NumericIndexRead<T>,
+{
     hw_counter
         .payload_index_io_read_counter()
         // We have to do 2 times binary search in mmap and immutable storage.
         .incr_delta(2 * ((index.total_unique_values_count()? as f32).log2().ceil() as usize));
+    Ok(())
+}
+
+/// Same as [`estimate_points`], but leaves the lookup cost accounting to the
+/// caller.
+fn estimate_points_uncharged<T, I>(
+    index: &I,
+    value: &T,
+    hw_counter: &HardwareCounterCell,
+) -> OperationResult<usize>
+where
+    T: Encodable + Numericable + StoredValue + Send + Sync + Default,
+    I: NumericIndexRead<T>,
+{
+    let start = Bound::Included(Point::new(*value, PointOffsetType::MIN));
+    let end = Bound::Included(Point::new(*value, PointOffsetType::MAX));
 
     let range_size = index.values_range_size(start, end, hw_counter)?;
     if range_size == 0 {
@@ -126,6 +281,57 @@ where
     Ok((range_size as f32 / avg_values_per_point).max(1.0).round() as usize)
 }
 
+/// Distinct index keys of a `Match::Any` integer set, in ascending order.
+///
+/// `None` if the keys of `T` are not integers, see [`Numericable::from_integer`].
+pub(super) fn match_any_keys<T: Numericable>(
+    integers: &IndexSet<IntPayloadType, FnvBuildHasher>,
+) -> Option<Vec<T>> {
+    let mut keys = integers
+        .iter()
+        .map(|integer| T::from_integer(*integer))
+        .collect::<Option<Vec<_>>>()?;
+    keys.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    keys.dedup();
+    Some(keys)
+}
+
+/// Cardinality estimation for the distinct keys of a `Match::Any` integer set.
+///
+/// Every distinct element is estimated on its own and the results are combined
+/// as a `should` clause, which keeps `min`/`max` conservative when the
+/// per-element matches overlap on multi-valued points.
+fn match_any_cardinality<T, I>(
+    index: &I,
+    keys: &[T],
+    hw_counter: &HardwareCounterCell,
+) -> OperationResult<CardinalityEstimation>
+where
+    T: Encodable + Numericable + StoredValue + Send + Sync + Default,
+    I: NumericIndexRead<T>,
+{
+    if keys.is_empty() {
+        return Ok(CardinalityEstimation::exact(0));
+    }
+
+    let estimations = keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| {
+            if i < MATCH_ANY_MAX_CHARGED_ELEMENTS {
+                charge_point_lookup(index, hw_counter)?;
+            }
+            let count = estimate_points_uncharged(index, key, hw_counter)?;
+            Ok(CardinalityEstimation::exact(count))
+        })
+        .collect::<OperationResult<Vec<_>>>()?;
+
+    Ok(combine_should_estimations(
+        &estimations,
+        index.get_points_count(),
+    ))
+}
+
 /// Point iterator for a `match`/`range` field condition.
 ///
 /// Returns `Ok(None)` when the condition is not one a numeric index can
@@ -139,43 +345,84 @@ where
     T: Encodable + Numericable + StoredValue + Send + Sync + Default,
     I: NumericIndexRead<T>,
 {
-    if let Some(Match::Value(MatchValue {
-        value: ValueVariants::String(keyword),
-    })) = &condition.r#match
-    {
-        let keyword = keyword.as_str();
-
-        if let Ok(uuid) = Uuid::from_str(keyword) {
-            let value = T::from_u128(uuid.as_u128());
-            let start = Bound::Included(Point::new(value, PointOffsetType::MIN));
-            let end = Bound::Included(Point::new(value, PointOffsetType::MAX));
-            return Ok(Some(Box::new(index.values_range(start, end, hw_counter)?)));
-        }
+    fn allow_all(_: PointOffsetType) -> bool {
+        true
     }
+    filter_prefiltered(index, condition, &allow_all, hw_counter)
+}
 
-    let Some(range_cond) = condition.range.as_ref() else {
+/// Same as [`filter`], but only yields points passing `allowed`.
+///
+/// `allowed` is checked while iterating the index, before combining the
+/// iterators of a `match any` condition.
+pub(super) fn filter_prefiltered<'a, T, I>(
+    index: &'a I,
+    condition: &FieldCondition,
+    allowed: &'a impl Fn(PointOffsetType) -> bool,
+    hw_counter: &'a HardwareCounterCell,
+) -> OperationResult<Option<Box<dyn Iterator<Item = PointOffsetType> + 'a>>>
+where
+    T: Encodable + Numericable + StoredValue + Send + Sync + Default,
+    I: NumericIndexRead<T>,
+{
+    let Some(compiled) = CompiledNumericCondition::compile(condition) else {
         return Ok(None);
     };
+    filter_compiled(index, &compiled, allowed, hw_counter).map(Some)
+}
 
-    let (start_bound, end_bound) = match range_cond {
-        RangeInterface::Float(float_range) => T::from_f64_range(*float_range),
-        RangeInterface::DateTime(datetime_range) => {
-            datetime_range.map(|dt| T::from_u128(dt.timestamp() as u128))
+/// Points matching a [`CompiledNumericCondition`] and passing `allowed`.
+pub(super) fn filter_compiled<'a, T, I>(
+    index: &'a I,
+    compiled: &CompiledNumericCondition<T>,
+    allowed: &'a impl Fn(PointOffsetType) -> bool,
+    hw_counter: &'a HardwareCounterCell,
+) -> OperationResult<Box<dyn Iterator<Item = PointOffsetType> + 'a>>
+where
+    T: Encodable + Numericable + StoredValue + Send + Sync + Default,
+    I: NumericIndexRead<T>,
+{
+    let is_allowed = move |idx: &PointOffsetType| allowed(*idx);
+
+    match &compiled.keys {
+        CompiledKeys::Value(value) => {
+            let start = Bound::Included(Point::new(*value, PointOffsetType::MIN));
+            let end = Bound::Included(Point::new(*value, PointOffsetType::MAX));
+            Ok(Box::new(
+                index
+                    .values_range(start, end, hw_counter)?
+                    .filter(is_allowed),
+            ))
         }
-    }
-    .as_index_key_bounds();
+        CompiledKeys::Any(keys) => {
+            let iterators = keys
+                .iter()
+                .map(|key| {
+                    let start = Bound::Included(Point::new(*key, PointOffsetType::MIN));
+                    let end = Bound::Included(Point::new(*key, PointOffsetType::MAX));
+                    Ok(index
+                        .values_range(start, end, hw_counter)?
+                        .filter(is_allowed))
+                })
+                .collect::<OperationResult<Vec<_>>>()?;
+            Ok(Box::new(iterators.into_iter().flatten().unique()))
+        }
+        CompiledKeys::Range(range) => {
+            let (start_bound, end_bound) = range.as_index_key_bounds();
 
-    // map.range
-    // Panics if range start > end. Panics if range start == end and both bounds are Excluded.
-    if !check_boundaries(&start_bound, &end_bound) {
-        return Ok(Some(Box::new(std::iter::empty())));
-    }
+            // map.range
+            // Panics if range start > end. Panics if range start == end and both bounds are Excluded.
+            if !check_boundaries(&start_bound, &end_bound) {
+                return Ok(Box::new(std::iter::empty()));
+            }
 
-    Ok(Some(Box::new(index.values_range(
-        start_bound,
-        end_bound,
-        hw_counter,
-    )?)))
+            Ok(Box::new(
+                index
+                    .values_range(start_bound, end_bound, hw_counter)?
+                    .filter(is_allowed),
+            ))
+        }
+    }
 }
 
 /// Cardinality estimation for a `match`/`range` field condition.
@@ -188,39 +435,46 @@ where
     T: Encodable + Numericable + StoredValue + Send + Sync + Default,
     I: NumericIndexRead<T>,
 {
-    if let Some(Match::Value(MatchValue {
-        value: ValueVariants::String(keyword),
-    })) = &condition.r#match
-    {
-        let keyword = keyword.as_str();
-        if let Ok(uuid) = Uuid::from_str(keyword) {
-            let key = T::from_u128(uuid.as_u128());
-
-            let estimated_count = estimate_points(index, &key, hw_counter)?;
-            return Ok(Some(
-                CardinalityEstimation::exact(estimated_count)
-                    .with_primary_clause(PrimaryCondition::Condition(Box::new(condition.clone()))),
-            ));
-        }
-    }
-
-    condition
-        .range
-        .as_ref()
-        .map(|range| {
-            let mut cardinality = range_cardinality(index, range)?;
-            cardinality
-                .primary_clauses
-                .push(PrimaryCondition::Condition(Box::new(condition.clone())));
-            Ok(cardinality)
-        })
+    CompiledNumericCondition::compile(condition)
+        .map(|compiled| estimate_compiled(index, &compiled, hw_counter))
         .transpose()
 }
 
+/// Cardinality estimation for a [`CompiledNumericCondition`].
+pub(super) fn estimate_compiled<T, I>(
+    index: &I,
+    compiled: &CompiledNumericCondition<T>,
+    hw_counter: &HardwareCounterCell,
+) -> OperationResult<CardinalityEstimation>
+where
+    T: Encodable + Numericable + StoredValue + Send + Sync + Default,
+    I: NumericIndexRead<T>,
+{
+    let estimation = match &compiled.keys {
+        CompiledKeys::Value(key) => {
+            CardinalityEstimation::exact(estimate_points(index, key, hw_counter)?)
+        }
+        CompiledKeys::Any(keys) => match_any_cardinality(index, keys, hw_counter)?,
+        CompiledKeys::Range(range) => typed_range_cardinality(index, range, hw_counter)?,
+    };
+    Ok(
+        estimation.with_primary_clause(PrimaryCondition::Condition(Box::new(
+            compiled.condition.clone(),
+        ))),
+    )
+}
+
+/// Range of a payload block and its estimated cardinality.
+type RangeBlock = (Range<OrderedFloat<f64>>, usize);
+
 /// Iterate histogram-balanced payload blocks of at least `threshold` size.
+///
+/// Blocks are emitted in ascending order of their ranges. With `max_blocks`, adjacent blocks
+/// are merged until there are at most that many, see [`merge_payload_blocks`].
 pub(super) fn for_each_payload_block<T, I>(
     index: &I,
     threshold: usize,
+    max_blocks: Option<usize>,
     key: PayloadKeyType,
     f: &mut dyn FnMut(PayloadBlockCondition) -> OperationResult<()>,
 ) -> OperationResult<()>
@@ -228,10 +482,12 @@ where
     T: Encodable + Numericable + StoredValue + Send + Sync + Default,
     I: NumericIndexRead<T>,
 {
-    let collect_blocks = || -> OperationResult<Vec<PayloadBlockCondition>> {
+    // Only used while building HNSW, which intentionally bypasses hardware measurement
+    let hw_counter = HardwareCounterCell::disposable();
+    let collect_blocks = || -> OperationResult<Vec<RangeBlock>> {
         let mut lower_bound = Unbounded;
         let mut pre_lower_bound: Option<Bound<T>> = None;
-        let mut payload_conditions = Vec::new();
+        let mut blocks = Vec::new();
 
         let value_per_point =
             index.total_unique_values_count()? as f64 / index.get_points_count() as f64;
@@ -261,27 +517,18 @@ where
                         Excluded(_) | Unbounded => None,
                     },
                 };
-                let cardinality = range_cardinality(index, &RangeInterface::Float(range))?;
-                let condition = PayloadBlockCondition {
-                    condition: FieldCondition::new_range(key.clone(), range),
-                    cardinality: cardinality.exp,
-                };
-
-                payload_conditions.push(condition);
+                let cardinality =
+                    range_cardinality(index, &RangeInterface::Float(range), &hw_counter)?;
+                blocks.push((range, cardinality.exp));
             } else if upper_bound == Unbounded {
                 // One block covers all points
-                payload_conditions.push(PayloadBlockCondition {
-                    condition: FieldCondition::new_range(
-                        key.clone(),
-                        Range {
-                            gte: None,
-                            lte: None,
-                            lt: None,
-                            gt: None,
-                        },
-                    ),
-                    cardinality: index.get_points_count(),
-                });
+                let range = Range {
+                    gte: None,
+                    lte: None,
+                    lt: None,
+                    gt: None,
+                };
+                blocks.push((range, index.get_points_count()));
             }
 
             pre_lower_bound = Some(lower_bound);
@@ -292,10 +539,72 @@ where
                 Unbounded => break,
             };
         }
-        Ok(payload_conditions)
+        Ok(blocks)
     };
 
-    collect_blocks()?.into_iter().try_for_each(f)
+    let mut blocks = collect_blocks()?;
+    if let Some(max_blocks) = max_blocks {
+        blocks = merge_payload_blocks(blocks, max_blocks);
+    }
+
+    blocks
+        .into_iter()
+        .map(|(range, cardinality)| PayloadBlockCondition {
+            condition: FieldCondition::new_range(key.clone(), range),
+            cardinality,
+        })
+        .try_for_each(f)
+}
+
+/// Merge runs of adjacent blocks, so at most `max_blocks` remain.
+///
+/// Blocks are swept in order and grouped until a group reaches the average cardinality of
+/// `max_blocks` groups, so small blocks are merged into their neighbours first. The ranges
+/// must be ascending and adjacent; a merged block spans from the first to the last range of
+/// its group. Its cardinality is the sum of the merged ones.
+fn merge_payload_blocks(blocks: Vec<RangeBlock>, max_blocks: usize) -> Vec<RangeBlock> {
+    let max_blocks = max_blocks.max(1);
+    if blocks.len() <= max_blocks {
+        return blocks;
+    }
+
+    let total_cardinality: usize = blocks.iter().map(|(_, cardinality)| cardinality).sum();
+    // Every closed group holds at least this much, so at most `max_blocks` groups are closed
+    let group_cardinality = total_cardinality.div_ceil(max_blocks).max(1);
+
+    let mut merged: Vec<RangeBlock> = Vec::with_capacity(max_blocks);
+    let mut group: Option<RangeBlock> = None;
+    for (range, cardinality) in blocks {
+        let (group_range, group_size) = match group.take() {
+            None => (range, cardinality),
+            Some((group_range, group_size)) => (
+                Range {
+                    gt: group_range.gt,
+                    gte: group_range.gte,
+                    lt: range.lt,
+                    lte: range.lte,
+                },
+                group_size + cardinality,
+            ),
+        };
+        if group_size >= group_cardinality {
+            merged.push((group_range, group_size));
+        } else {
+            group = Some((group_range, group_size));
+        }
+    }
+
+    if let Some((range, cardinality)) = group {
+        if merged.len() < max_blocks {
+            merged.push((range, cardinality));
+        } else if let Some((last_range, last_cardinality)) = merged.last_mut() {
+            // Only blocks without points are left over
+            last_range.lt = range.lt;
+            last_range.lte = range.lte;
+            *last_cardinality += cardinality;
+        }
+    }
+    merged
 }
 
 /// Build a per-point checker closure for a `range` field condition, if the
@@ -323,19 +632,7 @@ where
         is_null: _,
     } = condition;
 
-    let range = range.as_ref()?;
-    // Convert the range bounds into the index's storage type `T`.
-    // `T::from_f64_range` / `T::from_u128` are total functions provided by
-    // `Numericable`, so every numeric variant (Int / Float / Datetime /
-    // Uuid) can serve any `RangeInterface` shape. For integer `T`, the
-    // float-range conversion rounds each bound *away* from the matching
-    // set so fractional bounds keep their `f64`-comparison semantics.
-    let typed_range = match range {
-        RangeInterface::Float(float_range) => T::from_f64_range(*float_range),
-        RangeInterface::DateTime(datetime_range) => {
-            datetime_range.map(|dt| T::from_u128(dt.timestamp() as u128))
-        }
-    };
+    let typed_range = typed_range::<T>(range.as_ref()?);
 
     let hw_counter = hw_acc.get_counter_cell();
     Some(Box::new(move |point_id: PointOffsetType| {
@@ -347,7 +644,8 @@ where
     }))
 }
 
-/// Stream `(value, point)` pairs of the given range in ascending order.
+/// Stream `(value, point)` pairs of the given range in ascending
+/// `(value, point)` order.
 ///
 /// The iterator is double-ended, so callers can also walk it in
 /// descending order.
@@ -359,13 +657,7 @@ where
     T: Encodable + Numericable + StoredValue + Send + Sync + Default,
     I: NumericIndexRead<T>,
 {
-    let range = match range {
-        RangeInterface::Float(float_range) => T::from_f64_range(*float_range),
-        RangeInterface::DateTime(datetime_range) => {
-            datetime_range.map(|dt| T::from_u128(dt.timestamp() as u128))
-        }
-    };
-    let (start_bound, end_bound) = range.as_index_key_bounds();
+    let (start_bound, end_bound) = typed_range::<T>(range).as_index_key_bounds();
 
     // map.range
     // Panics if range start > end. Panics if range start == end and both bounds are Excluded.
@@ -377,3 +669,47 @@ where
         index.orderable_values_range(start_bound, end_bound)?,
     ))
 }
+
+/// Page of [`stream_range`] following `cursor`, see [`super::StreamRange::stream_range_page`].
+pub(super) fn stream_range_page<T, I>(
+    index: &I,
+    range: &RangeInterface,
+    cursor: Option<&StreamCursor>,
+    limit: usize,
+) -> OperationResult<(Vec<(T, PointOffsetType)>, Option<StreamCursor>)>
+where
+    T: Encodable + Numericable + StoredValue + Send + Sync + Default,
+    I: NumericIndexRead<T>,
+{
+    if limit == 0 {
+        return Err(OperationError::validation_error(
+            "Range page limit must be greater than zero",
+        ));
+    }
+
+    let (mut start_bound, end_bound) = typed_range::<T>(range).as_index_key_bounds();
+    if let Some(cursor) = cursor {
+        // Seek past the last returned pair, unless it lies before the range
+        let last = cursor.decode::<T>()?;
+        let resume = match &start_bound {
+            Included(start) => last >= *start,
+            Excluded(start) => last > *start,
+            Unbounded => true,
+        };
+        if resume {
+            start_bound = Excluded(last);
+        }
+    }
+
+    if !check_boundaries(&start_bound, &end_bound) {
+        return Ok((Vec::new(), None));
+    }
+
+    let mut pairs = index.orderable_values_range(start_bound, end_bound)?;
+    let page = pairs.by_ref().take(limit).collect_vec();
+    let next_cursor = match page.last() {
+        Some((value, point)) if pairs.next().is_some() => Some(StreamCursor::new(value, *point)),
+        Some(_) | None => None,
+    };
+    Ok((page, next_cursor))
+}
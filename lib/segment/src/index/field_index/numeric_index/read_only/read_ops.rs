@@ -9,8 +9,8 @@ use common::types::PointOffsetType;
 use common::universal_io::UniversalRead;
 use gridstore::Blob;
 
-use super::super::Encodable;
 use super::super::numeric_index_read::NumericIndexRead;
+use super::super::{Encodable, NumericIndex, NumericIndexIntoInnerValue};
 use super::ReadOnlyNumericIndex;
 use crate::common::operation_error::OperationResult;
 use crate::index::field_index::histogram::Histogram;
@@ -95,6 +95,7 @@ impl<T: Encodable + Numericable + StoredValue + Send + Sync + Default, P, S: Uni
     PayloadFieldIndexRead for ReadOnlyNumericIndex<T, P, S>
 where
     Vec<T>: Blob,
+    NumericIndex<T, P>: NumericIndexIntoInnerValue<T, P>,
 {
     fn count_indexed_points(&self) -> usize {
         self.inner.count_indexed_points()
@@ -105,6 +106,9 @@ where
         condition: &'a FieldCondition,
         hw_counter: &'a HardwareCounterCell,
     ) -> OperationResult<Option<Box<dyn Iterator<Item = PointOffsetType> + 'a>>> {
+        if NumericIndex::<T, P>::is_unserved_match_any(condition) {
+            return Ok(None);
+        }
         self.inner.filter(condition, hw_counter)
     }
 
@@ -113,6 +117,9 @@ where
         condition: &FieldCondition,
         hw_counter: &HardwareCounterCell,
     ) -> OperationResult<Option<CardinalityEstimation>> {
+        if NumericIndex::<T, P>::is_unserved_match_any(condition) {
+            return Ok(None);
+        }
         self.inner.estimate_cardinality(condition, hw_counter)
     }
 
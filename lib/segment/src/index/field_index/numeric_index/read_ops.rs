@@ -1,31 +1,102 @@
 //! Read-path surface for the numeric-index module: the [`StreamRange`]
-//! trait, the [`Range`] → index-key-bounds conversion, and the
-//! [`PayloadFieldIndexRead`] implementation for [`NumericIndex`].
+//! trait, the [`Range`] → index-key-bounds conversion, the
+//! [`PayloadFieldIndexRead`] implementation for [`NumericIndex`] and its
+//! NULL-aware [`NumericIndex::filter_three_valued`] companion, and
+//! [`NumericIndex::validate_condition`].
 
 use std::ops::Bound;
 use std::ops::Bound::{Excluded, Included, Unbounded};
+use std::rc::Rc;
 
+use common::bitvec::BitVec;
 use common::counter::hardware_accumulator::HwMeasurementAcc;
 use common::counter::hardware_counter::HardwareCounterCell;
 use common::types::PointOffsetType;
 use gridstore::Blob;
 use serde_json::Value;
 
-use super::{Encodable, NumericIndex};
-use crate::common::operation_error::OperationResult;
+use super::numeric_index_read::NumericIndexRead;
+use super::query;
+use super::validation::{FilterError, check_range_order, check_uuid_keyword};
+use super::{Encodable, NumericIndex, NumericIndexIntoInnerValue};
+use crate::common::operation_error::{OperationError, OperationResult};
 use crate::index::field_index::numeric_point::{Numericable, Point};
 use crate::index::field_index::stored_point_to_values::StoredValue;
 use crate::index::field_index::{
-    CardinalityEstimation, PayloadBlockCondition, PayloadFieldIndexRead,
+    CardinalityEstimation, PayloadBlockCondition, PayloadFieldIndexRead, PrimaryCondition,
+    unsupported_condition_reason,
 };
 use crate::index::query_optimization::optimized_filter::ConditionCheckerFn;
-use crate::types::{FieldCondition, PayloadKeyType, Range, RangeInterface};
+use crate::types::{FieldCondition, Match, MatchValue, PayloadKeyType, Range, RangeInterface};
+
+/// Result of [`NumericIndex::filter_three_valued`]: points matching the
+/// condition, points with values that don't match it, and points without
+/// values.
+pub type ThreeValuedFilter<'a> = (
+    Box<dyn Iterator<Item = PointOffsetType> + 'a>,
+    Box<dyn Iterator<Item = PointOffsetType> + 'a>,
+    Box<dyn Iterator<Item = PointOffsetType> + 'a>,
+);
 
 pub trait StreamRange<T> {
+    /// Stream `(value, point_id)` pairs of the given range, ordered by value and then by point
+    /// id, both ascending.
+    ///
+    /// The order is a total order including ties, identical for every storage variant and stable
+    /// across reopen, so it can be relied upon for keyset pagination.
     fn stream_range(
         &self,
         range: &RangeInterface,
     ) -> OperationResult<impl DoubleEndedIterator<Item = (T, PointOffsetType)> + '_>;
+
+    /// Page of [`Self::stream_range`]: at most `limit` pairs following `cursor`, or from the
+    /// start of the range without one.
+    ///
+    /// Also returns the cursor to resume after the last pair of the page, `None` once the range
+    /// is exhausted. Resuming seeks past the cursor instead of skipping the previous pages.
+    /// A `limit` of zero is rejected, an empty page couldn't tell whether the range is exhausted.
+    fn stream_range_page(
+        &self,
+        range: &RangeInterface,
+        cursor: Option<&StreamCursor>,
+        limit: usize,
+    ) -> OperationResult<(Vec<(T, PointOffsetType)>, Option<StreamCursor>)>;
+}
+
+/// Opaque position in a [`StreamRange::stream_range_page`] scan: the last `(value, point)` pair
+/// of a page, in the [`Encodable`] key format.
+///
+/// Only valid for the index field it was returned for. Since the pair order is stable across
+/// storage variants and reopen, a cursor stays valid when the index is converted or reloaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamCursor(Vec<u8>);
+
+impl StreamCursor {
+    pub(super) fn new<T: Encodable>(value: &T, point: PointOffsetType) -> Self {
+        Self(value.encode_key(point))
+    }
+
+    /// Restore a cursor from the bytes of [`Self::as_bytes`].
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Last pair returned before this cursor, rejects bytes which are not a key of `T`.
+    pub(super) fn decode<T: Encodable + Numericable + Default>(&self) -> OperationResult<Point<T>> {
+        let key_len = T::default().encode_key(PointOffsetType::MIN).len();
+        if self.0.len() != key_len {
+            return Err(OperationError::validation_error(format!(
+                "Invalid stream cursor: expected {key_len} bytes, got {}",
+                self.0.len(),
+            )));
+        }
+        let (point, value) = T::decode_key(&self.0);
+        Ok(Point::new(value, point))
+    }
 }
 
 impl<T: Encodable + Numericable> Range<T> {
@@ -48,10 +119,165 @@ impl<T: Encodable + Numericable> Range<T> {
     }
 }
 
+impl<T: Encodable + Numericable + StoredValue + Send + Sync + Default, P> NumericIndex<T, P>
+where
+    Vec<T>: Blob,
+    Self: NumericIndexIntoInnerValue<T, P>,
+{
+    /// Index key of an exact `match` condition on a payload type stored under
+    /// a different representation, see
+    /// [`NumericIndexIntoInnerValue::match_value_key`].
+    fn condition_match_key(condition: &FieldCondition) -> Option<T> {
+        let Some(Match::Value(MatchValue { value })) = &condition.r#match else {
+            return None;
+        };
+        Self::match_value_key(value)
+    }
+
+    /// Whether `condition` is a `match any` this index doesn't serve, see
+    /// [`NumericIndexIntoInnerValue::integer_keys`].
+    pub(super) fn is_unserved_match_any(condition: &FieldCondition) -> bool {
+        matches!(condition.r#match, Some(Match::Any(_))) && !Self::integer_keys()
+    }
+
+    /// Check `condition` for mistakes at query-build time.
+    ///
+    /// [`PayloadFieldIndexRead::filter`] returns `None` both for conditions this index can't
+    /// serve and for malformed ones, this tells the latter apart: a range with its lower bound
+    /// above the upper one, or a keyword which is not a UUID matched against a UUID index.
+    /// Conditions the index can't serve are not errors.
+    pub fn validate_condition(condition: &FieldCondition) -> Result<(), FilterError> {
+        if let Some(range) = &condition.range {
+            check_range_order(range)?;
+        }
+        if Self::uuid_keywords() {
+            check_uuid_keyword(condition)?;
+        }
+        Ok(())
+    }
+
+    /// Same as [`PayloadFieldIndexRead::filter`], but only yields points
+    /// passing `allowed`.
+    ///
+    /// The predicate is applied while iterating the index, so points rejected
+    /// by a more selective condition are skipped instead of being yielded and
+    /// discarded by the caller.
+    pub fn filter_prefiltered<'a>(
+        &'a self,
+        condition: &'a FieldCondition,
+        allowed: &'a impl Fn(PointOffsetType) -> bool,
+        hw_counter: &'a HardwareCounterCell,
+    ) -> OperationResult<Option<Box<dyn Iterator<Item = PointOffsetType> + 'a>>> {
+        if let Some(key) = Self::condition_match_key(condition) {
+            let start = Included(Point::new(key, PointOffsetType::MIN));
+            let end = Included(Point::new(key, PointOffsetType::MAX));
+            return Ok(Some(Box::new(
+                self.inner
+                    .values_range(start, end, hw_counter)?
+                    .filter(|&idx| allowed(idx)),
+            )));
+        }
+        if Self::is_unserved_match_any(condition) {
+            return Ok(None);
+        }
+        self.inner
+            .filter_prefiltered(condition, allowed, hw_counter)
+    }
+
+    /// Points with a value in `range`, once for each of their values in it.
+    ///
+    /// Unlike a range [`FieldCondition`], `range` must be of the kind of the payload type:
+    /// datetime ranges for datetime indexes, number ranges for all others. A mismatch is
+    /// rejected, rather than converting timestamps to numbers or the other way around.
+    pub fn range_query<'a>(
+        &'a self,
+        range: &RangeInterface,
+        hw_counter: &'a HardwareCounterCell,
+    ) -> OperationResult<Box<dyn Iterator<Item = PointOffsetType> + 'a>> {
+        match (range, Self::datetime_ranges()) {
+            (RangeInterface::Float(_), false) | (RangeInterface::DateTime(_), true) => {}
+            (RangeInterface::Float(_), true) => {
+                return Err(OperationError::validation_error(
+                    "Number range can't be applied to a datetime index",
+                ));
+            }
+            (RangeInterface::DateTime(_), false) => {
+                return Err(OperationError::validation_error(
+                    "Datetime range can't be applied to a non-datetime index",
+                ));
+            }
+        }
+        query::range_points(&self.inner, range, hw_counter)
+    }
+
+    /// Report the number of points a query actually matched for `condition`.
+    ///
+    /// The count is compared against the cardinality estimation of the condition, the running
+    /// error is reported in telemetry as
+    /// [`PayloadIndexTelemetry::cardinality_estimation_error`]. Conditions this index can't
+    /// estimate are ignored.
+    ///
+    /// [`PayloadIndexTelemetry::cardinality_estimation_error`]: crate::telemetry::PayloadIndexTelemetry::cardinality_estimation_error
+    pub fn record_actual_cardinality(
+        &self,
+        condition: &FieldCondition,
+        actual: usize,
+    ) -> OperationResult<()> {
+        // Internal operation, not charged to the query
+        let hw_counter = HardwareCounterCell::disposable();
+        if let Some(estimation) = self.estimate_cardinality(condition, &hw_counter)? {
+            self.cardinality_feedback.record(estimation.exp, actual);
+        }
+        Ok(())
+    }
+
+    /// NULL-aware counterpart of [`PayloadFieldIndexRead::filter`]: classifies
+    /// the points `0..num_points` into matching `condition`, having values that
+    /// don't match it, and having no value.
+    ///
+    /// The index only knows points that have values, so the caller provides the
+    /// range of point ids. Deleted points are classified as having no value.
+    ///
+    /// Returns `None` if the condition is not supported by this index.
+    pub fn filter_three_valued<'a>(
+        &'a self,
+        condition: &'a FieldCondition,
+        num_points: PointOffsetType,
+        hw_counter: &'a HardwareCounterCell,
+    ) -> OperationResult<Option<ThreeValuedFilter<'a>>> {
+        let Some(matches) = self.filter(condition, hw_counter)? else {
+            return Ok(None);
+        };
+
+        let mut matched = BitVec::repeat(false, num_points as usize);
+        for idx in matches {
+            if idx < num_points {
+                matched.set(idx as usize, true);
+            }
+        }
+        let matched = Rc::new(matched);
+
+        let matching = {
+            let matched = Rc::clone(&matched);
+            (0..num_points).filter(move |&idx| matched[idx as usize])
+        };
+        let non_matching = (0..num_points)
+            .filter(move |&idx| !matched[idx as usize] && !self.values_is_empty(idx));
+        let missing = (0..num_points).filter(move |&idx| self.values_is_empty(idx));
+
+        Ok(Some((
+            Box::new(matching),
+            Box::new(non_matching),
+            Box::new(missing),
+        )))
+    }
+}
+
 impl<T: Encodable + Numericable + StoredValue + Send + Sync + Default, P> PayloadFieldIndexRead
     for NumericIndex<T, P>
 where
     Vec<T>: Blob,
+    Self: NumericIndexIntoInnerValue<T, P>,
 {
     fn count_indexed_points(&self) -> usize {
         self.inner.count_indexed_points()
@@ -62,6 +288,16 @@ where
         condition: &'a FieldCondition,
         hw_counter: &'a HardwareCounterCell,
     ) -> OperationResult<Option<Box<dyn Iterator<Item = PointOffsetType> + 'a>>> {
+        if let Some(key) = Self::condition_match_key(condition) {
+            let start = Included(Point::new(key, PointOffsetType::MIN));
+            let end = Included(Point::new(key, PointOffsetType::MAX));
+            return Ok(Some(Box::new(
+                self.inner.values_range(start, end, hw_counter)?,
+            )));
+        }
+        if Self::is_unserved_match_any(condition) {
+            return Ok(None);
+        }
         self.inner.filter(condition, hw_counter)
     }
 
@@ -70,9 +306,30 @@ where
         condition: &FieldCondition,
         hw_counter: &HardwareCounterCell,
     ) -> OperationResult<Option<CardinalityEstimation>> {
+        if let Some(key) = Self::condition_match_key(condition) {
+            let start = Included(Point::new(key, PointOffsetType::MIN));
+            let end = Included(Point::new(key, PointOffsetType::MAX));
+            // A point holds a key at most once, so live pairs of the key are its matching points
+            let live_points = self.inner.live_values_range_size(start, end, hw_counter)?;
+            return Ok(Some(
+                CardinalityEstimation::exact(live_points)
+                    .with_primary_clause(PrimaryCondition::Condition(Box::new(condition.clone()))),
+            ));
+        }
+        if Self::is_unserved_match_any(condition) {
+            return Ok(None);
+        }
         self.inner.estimate_cardinality(condition, hw_counter)
     }
 
+    fn not_served_reason(&self, condition: &FieldCondition) -> String {
+        if Self::uuid_keywords() {
+            self.inner.not_served_reason(condition)
+        } else {
+            unsupported_condition_reason(condition)
+        }
+    }
+
     fn for_each_payload_block(
         &self,
         threshold: usize,
@@ -87,6 +344,13 @@ where
         condition: &FieldCondition,
         hw_acc: HwMeasurementAcc,
     ) -> Option<ConditionCheckerFn<'a>> {
+        if let Some(key) = Self::condition_match_key(condition) {
+            let hw_counter = hw_acc.get_counter_cell();
+            return Some(Box::new(move |point_id: PointOffsetType| {
+                self.inner
+                    .check_values_any(point_id, |value| *value == key, &hw_counter)
+            }));
+        }
         self.inner.condition_checker(condition, hw_acc)
     }
 
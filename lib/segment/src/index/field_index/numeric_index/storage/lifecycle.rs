@@ -1,21 +1,23 @@
 //! Construction, persistence, mutation, and cache-control forwarding for
 //! [`NumericIndexInner`].
 
+use std::ops::Bound;
 use std::path::{Path, PathBuf};
 
-use common::bitvec::BitSlice;
+use common::bitvec::{BitSlice, BitVec};
 use common::types::PointOffsetType;
 use common::universal_io::MmapFs;
 use gridstore::Blob;
 
 use super::super::Encodable;
 use super::super::immutable_numeric_index::ImmutableNumericIndex;
-use super::super::mutable_numeric_index::MutableNumericIndex;
-use super::super::universal_numeric_index::UniversalNumericIndex;
+use super::super::mutable_numeric_index::{InMemoryNumericIndex, MutableNumericIndex};
+use super::super::numeric_index_read::NumericIndexRead;
+use super::super::universal_numeric_index::{BuildOptions, UniversalNumericIndex};
 use super::NumericIndexInner;
 use crate::common::Flusher;
-use crate::common::operation_error::OperationResult;
-use crate::index::field_index::numeric_point::Numericable;
+use crate::common::operation_error::{OperationError, OperationResult};
+use crate::index::field_index::numeric_point::{Numericable, Point};
 use crate::index::field_index::stored_point_to_values::StoredValue;
 
 impl<T: Encodable + Numericable + StoredValue + Send + Sync + Default> NumericIndexInner<T>
@@ -58,6 +60,86 @@ where
             .map(NumericIndexInner::Mutable))
     }
 
+    /// Write the live values of this index into a new mmap index at `path`, which must not be
+    /// the directory of this index. Supported for every variant.
+    ///
+    /// Point ids, the key order and the datetime precision are kept. Deleted points become points
+    /// without values.
+    pub fn to_mmap(&self, path: &Path, is_on_disk: bool) -> OperationResult<Self> {
+        let mut in_memory_index = InMemoryNumericIndex::default();
+        self.for_each_point_values(|idx, values| {
+            in_memory_index.add_many_to_list(idx, values.to_vec());
+        })?;
+        // Cover the same point ids, even if the last ones have no values
+        in_memory_index
+            .point_to_values
+            .resize_with(self.has_value_bitmap().len(), Vec::new);
+
+        let index = UniversalNumericIndex::build_with_options(
+            &MmapFs,
+            in_memory_index,
+            path,
+            is_on_disk,
+            &BitVec::new(),
+            BuildOptions {
+                order: self.key_order(),
+                datetime_precision: self.datetime_precision(),
+            },
+        )?;
+        Ok(NumericIndexInner::Mmap(index))
+    }
+
+    /// Load an mmap index into RAM, serving it as [`NumericIndexInner::Immutable`] on top of the
+    /// same files, without reopening them. Inverse of [`Self::into_mmap`]. Deletions are kept.
+    ///
+    /// Immutable indexes are returned as is. Mutable indexes have no mmap files to load, they are
+    /// handed back with the error, see [`Self::to_mmap`] instead. So are mmap indexes whose files
+    /// can't be populated.
+    pub fn into_immutable(self) -> Result<Self, (OperationError, Box<Self>)> {
+        match self {
+            NumericIndexInner::Mutable(_) => Err((
+                OperationError::service_error(
+                    "Can't load a mutable numeric index as immutable, convert it to mmap first",
+                ),
+                Box::new(self),
+            )),
+            NumericIndexInner::Immutable(index) => Ok(NumericIndexInner::Immutable(index)),
+            NumericIndexInner::Mmap(mut index) => match index.set_on_disk(false) {
+                Ok(()) => Ok(NumericIndexInner::Immutable(
+                    ImmutableNumericIndex::open_mmap(index),
+                )),
+                Err(err) => Err((err, Box::new(NumericIndexInner::Mmap(index)))),
+            },
+        }
+    }
+
+    /// Drop the RAM copy of an immutable index, serving it from its backing mmap files as an
+    /// on-disk [`NumericIndexInner::Mmap`] index without reopening them. Reclaims memory under
+    /// pressure, see [`Self::into_immutable`] for the way back.
+    ///
+    /// Deletions are kept, a histogram refreshed in RAM is replaced by the persisted one. Mmap
+    /// indexes are moved to disk. Mutable indexes have no backing mmap files, they are handed back
+    /// with the error, see [`Self::to_mmap`] instead. So are indexes whose files can't be dropped
+    /// from the cache.
+    pub fn into_mmap(self) -> Result<Self, (OperationError, Box<Self>)> {
+        match self {
+            NumericIndexInner::Mutable(_) => Err((
+                OperationError::service_error(
+                    "Mutable numeric index has no mmap files to serve, convert it with `to_mmap`",
+                ),
+                Box::new(self),
+            )),
+            NumericIndexInner::Immutable(mut index) => match index.storage.set_on_disk(true) {
+                Ok(()) => Ok(NumericIndexInner::Mmap(*index.storage)),
+                Err(err) => Err((err, Box::new(NumericIndexInner::Immutable(index)))),
+            },
+            NumericIndexInner::Mmap(mut index) => match index.set_on_disk(true) {
+                Ok(()) => Ok(NumericIndexInner::Mmap(index)),
+                Err(err) => Err((err, Box::new(NumericIndexInner::Mmap(index)))),
+            },
+        }
+    }
+
     pub fn flusher(&self) -> Flusher {
         match self {
             NumericIndexInner::Mutable(index) => index.flusher(),
@@ -91,6 +173,38 @@ where
         Ok(())
     }
 
+    /// Remove a batch of points at once. `ids` must be ascending.
+    pub fn remove_points(&mut self, ids: &[PointOffsetType]) -> OperationResult<()> {
+        match self {
+            NumericIndexInner::Mutable(index) => {
+                for &idx in ids {
+                    index.remove_point(idx)?;
+                }
+            }
+            NumericIndexInner::Immutable(index) => index.remove_points(ids),
+            NumericIndexInner::Mmap(index) => index.remove_points(ids),
+        }
+        Ok(())
+    }
+
+    /// Rebuild the histogram from the live values, keeping its bucket size and precision.
+    ///
+    /// Restores the accuracy of cardinality estimations after many deletions, in particular for
+    /// mmap indexes, whose histogram is not updated by runtime deletions. Like those deletions,
+    /// the rebuilt histogram of an mmap index is not persisted.
+    pub fn rebuild_histogram(&mut self) -> OperationResult<()> {
+        let histogram = self.get_histogram().rebuilt(
+            self.orderable_values_range(Bound::Unbounded, Bound::Unbounded)?
+                .map(|(val, idx)| Point::new(val, idx)),
+        );
+        match self {
+            NumericIndexInner::Mutable(index) => index.in_memory_index.histogram = histogram,
+            NumericIndexInner::Immutable(index) => index.set_histogram(histogram),
+            NumericIndexInner::Mmap(index) => index.histogram = histogram,
+        }
+        Ok(())
+    }
+
     /// Populate all pages in the mmap.
     /// Block until all pages are populated.
     pub fn populate(&self) -> OperationResult<()> {
@@ -102,6 +216,25 @@ where
         Ok(())
     }
 
+    /// Move an `Mmap` index between disk and RAM, see
+    /// [`UniversalNumericIndex::set_on_disk`].
+    ///
+    /// The other variants are held in RAM, and can only be moved to disk by
+    /// reopening them as mmap index.
+    pub fn set_on_disk(&mut self, on_disk: bool) -> OperationResult<()> {
+        match self {
+            NumericIndexInner::Mutable(_) | NumericIndexInner::Immutable(_) => {
+                if on_disk {
+                    return Err(OperationError::service_error(
+                        "Only mmap numeric index can be moved to disk at runtime",
+                    ));
+                }
+            }
+            NumericIndexInner::Mmap(index) => index.set_on_disk(on_disk)?,
+        }
+        Ok(())
+    }
+
     /// Drop disk cache.
     pub fn clear_cache(&self) -> OperationResult<()> {
         match self {
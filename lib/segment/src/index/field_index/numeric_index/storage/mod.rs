@@ -5,8 +5,8 @@
 //! operation to the active variant. The forwarding impls are split
 //! across sibling modules:
 //!
-//! - [`lifecycle`]: construction, persistence, file listing, cache
-//!   control, and `remove_point`.
+//! - [`lifecycle`]: construction, conversion between variants,
+//!   persistence, file listing, cache control, and `remove_point`.
 //! - [`read_ops`]: read-path forwarding — value lookups, telemetry,
 //!   RAM accounting, `is_on_disk`.
 //! - [`trait_impls`]: [`PayloadFieldIndex`], [`PayloadFieldIndexRead`],
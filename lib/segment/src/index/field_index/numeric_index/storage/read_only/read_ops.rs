@@ -13,7 +13,7 @@ use common::universal_io::UniversalRead;
 use gridstore::Blob;
 
 use super::super::super::numeric_index_read::NumericIndexRead;
-use super::super::super::{Encodable, StreamRange, query};
+use super::super::super::{Encodable, StreamCursor, StreamRange, query};
 use super::ReadOnlyNumericIndexInner;
 use crate::common::operation_error::OperationResult;
 use crate::index::field_index::histogram::Histogram;
@@ -151,4 +151,13 @@ where
     ) -> OperationResult<impl DoubleEndedIterator<Item = (T, PointOffsetType)> + '_> {
         query::stream_range(self, range)
     }
+
+    fn stream_range_page(
+        &self,
+        range: &RangeInterface,
+        cursor: Option<&StreamCursor>,
+        limit: usize,
+    ) -> OperationResult<(Vec<(T, PointOffsetType)>, Option<StreamCursor>)> {
+        query::stream_range_page(self, range, cursor, limit)
+    }
 }
@@ -53,7 +53,7 @@ where
         key: PayloadKeyType,
         f: &mut dyn FnMut(PayloadBlockCondition) -> OperationResult<()>,
     ) -> OperationResult<()> {
-        query::for_each_payload_block(self, threshold, key, f)
+        query::for_each_payload_block(self, threshold, None, key, f)
     }
 
     fn condition_checker<'a>(
@@ -2,23 +2,30 @@
 //!
 //! Forwards every read-path method to the active storage variant. Each
 //! variant already implements [`NumericIndexRead`]; this impl just picks
-//! the arm. `point_ids_by_value` is an enum-only convenience wrapper that
-//! isn't part of the shared trait.
+//! the arm. The inherent methods are enum-only convenience wrappers that
+//! aren't part of the shared trait, mostly forwarding to the shared query
+//! logic in [`query`].
 
 use std::ops::Bound;
 
+use common::bitvec::BitVec;
 use common::counter::hardware_counter::HardwareCounterCell;
 use common::types::PointOffsetType;
 use gridstore::Blob;
 
-use super::super::Encodable;
+use super::super::compiled_condition::CompiledNumericCondition;
 use super::super::numeric_index_read::NumericIndexRead;
+use super::super::query;
+use super::super::universal_numeric_index::DateTimePrecision;
+use super::super::{Encodable, KeyOrder};
 use super::NumericIndexInner;
-use crate::common::operation_error::OperationResult;
+use crate::common::operation_error::{OperationError, OperationResult};
 use crate::index::field_index::histogram::Histogram;
 use crate::index::field_index::numeric_point::{Numericable, Point};
 use crate::index::field_index::stored_point_to_values::StoredValue;
+use crate::index::field_index::{CardinalityEstimation, PayloadBlockCondition};
 use crate::index::payload_config::StorageType;
+use crate::types::{FieldCondition, FloatPayloadType, PayloadKeyType, RangeInterface};
 
 impl<T: Encodable + Numericable + StoredValue + Send + Sync + Default> NumericIndexRead<T>
     for NumericIndexInner<T>
@@ -105,6 +112,44 @@ where
         Ok(boxed)
     }
 
+    fn values_range_size(
+        &self,
+        start_bound: Bound<Point<T>>,
+        end_bound: Bound<Point<T>>,
+        hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<usize> {
+        match self {
+            NumericIndexInner::Mutable(index) => {
+                index.values_range_size(start_bound, end_bound, hw_counter)
+            }
+            NumericIndexInner::Immutable(index) => {
+                index.values_range_size(start_bound, end_bound, hw_counter)
+            }
+            NumericIndexInner::Mmap(index) => {
+                index.values_range_size(start_bound, end_bound, hw_counter)
+            }
+        }
+    }
+
+    fn live_values_range_size(
+        &self,
+        start_bound: Bound<Point<T>>,
+        end_bound: Bound<Point<T>>,
+        hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<usize> {
+        match self {
+            NumericIndexInner::Mutable(index) => {
+                index.live_values_range_size(start_bound, end_bound, hw_counter)
+            }
+            NumericIndexInner::Immutable(index) => {
+                index.live_values_range_size(start_bound, end_bound, hw_counter)
+            }
+            NumericIndexInner::Mmap(index) => {
+                index.live_values_range_size(start_bound, end_bound, hw_counter)
+            }
+        }
+    }
+
     fn get_histogram(&self) -> &Histogram<T> {
         match self {
             NumericIndexInner::Mutable(index) => index.get_histogram(),
@@ -173,6 +218,202 @@ where
         self.values_range(start, end, hw_counter)
     }
 
+    /// Number of points with a value in `range`, without materializing their ids.
+    ///
+    /// See [`query::count_in_range`].
+    pub fn count_in_range(
+        &self,
+        range: &RangeInterface,
+        hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<usize> {
+        query::count_in_range(self, range, hw_counter)
+    }
+
+    /// Expected number of sorted entries in `range` to read before `limit` live matches
+    /// accumulate, for choosing between an index-order scan and filter-then-sort.
+    ///
+    /// Entries of deleted points are assumed to be spread evenly, so the estimate grows with
+    /// the share of entries in `range` left behind by deleted points. See
+    /// [`query::estimate_scan_depth`].
+    pub fn estimate_scan_depth(
+        &self,
+        range: &RangeInterface,
+        limit: usize,
+        hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<usize> {
+        query::estimate_scan_depth(self, range, limit, hw_counter)
+    }
+
+    /// [`PayloadFieldIndexRead::filter`] yielding only points passing `allowed`.
+    ///
+    /// See [`query::filter_prefiltered`].
+    ///
+    /// [`PayloadFieldIndexRead::filter`]: crate::index::field_index::PayloadFieldIndexRead::filter
+    pub fn filter_prefiltered<'a>(
+        &'a self,
+        condition: &FieldCondition,
+        allowed: &'a impl Fn(PointOffsetType) -> bool,
+        hw_counter: &'a HardwareCounterCell,
+    ) -> OperationResult<Option<Box<dyn Iterator<Item = PointOffsetType> + 'a>>> {
+        query::filter_prefiltered(self, condition, allowed, hw_counter)
+    }
+
+    /// Convert `condition` to keys of this index type once, to query the indexes of many
+    /// segments with [`Self::filter_compiled`] and [`Self::estimate_compiled`].
+    ///
+    /// `None` if the condition is not one a numeric index can serve.
+    pub fn compile_condition(condition: &FieldCondition) -> Option<CompiledNumericCondition<T>> {
+        CompiledNumericCondition::compile(condition)
+    }
+
+    /// [`PayloadFieldIndexRead::filter`] for a compiled condition.
+    ///
+    /// [`PayloadFieldIndexRead::filter`]: crate::index::field_index::PayloadFieldIndexRead::filter
+    pub fn filter_compiled<'a>(
+        &'a self,
+        compiled: &CompiledNumericCondition<T>,
+        hw_counter: &'a HardwareCounterCell,
+    ) -> OperationResult<Box<dyn Iterator<Item = PointOffsetType> + 'a>> {
+        fn allow_all(_: PointOffsetType) -> bool {
+            true
+        }
+        query::filter_compiled(self, compiled, &allow_all, hw_counter)
+    }
+
+    /// [`PayloadFieldIndexRead::estimate_cardinality`] for a compiled condition.
+    ///
+    /// [`PayloadFieldIndexRead::estimate_cardinality`]: crate::index::field_index::PayloadFieldIndexRead::estimate_cardinality
+    pub fn estimate_compiled(
+        &self,
+        compiled: &CompiledNumericCondition<T>,
+        hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<CardinalityEstimation> {
+        query::estimate_compiled(self, compiled, hw_counter)
+    }
+
+    /// [`PayloadFieldIndexRead::for_each_payload_block`] emitting at most `max_blocks` blocks.
+    ///
+    /// Adjacent blocks are merged to respect the cap, smallest first. Blocks are emitted in
+    /// ascending order of their ranges, so an unchanged index always yields the same blocks.
+    ///
+    /// [`PayloadFieldIndexRead::for_each_payload_block`]: crate::index::field_index::PayloadFieldIndexRead::for_each_payload_block
+    pub fn for_each_payload_block_capped(
+        &self,
+        threshold: usize,
+        max_blocks: usize,
+        key: PayloadKeyType,
+        f: &mut dyn FnMut(PayloadBlockCondition) -> OperationResult<()>,
+    ) -> OperationResult<()> {
+        query::for_each_payload_block(self, threshold, Some(max_blocks), key, f)
+    }
+
+    /// Key of `value` for point `id` in the [`Encodable`] format, for tools
+    /// seeking into raw storage. Keys sort in the same order as the index
+    /// orders its `(value, point)` pairs.
+    pub fn encode_key(value: &T, id: PointOffsetType) -> Vec<u8> {
+        value.encode_key(id)
+    }
+
+    /// Inverse of [`Self::encode_key`].
+    pub fn decode_key(key: &[u8]) -> (PointOffsetType, T) {
+        T::decode_key(key)
+    }
+
+    /// Call `f` once for every live point with the point's values, in ascending point order.
+    /// Deleted points and points without values are skipped.
+    ///
+    /// Meant for full scans: walks the backing storage once instead of boxing an iterator per
+    /// point like [`NumericIndexRead::get_values`] does.
+    pub fn for_each_point_values(
+        &self,
+        f: impl FnMut(PointOffsetType, &[T]),
+    ) -> OperationResult<()> {
+        match self {
+            NumericIndexInner::Mutable(index) => {
+                index.in_memory_index.for_each_point_values(f);
+                Ok(())
+            }
+            NumericIndexInner::Immutable(index) => {
+                index.for_each_point_values(f);
+                Ok(())
+            }
+            NumericIndexInner::Mmap(index) => index.for_each_point_values(f),
+        }
+    }
+
+    /// A bit per point id, set for points with at least one value. Bits of deleted points and
+    /// points without values are clear.
+    ///
+    /// Covers the point ids known to the index, higher ids have no value.
+    pub fn has_value_bitmap(&self) -> BitVec {
+        match self {
+            NumericIndexInner::Mutable(index) => index.in_memory_index.has_value_bitmap(),
+            NumericIndexInner::Immutable(index) => index.has_value_bitmap(),
+            NumericIndexInner::Mmap(index) => index.has_value_bitmap(),
+        }
+    }
+
+    /// Order in which reads scan the index forward. Ordered reads in the other direction walk
+    /// it backwards, with the same results.
+    ///
+    /// Only mmap indexes can be built in descending order, in-memory variants are ascending.
+    pub fn key_order(&self) -> KeyOrder {
+        match self {
+            NumericIndexInner::Mutable(_) | NumericIndexInner::Immutable(_) => KeyOrder::Ascending,
+            NumericIndexInner::Mmap(index) => index.order(),
+        }
+    }
+
+    /// Unit of stored datetime timestamps, see [`UniversalNumericIndex::datetime_precision`].
+    /// Mutable indexes are never migrated.
+    ///
+    /// [`UniversalNumericIndex::datetime_precision`]: super::super::universal_numeric_index::UniversalNumericIndex::datetime_precision
+    pub fn datetime_precision(&self) -> Option<DateTimePrecision> {
+        match self {
+            NumericIndexInner::Mutable(_) => None,
+            NumericIndexInner::Immutable(index) => index.storage.datetime_precision(),
+            NumericIndexInner::Mmap(index) => index.datetime_precision(),
+        }
+    }
+
+    /// Check the internal invariants of the index:
+    ///
+    /// - `(value, point)` pairs are sorted and refer to known points
+    /// - deletion flags agree with the deleted count
+    /// - the number of pairs matches the distinct values of all points
+    ///
+    /// Meant for diagnostics, reads the whole index. Returns an
+    /// [`OperationError::InconsistentStorage`] describing the first violation.
+    pub fn verify_integrity(&self) -> OperationResult<()> {
+        match self {
+            NumericIndexInner::Mutable(index) => index.in_memory_index.verify_integrity(),
+            NumericIndexInner::Immutable(index) => index.verify_integrity(),
+            NumericIndexInner::Mmap(index) => index.verify_integrity(),
+        }
+    }
+
+    /// Whether the share of deleted points exceeds `threshold`, so that rebuilding the index
+    /// would reclaim enough space to be worth it.
+    ///
+    /// The mutable variant removes points in place and never needs compaction.
+    pub fn needs_compaction(&self, threshold: f64) -> bool {
+        match self {
+            NumericIndexInner::Mutable(_) => false,
+            NumericIndexInner::Immutable(_) | NumericIndexInner::Mmap(_) => {
+                self.deleted_ratio() > threshold
+            }
+        }
+    }
+
+    /// Share of stored points that are deleted but still present in the sorted storage.
+    fn deleted_ratio(&self) -> f64 {
+        match self {
+            NumericIndexInner::Mutable(_) => 0.0,
+            NumericIndexInner::Immutable(index) => index.deleted_ratio(),
+            NumericIndexInner::Mmap(index) => index.deleted_ratio(),
+        }
+    }
+
     pub fn is_on_disk(&self) -> bool {
         match self {
             NumericIndexInner::Mutable(_) => false,
@@ -181,3 +422,25 @@ where
         }
     }
 }
+
+impl NumericIndexInner<FloatPayloadType> {
+    /// All point IDs with a value in `[value - epsilon, value + epsilon]`.
+    ///
+    /// Float counterpart of [`Self::point_ids_by_value`]: exact matches rarely
+    /// happen for floats because of representation error.
+    pub fn point_ids_near<'a>(
+        &'a self,
+        value: FloatPayloadType,
+        epsilon: FloatPayloadType,
+        hw_counter: &'a HardwareCounterCell,
+    ) -> OperationResult<impl Iterator<Item = PointOffsetType> + 'a> {
+        if !value.is_finite() || !epsilon.is_finite() || epsilon < 0.0 {
+            return Err(OperationError::validation_error(format!(
+                "Invalid approximate match of {value} with tolerance {epsilon}",
+            )));
+        }
+        let start = Bound::Included(Point::new(value - epsilon, PointOffsetType::MIN));
+        let end = Bound::Included(Point::new(value + epsilon, PointOffsetType::MAX));
+        self.values_range(start, end, hw_counter)
+    }
+}
@@ -19,7 +19,8 @@ use common::types::PointOffsetType;
 use gridstore::Blob;
 
 use super::super::numeric_index_read::NumericIndexRead;
-use super::super::{Encodable, StreamRange, query};
+use super::super::validation::check_uuid_keyword;
+use super::super::{Encodable, StreamCursor, StreamRange, query};
 use super::NumericIndexInner;
 use crate::common::Flusher;
 use crate::common::operation_error::OperationResult;
@@ -27,6 +28,7 @@ use crate::index::field_index::numeric_point::Numericable;
 use crate::index::field_index::stored_point_to_values::StoredValue;
 use crate::index::field_index::{
     CardinalityEstimation, PayloadBlockCondition, PayloadFieldIndex, PayloadFieldIndexRead,
+    unsupported_condition_reason,
 };
 use crate::index::query_optimization::optimized_filter::ConditionCheckerFn;
 use crate::types::{FieldCondition, PayloadKeyType, RangeInterface};
@@ -82,13 +84,21 @@ where
         query::estimate_cardinality(self, condition, hw_counter)
     }
 
+    fn not_served_reason(&self, condition: &FieldCondition) -> String {
+        // Keywords are served as UUIDs, name the keyword which is not one
+        match check_uuid_keyword(condition) {
+            Ok(()) => unsupported_condition_reason(condition),
+            Err(err) => err.to_string(),
+        }
+    }
+
     fn for_each_payload_block(
         &self,
         threshold: usize,
         key: PayloadKeyType,
         f: &mut dyn FnMut(PayloadBlockCondition) -> OperationResult<()>,
     ) -> OperationResult<()> {
-        query::for_each_payload_block(self, threshold, key, f)
+        query::for_each_payload_block(self, threshold, None, key, f)
     }
 
     fn condition_checker<'a>(
@@ -111,4 +121,13 @@ where
     ) -> OperationResult<impl DoubleEndedIterator<Item = (T, PointOffsetType)> + '_> {
         query::stream_range(self, range)
     }
+
+    fn stream_range_page(
+        &self,
+        range: &RangeInterface,
+        cursor: Option<&StreamCursor>,
+        limit: usize,
+    ) -> OperationResult<(Vec<(T, PointOffsetType)>, Option<StreamCursor>)> {
+        query::stream_range_page(self, range, cursor, limit)
+    }
 }
@@ -1,28 +1,40 @@
+use std::ops::Bound::{Excluded, Included, Unbounded};
 use std::path::Path;
 
 use common::bitvec::{BitSlice, BitVec};
 use common::counter::hardware_accumulator::HwMeasurementAcc;
 use common::counter::hardware_counter::HardwareCounterCell;
 use common::types::PointOffsetType;
+use common::universal_io::{MmapFile, MmapFs};
 use gridstore::Blob;
 use itertools::Itertools;
 use ordered_float::OrderedFloat;
 use rand::prelude::StdRng;
+use rand::seq::SliceRandom as _;
 use rand::{RngExt, SeedableRng};
 use rstest::rstest;
 use serde_json::Value;
 use tempfile::{Builder, TempDir};
 
 use super::immutable_numeric_index::ImmutableNumericIndex;
+use super::mutable_numeric_index::{InMemoryNumericIndex, MutableNumericIndex};
+use super::universal_numeric_index::{BuildOptions, UniversalNumericIndex};
 use super::*;
-use crate::common::operation_error::OperationResult;
-use crate::index::field_index::numeric_point::Numericable;
+use crate::common::operation_error::{OperationError, OperationResult};
+use crate::index::field_index::histogram::Histogram;
+use crate::index::field_index::numeric_point::{Numericable, Point};
 use crate::index::field_index::stored_point_to_values::StoredValue;
+use crate::index::field_index::utils::check_boundaries;
 use crate::index::field_index::{
-    CardinalityEstimation, FieldIndexBuilderTrait, PayloadFieldIndexRead, ValueIndexer,
+    CardinalityEstimation, ConditionServeability, FieldIndexBuilderTrait, PayloadBlockCondition,
+    PayloadFieldIndexRead, ValueIndexer,
 };
+use crate::index::payload_config::StorageType;
 use crate::json_path::JsonPath;
-use crate::types::{FieldCondition, FloatPayloadType, Range, RangeInterface};
+use crate::types::{
+    DateTimePayloadType, FieldCondition, FloatPayloadType, IntPayloadType, Match, Range,
+    RangeInterface, UuidIntType, UuidPayloadType, ValueVariants,
+};
 
 /// Generous default size for the deleted-points bitslice used in tests.
 ///
@@ -151,6 +163,8 @@ fn random_index(
         };
         index = NumericIndex {
             inner: NumericIndexInner::Immutable(ImmutableNumericIndex::open_mmap(mmap_index)),
+            out_of_range_policy: OutOfRangePolicy::default(),
+            cardinality_feedback: Default::default(),
             _phantom: Default::default(),
         };
     }
@@ -172,8 +186,12 @@ fn cardinality_request(
         lte: query.lte.map(OrderedFloat::from),
     };
 
-    let estimation =
-        query::range_cardinality(index.inner(), &RangeInterface::Float(ordered_range)).unwrap();
+    let estimation = query::range_cardinality(
+        index.inner(),
+        &RangeInterface::Float(ordered_range),
+        &hw_counter,
+    )
+    .unwrap();
 
     let result = index
         .inner()
@@ -197,6 +215,46 @@ fn cardinality_request(
     estimation
 }
 
+#[test]
+fn test_max_values_per_point_after_removal() {
+    let (_temp_dir, mut index) = random_index(0, 1, IndexType::MutableGridstore);
+    let hw_counter = HardwareCounterCell::new();
+
+    let add = |index: &mut NumericIndex<FloatPayloadType, FloatPayloadType>,
+               id: PointOffsetType,
+               count: usize| {
+        let values = (0..count).map(|i| Value::from(i)).collect_vec();
+        let values = values.iter().collect_vec();
+        index.add_point(id, &values, &hw_counter).unwrap();
+    };
+
+    assert_eq!(index.inner().get_max_values_per_point(), 0);
+
+    add(&mut index, 0, 1);
+    add(&mut index, 1, 3);
+    add(&mut index, 2, 2);
+    add(&mut index, 3, 3);
+    assert_eq!(index.inner().get_max_values_per_point(), 3);
+
+    // Another point still holds the maximum
+    index.remove_point(1).unwrap();
+    assert_eq!(index.inner().get_max_values_per_point(), 3);
+
+    index.remove_point(3).unwrap();
+    assert_eq!(index.inner().get_max_values_per_point(), 2);
+
+    // Shrinking the values of the max point
+    add(&mut index, 2, 1);
+    assert_eq!(index.inner().get_max_values_per_point(), 1);
+
+    index.remove_point(0).unwrap();
+    index.remove_point(2).unwrap();
+    assert_eq!(index.inner().get_max_values_per_point(), 0);
+
+    add(&mut index, 4, 5);
+    assert_eq!(index.inner().get_max_values_per_point(), 5);
+}
+
 #[test]
 fn test_set_empty_payload() {
     let (_temp_dir, mut index) = random_index(1000, 1, IndexType::MutableGridstore);
@@ -370,6 +428,61 @@ fn test_payload_blocks_small(#[case] index_type: IndexType) {
     assert!(!blocks.is_empty());
 }
 
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_payload_blocks_capped(#[case] index_type: IndexType) {
+    let (_temp_dir, index) = random_index(10_000, 1, index_type);
+    let collect_blocks = |max_blocks: Option<usize>| {
+        let mut blocks = Vec::new();
+        let mut f = |block: PayloadBlockCondition| {
+            let Some(RangeInterface::Float(range)) = block.condition.range else {
+                panic!("Expected float range block");
+            };
+            blocks.push((range, block.cardinality));
+            Ok(())
+        };
+        match max_blocks {
+            Some(max_blocks) => index.inner().for_each_payload_block_capped(
+                10,
+                max_blocks,
+                JsonPath::new("test"),
+                &mut f,
+            ),
+            None => index
+                .inner()
+                .for_each_payload_block(10, JsonPath::new("test"), &mut f),
+        }
+        .unwrap();
+        blocks
+    };
+
+    let uncapped = collect_blocks(None);
+    assert!(uncapped.len() > 100);
+    let total_cardinality: usize = uncapped.iter().map(|(_, cardinality)| cardinality).sum();
+
+    for max_blocks in [1, 7, 100] {
+        let blocks = collect_blocks(Some(max_blocks));
+        assert!(blocks.len() <= max_blocks, "{} blocks", blocks.len());
+        assert_eq!(blocks, collect_blocks(Some(max_blocks)));
+
+        // Blocks still cover the whole value range, in ascending order
+        let cardinality: usize = blocks.iter().map(|(_, cardinality)| cardinality).sum();
+        assert_eq!(cardinality, total_cardinality);
+        assert_eq!(blocks.first().unwrap().0.gte, None);
+        assert_eq!(blocks.first().unwrap().0.gt, None);
+        assert_eq!(blocks.last().unwrap().0.lt, None);
+        assert_eq!(blocks.last().unwrap().0.lte, None);
+        for ((prev, _), (next, _)) in blocks.iter().tuple_windows() {
+            assert_eq!(prev.lt.or(prev.lte), next.gt.or(next.gte));
+        }
+    }
+
+    // A cap above the number of blocks keeps them as they are
+    assert_eq!(collect_blocks(Some(uncapped.len())), uncapped);
+}
+
 #[rstest]
 #[case(IndexType::MutableGridstore)]
 #[case(IndexType::Mmap)]
@@ -742,10 +855,260 @@ fn test_numeric_index_reload(#[case] index_type: IndexType) {
     assert_eq!(index.inner().get_points_count(), 6);
 }
 
+/// `stream_range` must order ties by point id, identically on every variant and
+/// across reopen, so keyset pagination over a numeric field is reproducible.
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_stream_range_stable_order(#[case] index_type: IndexType) {
+    let num_points: PointOffsetType = 500;
+    let (temp_dir, mut index_builder) = get_index_builder(index_type);
+    let hw_counter = HardwareCounterCell::new();
+
+    // Few distinct values so that most points tie, inserted in random id order
+    let mut ids = (0..num_points).collect_vec();
+    ids.shuffle(&mut StdRng::seed_from_u64(42));
+    for id in ids {
+        let value = Value::from(FloatPayloadType::from(id % 5));
+        index_builder.add_point(id, &[&value], &hw_counter).unwrap();
+    }
+    let index = index_builder.finalize().unwrap();
+
+    let range = RangeInterface::Float(Range {
+        lt: None,
+        gt: None,
+        gte: Some(OrderedFloat(1.0)),
+        lte: Some(OrderedFloat(3.0)),
+    });
+    let expected = (1..=3)
+        .flat_map(|value| {
+            (0..num_points)
+                .filter(move |id| id % 5 == value)
+                .map(move |id| (FloatPayloadType::from(value), id))
+        })
+        .collect_vec();
+
+    let check_order = |index: &NumericIndex<FloatPayloadType, FloatPayloadType>| {
+        let streamed = index.inner().stream_range(&range).unwrap().collect_vec();
+        assert_eq!(streamed, expected);
+        let reversed = index
+            .inner()
+            .stream_range(&range)
+            .unwrap()
+            .rev()
+            .collect_vec();
+        assert!(reversed.iter().eq(expected.iter().rev()));
+    };
+
+    check_order(&index);
+
+    index.inner().flusher()().unwrap();
+    drop(index);
+    let index = open_index_from_disk(temp_dir.path(), index_type, &empty_deleted());
+    check_order(&index);
+}
+
+#[test]
+fn test_descending_key_order() {
+    let build = |order: KeyOrder| {
+        let temp_dir = Builder::new()
+            .prefix("test_numeric_index")
+            .tempdir()
+            .unwrap();
+        let mut builder = NumericIndex::<FloatPayloadType, FloatPayloadType>::builder_mmap(
+            temp_dir.path(),
+            false,
+            &empty_deleted(),
+        )
+        .with_key_order(order);
+        builder.init().unwrap();
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let hw_counter = HardwareCounterCell::new();
+        for id in 0..1000 {
+            // Few distinct values, so that range bounds fall within runs of ties
+            let values = (0..2)
+                .map(|_| Value::from(f64::from(rng.random_range(0..50_i32))))
+                .collect_vec();
+            builder
+                .add_point(id, &values.iter().collect_vec(), &hw_counter)
+                .unwrap();
+        }
+        (temp_dir, builder.finalize().unwrap())
+    };
+    let (_asc_dir, asc) = build(KeyOrder::Ascending);
+    let (desc_dir, desc) = build(KeyOrder::Descending);
+
+    let ranges = [
+        Range::default(),
+        Range {
+            lt: Some(OrderedFloat(20.0)),
+            gt: None,
+            gte: Some(OrderedFloat(10.0)),
+            lte: None,
+        },
+        Range {
+            lt: None,
+            gt: Some(OrderedFloat(10.0)),
+            gte: None,
+            lte: Some(OrderedFloat(20.0)),
+        },
+        Range {
+            lt: None,
+            gt: Some(OrderedFloat(48.5)),
+            gte: None,
+            lte: None,
+        },
+    ];
+    let check = |desc: &NumericIndex<FloatPayloadType, FloatPayloadType>| {
+        assert_eq!(desc.inner().key_order(), KeyOrder::Descending);
+        desc.inner().verify_integrity().unwrap();
+
+        let hw_counter = HardwareCounterCell::new();
+        for range in ranges {
+            let condition = FieldCondition::new_range(JsonPath::new("unused"), range);
+            let filtered = |index: &NumericIndex<FloatPayloadType, FloatPayloadType>| {
+                index
+                    .inner()
+                    .filter(&condition, &hw_counter)
+                    .unwrap()
+                    .unwrap()
+                    .sorted()
+                    .collect_vec()
+            };
+            assert_eq!(filtered(desc), filtered(&asc));
+
+            let range = RangeInterface::Float(range);
+            let streamed = asc.inner().stream_range(&range).unwrap().collect_vec();
+            assert!(!streamed.is_empty());
+            assert_eq!(
+                desc.inner().stream_range(&range).unwrap().collect_vec(),
+                streamed,
+            );
+            assert!(
+                desc.inner()
+                    .stream_range(&range)
+                    .unwrap()
+                    .rev()
+                    .eq(streamed.iter().rev().copied())
+            );
+        }
+
+        // Descending order is the order of the stored pairs
+        let NumericIndexInner::Mmap(index) = &desc.inner else {
+            panic!("Expected mmap index");
+        };
+        let stored = index
+            .storage
+            .pairs
+            .read_whole()
+            .unwrap()
+            .iter()
+            .map(|point| (point.val, point.idx))
+            .collect_vec();
+        let descending = asc
+            .inner()
+            .stream_range(&RangeInterface::Float(Range::default()))
+            .unwrap()
+            .rev()
+            .collect_vec();
+        assert_eq!(stored, descending);
+    };
+
+    check(&desc);
+
+    // The order is persisted in the config
+    drop(desc);
+    let desc = open_index_from_disk(desc_dir.path(), IndexType::Mmap, &empty_deleted());
+    check(&desc);
+}
+
 /// Regression test: when reloading an mmap numeric index with a `deleted_points`
 /// bitslice shorter than `point_to_values.len()`, missing entries must default
 /// to live, not deleted. Empty-payload bits from the on-disk `deleted.bin` and
 /// any deletions encoded inside the short bitslice must still be honored.
+fn open_mutable_with_wal(path: &Path) -> MutableNumericIndex<f64> {
+    MutableNumericIndex::open_gridstore_with_wal(path.to_path_buf(), true)
+        .unwrap()
+        .unwrap()
+}
+
+fn mutable_values(index: &MutableNumericIndex<f64>, idx: PointOffsetType) -> Vec<f64> {
+    index
+        .get_values(idx)
+        .map(|values| values.collect_vec())
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_mutable_wal_recovers_unflushed_updates() {
+    let temp_dir = Builder::new().prefix("test_dir").tempdir().unwrap();
+    let hw_counter = HardwareCounterCell::new();
+
+    let mut index = open_mutable_with_wal(temp_dir.path());
+    for idx in 0..10 {
+        index
+            .add_many_to_list(idx, vec![f64::from(idx)], &hw_counter)
+            .unwrap();
+    }
+    index.flusher()().unwrap();
+
+    for idx in 10..20 {
+        index
+            .add_many_to_list(idx, vec![f64::from(idx)], &hw_counter)
+            .unwrap();
+    }
+    index.remove_point(3).unwrap();
+    index.remove_point(5).unwrap();
+    index
+        .add_many_to_list(5, vec![50.0, 51.0], &hw_counter)
+        .unwrap();
+
+    // Crash, without flushing the updates since the last flush
+    drop(index);
+
+    let index = open_mutable_with_wal(temp_dir.path());
+    for idx in (0..20).filter(|idx| ![3, 5].contains(idx)) {
+        assert_eq!(mutable_values(&index, idx), vec![f64::from(idx)]);
+    }
+    assert!(mutable_values(&index, 3).is_empty());
+    assert_eq!(mutable_values(&index, 5), vec![50.0, 51.0]);
+
+    // Recovered updates are persisted, the log starts over
+    let wal_path = temp_dir.path().join("numeric_index.wal");
+    assert_eq!(fs_err::metadata(&wal_path).unwrap().len(), 0);
+    assert!(index.files().contains(&wal_path));
+}
+
+#[test]
+fn test_mutable_wal_truncated_on_flush() {
+    let temp_dir = Builder::new().prefix("test_dir").tempdir().unwrap();
+    let wal_path = temp_dir.path().join("numeric_index.wal");
+    let hw_counter = HardwareCounterCell::new();
+
+    let mut index = open_mutable_with_wal(temp_dir.path());
+    index.add_many_to_list(0, vec![1.0], &hw_counter).unwrap();
+    assert!(fs_err::metadata(&wal_path).unwrap().len() > 0);
+
+    index.flusher()().unwrap();
+    assert_eq!(fs_err::metadata(&wal_path).unwrap().len(), 0);
+
+    // Updates made while a flush is pending are not covered by it, and must stay in the log
+    index.add_many_to_list(1, vec![2.0], &hw_counter).unwrap();
+    let flusher = index.flusher();
+    index.add_many_to_list(2, vec![3.0], &hw_counter).unwrap();
+    flusher().unwrap();
+    assert!(fs_err::metadata(&wal_path).unwrap().len() > 0);
+
+    drop(index);
+
+    let index = open_mutable_with_wal(temp_dir.path());
+    assert_eq!(mutable_values(&index, 0), vec![1.0]);
+    assert_eq!(mutable_values(&index, 1), vec![2.0]);
+    assert_eq!(mutable_values(&index, 2), vec![3.0]);
+}
+
 #[rstest]
 #[case(IndexType::Mmap)]
 #[case(IndexType::RamMmap)]
@@ -827,6 +1190,200 @@ fn test_cond<
     assert_eq!(offsets, result);
 }
 
+fn build_int_index<B>(
+    mut builder: B,
+    num_points: PointOffsetType,
+    payload: impl Fn(PointOffsetType) -> Value,
+) -> B::FieldIndexType
+where
+    B: FieldIndexBuilderTrait<FieldIndexType = NumericIndex<IntPayloadType, IntPayloadType>>,
+{
+    let hw_counter = HardwareCounterCell::new();
+    builder.init().unwrap();
+    for i in 0..num_points {
+        builder.add_point(i, &[&payload(i)], &hw_counter).unwrap();
+    }
+    builder.finalize().unwrap()
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_match_any_cardinality(#[case] index_type: IndexType) {
+    // Not representable as `f64`, a round trip would look up 2^53 instead
+    const LARGE: IntPayloadType = (1 << 53) + 1;
+
+    let temp_dir = Builder::new()
+        .prefix("test_numeric_index")
+        .tempdir()
+        .unwrap();
+    let payload = |i: PointOffsetType| match i % 10 {
+        9 => Value::from(LARGE),
+        rest => Value::from(IntPayloadType::from(rest)),
+    };
+    let index = match index_type {
+        IndexType::MutableGridstore => build_int_index(
+            NumericIndex::<IntPayloadType, IntPayloadType>::builder_gridstore(
+                temp_dir.path().to_path_buf(),
+            ),
+            100,
+            payload,
+        ),
+        IndexType::Mmap | IndexType::RamMmap => build_int_index(
+            NumericIndex::<IntPayloadType, IntPayloadType>::builder_mmap(
+                temp_dir.path(),
+                false,
+                &empty_deleted(),
+            ),
+            100,
+            payload,
+        ),
+    };
+
+    // `5` is repeated on purpose, `42` and `LARGE - 1` are not present in the index.
+    let condition = FieldCondition::new_match(
+        JsonPath::new("unused"),
+        Match::from(vec![2, 5, 5, 42, LARGE - 1, LARGE]),
+    );
+
+    let hw_acc = HwMeasurementAcc::new();
+    let hw_counter = hw_acc.get_counter_cell();
+    let estimation = index
+        .estimate_cardinality(&condition, &hw_counter)
+        .unwrap()
+        .unwrap();
+    let matched = index
+        .filter(&condition, &hw_counter)
+        .unwrap()
+        .unwrap()
+        .sorted()
+        .collect_vec();
+
+    let expected = (0..100)
+        .filter(|i| matches!(i % 10, 2 | 5 | 9))
+        .collect_vec();
+    assert_eq!(matched, expected);
+
+    assert_eq!(estimation.primary_clauses.len(), 1);
+    assert!(estimation.min <= matched.len(), "{estimation:#?}");
+    assert!(estimation.max >= matched.len(), "{estimation:#?}");
+    assert!(
+        estimation.exp.abs_diff(matched.len()) <= 3,
+        "{estimation:#?}"
+    );
+
+    let empty_condition =
+        FieldCondition::new_match(JsonPath::new("unused"), Match::from(Vec::<i64>::new()));
+    let estimation = index
+        .estimate_cardinality(&empty_condition, &hw_counter)
+        .unwrap()
+        .unwrap();
+    assert_eq!(estimation.max, 0);
+
+    // Payload checks only match integers to integers, so a float index doesn't serve `3` as `3.0`
+    let (_float_dir, mut builder) = get_index_builder(index_type);
+    for i in 0..10 {
+        let value = Value::from(FloatPayloadType::from(i));
+        builder.add_point(i, &[&value], &hw_counter).unwrap();
+    }
+    let float_index = builder.finalize().unwrap();
+    assert!(
+        float_index
+            .filter(&condition, &hw_counter)
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        float_index
+            .estimate_cardinality(&condition, &hw_counter)
+            .unwrap()
+            .is_none()
+    );
+    assert!(NumericIndexInner::<FloatPayloadType>::compile_condition(&condition).is_none());
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+fn test_add_point_charges_writes(#[case] index_type: IndexType) {
+    let (_temp_dir, mut builder) = get_index_builder(index_type);
+
+    let hw_counter = HardwareCounterCell::new();
+    let values = (0..10).map(|i| Value::from(f64::from(i))).collect_vec();
+    let values = values.iter().collect_vec();
+
+    builder.add_point(0, &values[..1], &hw_counter).unwrap();
+    let single_value_writes = hw_counter.payload_index_io_write_counter().get();
+    assert!(single_value_writes > 0);
+
+    builder.add_point(1, &values, &hw_counter).unwrap();
+    let batch_writes = hw_counter.payload_index_io_write_counter().get() - single_value_writes;
+    assert!(
+        batch_writes > single_value_writes,
+        "writing 10 values ({batch_writes} bytes) must cost more than a single one ({single_value_writes} bytes)",
+    );
+}
+
+#[test]
+fn test_sorted_values_reproducible_build() {
+    let temp_dir = Builder::new()
+        .prefix("test_sorted_values")
+        .tempdir()
+        .unwrap();
+
+    let build = |name: &str, payloads: &[Vec<FloatPayloadType>]| {
+        let path = temp_dir.path().join(name);
+        let mut builder = NumericIndex::<FloatPayloadType, FloatPayloadType>::builder_mmap(
+            &path,
+            false,
+            &empty_deleted(),
+        )
+        .with_sorted_values(true);
+        builder.init().unwrap();
+
+        let hw_counter = HardwareCounterCell::new();
+        for (idx, values) in payloads.iter().enumerate() {
+            let values = Value::from(values.clone());
+            builder
+                .add_point(idx as PointOffsetType, &[&values], &hw_counter)
+                .unwrap();
+        }
+        builder.finalize().unwrap();
+        path
+    };
+
+    let first = build("first", &[vec![3.0, 1.0, 2.0, 1.0], vec![5.0, 4.0]]);
+    let second = build("second", &[vec![1.0, 2.0, 3.0, 3.0], vec![4.0, 5.0]]);
+
+    let mut files = std::fs::read_dir(&first)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect_vec();
+    files.sort();
+    assert!(!files.is_empty());
+
+    for file in files {
+        assert_eq!(
+            std::fs::read(first.join(&file)).unwrap(),
+            std::fs::read(second.join(&file)).unwrap(),
+            "{file:?} differs between builds",
+        );
+    }
+
+    let index = NumericIndex::<FloatPayloadType, FloatPayloadType>::new_mmap(
+        &first,
+        false,
+        &empty_deleted(),
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(
+        index.get_values(0).unwrap().collect_vec(),
+        vec![1.0, 2.0, 3.0]
+    );
+}
+
 // Check we don't panic on an empty index. See <https://github.com/qdrant/qdrant/pull/2933>.
 #[rstest]
 #[case(IndexType::MutableGridstore)]
@@ -858,137 +1415,2628 @@ fn test_empty_cardinality(#[case] index_type: IndexType) {
     );
 }
 
-/// Reopen an immutable numeric index with an id-tracker deletion bitslice and
-/// verify the deleted points are excluded from counts and queries. Locks the
-/// open-time deletion path that the milestone-49 fix exposed in the map index
-/// loader (see `immutable_map_index.rs:99-102`); cf. the integration test in
-/// `lib/segment/src/segment/tests/test_immutable_payload_index_files.rs`.
-#[test]
-fn test_remove_reopen() {
-    use crate::index::field_index::PayloadFieldIndexRead;
-
-    let hw_acc = HwMeasurementAcc::new();
-    let hw_counter = hw_acc.get_counter_cell();
-    let (temp_dir, mut builder) = get_index_builder(IndexType::Mmap);
-    let values = [10.0_f64, 20.0, 30.0, 40.0];
-    for (idx, val) in values.iter().enumerate() {
-        builder
-            .add_point(idx as PointOffsetType, &[&Value::from(*val)], &hw_counter)
-            .unwrap();
-    }
-    // Persist on-disk state, then drop so the upcoming reopen sees only the
-    // files (no in-memory state holding handles).
-    let built = builder.finalize().unwrap();
-    drop(built);
-
-    // Reopen as immutable (RamMmap) with points 1 and 3 marked deleted by
-    // the id-tracker. The immutable open path must observe these deletions
-    // through the deleted bitslice argument.
-    let deleted = deleted_with(&[1, 3]);
-    let index = open_index_from_disk(temp_dir.path(), IndexType::RamMmap, &deleted);
-
-    // Deletions reflected in the indexed-point count.
-    assert_eq!(index.inner().count_indexed_points(), 2);
-
-    // Range query covering all four original values returns only the live
-    // ones; deleted points must be filtered out by the immutable index.
-    let range = Range {
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_range_cardinality_exactness(#[case] index_type: IndexType) {
+    let narrow_range = Range {
         lt: None,
         gt: None,
-        gte: Some(OrderedFloat(0.0)),
-        lte: Some(OrderedFloat(100.0)),
+        gte: Some(40.0),
+        lte: Some(45.0),
     };
-    let mut hits: Vec<_> = index
-        .inner()
-        .filter(
-            &FieldCondition::new_range(JsonPath::new("unused"), range),
+
+    // Small enough to be scanned
+    let (_temp_dir, index) = random_index(100, 1, index_type);
+    let estimation = cardinality_request(&index, narrow_range, HwMeasurementAcc::new());
+    assert!(estimation.is_exact(), "{estimation:#?}");
+    assert_eq!(estimation.relative_error(), 0.0);
+
+    let (_temp_dir, index) = random_index(10_000, 2, index_type);
+    let estimation = cardinality_request(
+        &index,
+        Range {
+            lt: None,
+            gt: None,
+            gte: Some(10.0),
+            lte: Some(90.0),
+        },
+        HwMeasurementAcc::new(),
+    );
+    assert!(!estimation.is_exact(), "{estimation:#?}");
+    assert!(estimation.relative_error() > 0.0);
+}
+
+#[test]
+fn test_rebuild_histogram() {
+    let (_temp_dir, mut index) = random_index(2000, 1, IndexType::Mmap);
+    let hw_counter = HardwareCounterCell::new();
+
+    // Runtime deletions of an mmap index leave its histogram untouched
+    let deleted = (0..2000)
+        .filter(|&idx| {
+            index
+                .inner()
+                .get_values(idx)
+                .unwrap()
+                .all(|value| value < 50.0)
+        })
+        .collect_vec();
+    index.inner.remove_points(&deleted).unwrap();
+
+    let ranges = [
+        Range {
+            lt: Some(OrderedFloat(50.0)),
+            gt: None,
+            gte: None,
+            lte: None,
+        },
+        Range {
+            lt: Some(OrderedFloat(75.0)),
+            gt: None,
+            gte: Some(OrderedFloat(25.0)),
+            lte: None,
+        },
+    ];
+    let estimation_errors = |index: &NumericIndex<FloatPayloadType, FloatPayloadType>| {
+        ranges
+            .iter()
+            .map(|range| {
+                let live = index
+                    .inner()
+                    .filter(
+                        &FieldCondition::new_range(JsonPath::new("unused"), *range),
+                        &hw_counter,
+                    )
+                    .unwrap()
+                    .unwrap()
+                    .unique()
+                    .count();
+                let estimation = query::range_cardinality(
+                    index.inner(),
+                    &RangeInterface::Float(*range),
+                    &hw_counter,
+                )
+                .unwrap();
+                estimation.exp.abs_diff(live)
+            })
+            .collect_vec()
+    };
+
+    let stale_errors = estimation_errors(&index);
+    index.inner.rebuild_histogram().unwrap();
+    let rebuilt_errors = estimation_errors(&index);
+
+    assert_eq!(
+        index.inner().get_histogram().get_total_count(),
+        2000 - deleted.len(),
+    );
+    assert_eq!(rebuilt_errors[0], 0);
+    for (rebuilt, stale) in rebuilt_errors.iter().zip(&stale_errors) {
+        assert!(rebuilt < stale, "{rebuilt_errors:?} vs {stale_errors:?}");
+    }
+}
+
+fn assert_same_queries(
+    expected: &NumericIndexInner<FloatPayloadType>,
+    actual: &NumericIndexInner<FloatPayloadType>,
+) {
+    let hw_counter = HardwareCounterCell::new();
+    for (gte, lt) in [(0.0, 100.0), (10.0, 20.0), (33.3, 33.4), (99.0, 100.0)] {
+        let condition = FieldCondition::new_range(
+            JsonPath::new("unused"),
+            Range {
+                lt: Some(OrderedFloat(lt)),
+                gt: None,
+                gte: Some(OrderedFloat(gte)),
+                lte: None,
+            },
+        );
+        let filtered = |index: &NumericIndexInner<FloatPayloadType>| {
+            index
+                .filter(&condition, &hw_counter)
+                .unwrap()
+                .unwrap()
+                .sorted()
+                .collect_vec()
+        };
+        assert_eq!(filtered(actual), filtered(expected));
+    }
+    for idx in 0..expected.has_value_bitmap().len() as PointOffsetType {
+        assert_eq!(
+            actual.get_values(idx).into_iter().flatten().collect_vec(),
+            expected.get_values(idx).into_iter().flatten().collect_vec(),
+        );
+    }
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_convert_storage_variants(#[case] index_type: IndexType) {
+    let with_deletions = || {
+        let (temp_dir, mut index) = random_index(500, 2, index_type);
+        index.inner.remove_points(&[3, 7, 250, 499]).unwrap();
+        (temp_dir, index.inner)
+    };
+    let (_temp_dir, expected) = with_deletions();
+
+    let mmap_dir = Builder::new()
+        .prefix("test_numeric_index_converted")
+        .tempdir()
+        .unwrap();
+    let mmap = expected.to_mmap(mmap_dir.path(), false).unwrap();
+    assert!(matches!(mmap, NumericIndexInner::Mmap(_)));
+    assert_same_queries(&expected, &mmap);
+    let Ok(immutable) = mmap.into_immutable() else {
+        panic!("Expected mmap index to load into RAM");
+    };
+    assert!(matches!(immutable, NumericIndexInner::Immutable(_)));
+    assert_same_queries(&expected, &immutable);
+
+    let (_temp_dir, index) = with_deletions();
+    let is_mutable = matches!(index, NumericIndexInner::Mutable(_));
+    match index.into_immutable() {
+        Ok(immutable) => {
+            assert!(!is_mutable);
+            assert!(matches!(immutable, NumericIndexInner::Immutable(_)));
+            assert_same_queries(&expected, &immutable);
+        }
+        Err((err, index)) => {
+            assert!(is_mutable, "{err}");
+            assert!(matches!(err, OperationError::ServiceError { .. }), "{err}");
+            // The index is handed back intact
+            assert!(matches!(*index, NumericIndexInner::Mutable(_)));
+            assert_same_queries(&expected, &index);
+        }
+    }
+
+    let (_temp_dir, index) = with_deletions();
+    let was_mmap = matches!(index, NumericIndexInner::Mmap(_));
+    match index.into_mmap() {
+        Ok(mmap) => {
+            assert!(!is_mutable);
+            assert!(matches!(mmap, NumericIndexInner::Mmap(_)));
+            // Mmap indexes are returned as is, others are moved to disk
+            assert!(was_mmap || mmap.is_on_disk());
+            assert_same_queries(&expected, &mmap);
+        }
+        Err((err, index)) => {
+            assert!(is_mutable, "{err}");
+            assert!(matches!(err, OperationError::ServiceError { .. }), "{err}");
+            assert!(matches!(*index, NumericIndexInner::Mutable(_)));
+            assert_same_queries(&expected, &index);
+        }
+    }
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_check_values_all_predicates(#[case] index_type: IndexType) {
+    let (_temp_dir, index) = random_index(1000, 3, index_type);
+    let hw_counter = HardwareCounterCell::new();
+
+    let low = |v: &FloatPayloadType| (0.0..=10.0).contains(v);
+    let high = |v: &FloatPayloadType| (90.0..=100.0).contains(v);
+    let none = |v: &FloatPayloadType| *v < 0.0;
+    let predicates: [&dyn Fn(&FloatPayloadType) -> bool; 3] = [&low, &high, &none];
+
+    // Include an id past the end of the index
+    for idx in 0..1001 {
+        let batched = index
+            .inner()
+            .check_values_all_predicates(idx, &predicates, &hw_counter);
+        let individual = predicates
+            .iter()
+            .map(|predicate| index.inner().check_values_any(idx, predicate, &hw_counter))
+            .collect_vec();
+        assert_eq!(batched, individual, "point {idx}");
+    }
+
+    assert!(
+        index
+            .inner()
+            .check_values_all_predicates(0, &[], &hw_counter)
+            .is_empty()
+    );
+}
+
+/// `clear_cache` must evict the point to values file together with the sorted pairs, otherwise
+/// `get_values` stays hot after a cache clear.
+#[test]
+fn test_clear_cache_evicts_point_to_values() {
+    let (temp_dir, index) = random_index(10_000, 2, IndexType::Mmap);
+    let pairs_path = temp_dir.path().join(universal_numeric_index::PAIRS_PATH);
+    let point_to_values_path = temp_dir.path().join("point_to_values.bin");
+    let resident_bytes = |path: &Path| MmapFile::probe_memory_stats(path).unwrap().1;
+
+    // Populate both files
+    let hw_counter = HardwareCounterCell::new();
+    let all_points = index
+        .inner()
+        .values_range(Unbounded, Unbounded, &hw_counter)
+        .unwrap()
+        .count();
+    assert_eq!(all_points, 20_000);
+    for idx in 0..10_000 {
+        assert_eq!(index.inner().get_values(idx).unwrap().count(), 2);
+    }
+    let pairs_before = resident_bytes(&pairs_path);
+    let point_to_values_before = resident_bytes(&point_to_values_path);
+    assert!(pairs_before > 0);
+    assert!(point_to_values_before > 0);
+
+    index.inner().clear_cache().unwrap();
+
+    // Eviction depends on kernel support and on the filesystem, e.g. tmpfs pages can't be
+    // paged out. Whenever the pairs got evicted, the point to values must be evicted as well.
+    let pairs_after = resident_bytes(&pairs_path);
+    let point_to_values_after = resident_bytes(&point_to_values_path);
+    if pairs_after < pairs_before {
+        assert!(
+            point_to_values_after < point_to_values_before,
+            "point to values stayed resident: {point_to_values_after} of {point_to_values_before} bytes",
+        );
+    }
+
+    // Subsequent reads fault the values back in from disk
+    for idx in 0..10_000 {
+        assert_eq!(index.inner().get_values(idx).unwrap().count(), 2);
+    }
+    assert!(resident_bytes(&point_to_values_path) > 0);
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_explain_condition(#[case] index_type: IndexType) {
+    let (_temp_dir, index) = random_index(1000, 1, index_type);
+    let hw_counter = HardwareCounterCell::new();
+
+    let range_condition = FieldCondition::new_range(
+        JsonPath::new("unused"),
+        Range {
+            lt: Some(OrderedFloat(20.0)),
+            gt: None,
+            gte: Some(OrderedFloat(10.0)),
+            lte: None,
+        },
+    );
+    let estimation = index
+        .inner()
+        .estimate_cardinality(&range_condition, &hw_counter)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        index
+            .inner()
+            .explain_condition(&range_condition, &hw_counter)
+            .unwrap(),
+        ConditionServeability::Served(estimation),
+    );
+
+    let text_condition = FieldCondition::new_match(JsonPath::new("unused"), Match::new_text("10"));
+    let ConditionServeability::NotServed { reason } = index
+        .inner()
+        .explain_condition(&text_condition, &hw_counter)
+        .unwrap()
+    else {
+        panic!("Full-text match must not be served by a numeric index");
+    };
+    assert!(reason.contains("full-text match"), "{reason}");
+}
+
+#[test]
+fn test_filter_with_io_stats() {
+    let (temp_dir, index) = random_index(1000, 2, IndexType::Mmap);
+    drop(index);
+    // Reads are only charged for on-disk storage
+    let index = open_index_from_disk(temp_dir.path(), IndexType::Mmap, &empty_deleted());
+    let hw_counter = HardwareCounterCell::new();
+
+    let condition = FieldCondition::new_range(
+        JsonPath::new("unused"),
+        Range {
+            lt: Some(OrderedFloat(40.0)),
+            gt: None,
+            gte: Some(OrderedFloat(20.0)),
+            lte: None,
+        },
+    );
+    let expected = index
+        .inner()
+        .filter(&condition, &hw_counter)
+        .unwrap()
+        .unwrap()
+        .collect_vec();
+
+    let bytes_read_before = hw_counter.payload_index_io_read_counter().get();
+    let (iter, stats) = index
+        .inner()
+        .filter_with_io_stats(&condition, &hw_counter)
+        .unwrap()
+        .unwrap();
+    assert_eq!(stats.totals(), None);
+    let points = iter.collect_vec();
+    let bytes_read = hw_counter.payload_index_io_read_counter().get() - bytes_read_before;
+
+    assert_eq!(points, expected);
+    let totals = stats.totals().unwrap();
+    assert!(totals.bytes_read > 0);
+    assert_eq!(totals.bytes_read, bytes_read);
+    assert_eq!(totals.entries, points.len());
+}
+
+#[test]
+fn test_uuid_from_bytes() {
+    let temp_dir = Builder::new()
+        .prefix("test_numeric_index")
+        .tempdir()
+        .unwrap();
+    let hw_counter = HardwareCounterCell::new();
+    let uuid = UuidPayloadType::parse_str("f47ac10b-58cc-4372-a567-0e02b2c3d479").unwrap();
+    let other = UuidPayloadType::from_u128(3);
+
+    assert_eq!(
+        <NumericIndex<UuidIntType, UuidPayloadType> as ValueIndexer>::get_values(&Value::from(
+            vec![uuid.as_bytes().to_vec(), other.as_bytes().to_vec()]
+        )),
+        vec![uuid, other],
+    );
+
+    let mut builder = NumericIndex::<UuidIntType, UuidPayloadType>::builder_mmap(
+        temp_dir.path(),
+        false,
+        &empty_deleted(),
+    );
+    builder.init().unwrap();
+    let payloads = [
+        Value::String(uuid.to_string()),
+        Value::from(uuid.as_bytes().to_vec()),
+        Value::String(other.to_string()),
+        // Not a byte, so not a UUID
+        Value::from(uuid.as_bytes().map(|byte| u64::from(byte) + 256).to_vec()),
+    ];
+    for (id, payload) in payloads.iter().enumerate() {
+        builder
+            .add_point(id as PointOffsetType, &[payload], &hw_counter)
+            .unwrap();
+    }
+    let index = builder.finalize().unwrap();
+    assert!(index.inner().values_is_empty(3));
+
+    let condition = FieldCondition::new_match(
+        JsonPath::new("unused"),
+        Match::new_value(ValueVariants::String(uuid.to_string())),
+    );
+    let points = index
+        .filter(&condition, &hw_counter)
+        .unwrap()
+        .unwrap()
+        .sorted()
+        .collect_vec();
+    assert_eq!(points, vec![0, 1]);
+}
+
+#[test]
+fn test_explain_uuid_condition() {
+    let temp_dir = Builder::new()
+        .prefix("test_numeric_index")
+        .tempdir()
+        .unwrap();
+    let hw_counter = HardwareCounterCell::new();
+    let mut builder = NumericIndex::<UuidIntType, UuidPayloadType>::builder_mmap(
+        temp_dir.path(),
+        false,
+        &empty_deleted(),
+    );
+    builder.init().unwrap();
+    for i in 0..100 {
+        let value = Value::String(UuidPayloadType::from_u128(u128::from(i % 10)).to_string());
+        builder.add_point(i, &[&value], &hw_counter).unwrap();
+    }
+    let index = builder.finalize().unwrap();
+
+    let uuid_condition = FieldCondition::new_match(
+        JsonPath::new("unused"),
+        Match::new_value(ValueVariants::String(
+            UuidPayloadType::from_u128(3).to_string(),
+        )),
+    );
+    let ConditionServeability::Served(estimation) = index
+        .inner()
+        .explain_condition(&uuid_condition, &hw_counter)
+        .unwrap()
+    else {
+        panic!("UUID match must be served by a UUID index");
+    };
+    assert!(
+        estimation.min <= 10 && estimation.max >= 10,
+        "{estimation:#?}"
+    );
+
+    let keyword_condition = FieldCondition::new_match(
+        JsonPath::new("unused"),
+        Match::new_value(ValueVariants::String("not a uuid".to_string())),
+    );
+    let ConditionServeability::NotServed { reason } = index
+        .inner()
+        .explain_condition(&keyword_condition, &hw_counter)
+        .unwrap()
+    else {
+        panic!("keyword which is not a UUID must not be served");
+    };
+    assert!(reason.contains("\"not a uuid\""), "{reason}");
+    assert!(reason.contains("not a UUID"), "{reason}");
+}
+
+fn build_bool_index<B>(mut builder: B, num_points: PointOffsetType) -> B::FieldIndexType
+where
+    B: FieldIndexBuilderTrait<FieldIndexType = NumericIndex<IntPayloadType, bool>>,
+{
+    let hw_counter = HardwareCounterCell::new();
+    builder.init().unwrap();
+    for i in 0..num_points {
+        // Every fifth point has no value at all
+        let payload = match i % 5 {
+            4 => Value::Null,
+            _ => Value::Bool(i % 3 == 0),
+        };
+        builder.add_point(i, &[&payload], &hw_counter).unwrap();
+    }
+    builder.finalize().unwrap()
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore, 100)]
+#[case(IndexType::Mmap, 100)]
+#[case(IndexType::MutableGridstore, 0)]
+#[case(IndexType::Mmap, 0)]
+fn test_bool_numeric_index(#[case] index_type: IndexType, #[case] num_points: PointOffsetType) {
+    let temp_dir = Builder::new()
+        .prefix("test_numeric_index")
+        .tempdir()
+        .unwrap();
+    let mut index = match index_type {
+        IndexType::MutableGridstore => build_bool_index(
+            NumericIndex::<IntPayloadType, bool>::builder_gridstore(temp_dir.path().to_path_buf()),
+            num_points,
+        ),
+        IndexType::Mmap | IndexType::RamMmap => build_bool_index(
+            NumericIndex::<IntPayloadType, bool>::builder_mmap(
+                temp_dir.path(),
+                false,
+                &empty_deleted(),
+            ),
+            num_points,
+        ),
+    };
+    let hw_counter = HardwareCounterCell::new();
+
+    // Stored as 0/1
+    for i in 0..num_points {
+        let expected_values = (i % 5 != 4).then_some(IntPayloadType::from(i % 3 == 0));
+        assert_eq!(
+            index.get_values(i).into_iter().flatten().collect_vec(),
+            expected_values.into_iter().collect_vec(),
+        );
+    }
+
+    for value in [true, false] {
+        let expected = (0..num_points)
+            .filter(|i| i % 5 != 4 && (i % 3 == 0) == value)
+            .collect_vec();
+        let condition = FieldCondition::new_match(
+            JsonPath::new("unused"),
+            Match::new_value(ValueVariants::Bool(value)),
+        );
+
+        let matched = index
+            .filter(&condition, &hw_counter)
+            .unwrap()
+            .unwrap()
+            .sorted()
+            .collect_vec();
+        assert_eq!(matched, expected);
+
+        let estimation = index
+            .estimate_cardinality(&condition, &hw_counter)
+            .unwrap()
+            .unwrap();
+        assert!(
+            estimation.equals_min_exp_max(&CardinalityEstimation::exact(expected.len())),
+            "{estimation:#?}",
+        );
+        assert_eq!(estimation.primary_clauses.len(), 1);
+
+        let checker = index
+            .condition_checker(&condition, HwMeasurementAcc::new())
+            .unwrap();
+        let checked = (0..num_points).filter(|&i| checker(i)).collect_vec();
+        assert_eq!(checked, expected);
+    }
+
+    // Plain numeric matches keep their meaning, `1` is not `true`
+    let integer_condition = FieldCondition::new_match(
+        JsonPath::new("unused"),
+        Match::new_value(ValueVariants::Integer(1)),
+    );
+    assert!(
+        index
+            .filter(&integer_condition, &hw_counter)
+            .unwrap()
+            .is_none()
+    );
+    // Nor is a `match any` of integers, even though booleans are stored as integers
+    let any_condition = FieldCondition::new_match(JsonPath::new("unused"), Match::from(vec![0, 1]));
+    assert!(index.filter(&any_condition, &hw_counter).unwrap().is_none());
+
+    // Deleted points are not counted by the exact estimation
+    let removed = (0..num_points).step_by(2).collect_vec();
+    index.remove_points(&removed).unwrap();
+    for value in [true, false] {
+        let condition = FieldCondition::new_match(
+            JsonPath::new("unused"),
+            Match::new_value(ValueVariants::Bool(value)),
+        );
+        let live = index
+            .filter(&condition, &hw_counter)
+            .unwrap()
+            .unwrap()
+            .count();
+        assert_eq!(
+            live,
+            (0..num_points)
+                .filter(|i| i % 2 == 1 && i % 5 != 4 && (i % 3 == 0) == value)
+                .count(),
+        );
+        let estimation = index
+            .estimate_cardinality(&condition, &hw_counter)
+            .unwrap()
+            .unwrap();
+        assert!(
+            estimation.equals_min_exp_max(&CardinalityEstimation::exact(live)),
+            "{estimation:#?}",
+        );
+    }
+}
+
+/// Reopen an immutable numeric index with an id-tracker deletion bitslice and
+/// verify the deleted points are excluded from counts and queries. Locks the
+/// open-time deletion path that the milestone-49 fix exposed in the map index
+/// loader (see `immutable_map_index.rs:99-102`); cf. the integration test in
+/// `lib/segment/src/segment/tests/test_immutable_payload_index_files.rs`.
+#[test]
+fn test_remove_reopen() {
+    use crate::index::field_index::PayloadFieldIndexRead;
+
+    let hw_acc = HwMeasurementAcc::new();
+    let hw_counter = hw_acc.get_counter_cell();
+    let (temp_dir, mut builder) = get_index_builder(IndexType::Mmap);
+    let values = [10.0_f64, 20.0, 30.0, 40.0];
+    for (idx, val) in values.iter().enumerate() {
+        builder
+            .add_point(idx as PointOffsetType, &[&Value::from(*val)], &hw_counter)
+            .unwrap();
+    }
+    // Persist on-disk state, then drop so the upcoming reopen sees only the
+    // files (no in-memory state holding handles).
+    let built = builder.finalize().unwrap();
+    drop(built);
+
+    // Reopen as immutable (RamMmap) with points 1 and 3 marked deleted by
+    // the id-tracker. The immutable open path must observe these deletions
+    // through the deleted bitslice argument.
+    let deleted = deleted_with(&[1, 3]);
+    let index = open_index_from_disk(temp_dir.path(), IndexType::RamMmap, &deleted);
+
+    // Deletions reflected in the indexed-point count.
+    assert_eq!(index.inner().count_indexed_points(), 2);
+
+    // Range query covering all four original values returns only the live
+    // ones; deleted points must be filtered out by the immutable index.
+    let range = Range {
+        lt: None,
+        gt: None,
+        gte: Some(OrderedFloat(0.0)),
+        lte: Some(OrderedFloat(100.0)),
+    };
+    let mut hits: Vec<_> = index
+        .inner()
+        .filter(
+            &FieldCondition::new_range(JsonPath::new("unused"), range),
             &hw_counter,
         )
-        .unwrap()
-        .unwrap()
-        .collect();
-    hits.sort();
-    assert_eq!(hits, vec![0, 2]);
+        .unwrap()
+        .unwrap()
+        .collect();
+    hits.sort();
+    assert_eq!(hits, vec![0, 2]);
+
+    // Per-point queries: deleted points report no values; live points still
+    // see their original value.
+    assert_eq!(index.values_count(0), 1);
+    assert_eq!(index.values_count(1), 0);
+    assert_eq!(index.values_count(2), 1);
+    assert_eq!(index.values_count(3), 0);
+}
+
+/// Regression test for #9049: fractional `f64` range bounds were truncated
+/// toward zero when converted to the integer index's storage type, so a
+/// `gte: 1.5` predicate (meaning `>= 1.5`) wrongly matched the integer
+/// value `1`. The indexed path must agree with the documented
+/// `f64`-comparison semantics for every fractional bound.
+#[test]
+fn test_integer_index_fractional_range_bounds() {
+    let temp_dir = Builder::new()
+        .prefix("test_integer_index_fractional_range_bounds")
+        .tempdir()
+        .unwrap();
+
+    let mut builder = NumericIndex::<IntPayloadType, IntPayloadType>::builder_gridstore(
+        temp_dir.path().to_path_buf(),
+    );
+    builder.init().unwrap();
+
+    let hw_counter = HardwareCounterCell::new();
+    let v1 = Value::from(1_i64);
+    let v2 = Value::from(2_i64);
+    builder.add_point(0, &[&v1], &hw_counter).unwrap();
+    builder.add_point(1, &[&v2], &hw_counter).unwrap();
+    let index = builder.finalize().unwrap();
+
+    let run = |range: Range<FloatPayloadType>| -> Vec<PointOffsetType> {
+        let cond = FieldCondition::new_range(JsonPath::new("price"), range.map(OrderedFloat::from));
+        let hw = HardwareCounterCell::new();
+        let mut ids: Vec<_> = index.inner().filter(&cond, &hw).unwrap().unwrap().collect();
+        ids.sort();
+        ids
+    };
+
+    // `gte: 1.5` ⇒ `>= 1.5` ⇒ integer 1 must NOT match, integer 2 must match.
+    assert_eq!(
+        run(Range {
+            gte: Some(1.5),
+            ..Default::default()
+        }),
+        vec![1],
+        "gte: 1.5 must exclude integer 1",
+    );
+
+    // `gt: 1.5` ⇒ `> 1.5` ⇒ integer 1 must NOT match, integer 2 must match.
+    assert_eq!(
+        run(Range {
+            gt: Some(1.5),
+            ..Default::default()
+        }),
+        vec![1],
+        "gt: 1.5 must exclude integer 1",
+    );
+
+    // `lt: 1.5` ⇒ `< 1.5` ⇒ integer 1 must match, integer 2 must NOT.
+    assert_eq!(
+        run(Range {
+            lt: Some(1.5),
+            ..Default::default()
+        }),
+        vec![0],
+        "lt: 1.5 must include integer 1 and exclude 2",
+    );
+
+    // `lte: 1.5` ⇒ `<= 1.5` ⇒ integer 1 must match, integer 2 must NOT.
+    assert_eq!(
+        run(Range {
+            lte: Some(1.5),
+            ..Default::default()
+        }),
+        vec![0],
+        "lte: 1.5 must include integer 1 and exclude 2",
+    );
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+fn test_datetime_out_of_range_policy(#[case] index_type: IndexType) {
+    use std::str::FromStr;
+
+    type DateTimeIndex = NumericIndex<IntPayloadType, DateTimePayloadType>;
+
+    let temp_dir = Builder::new()
+        .prefix("test_datetime_out_of_range")
+        .tempdir()
+        .unwrap();
+    let build = |name: &str,
+                 policy: OutOfRangePolicy,
+                 payload: &[&Value]|
+     -> OperationResult<DateTimeIndex> {
+        let dir = temp_dir.path().join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        let hw_counter = HardwareCounterCell::new();
+        match index_type {
+            IndexType::MutableGridstore => {
+                let mut builder =
+                    DateTimeIndex::builder_gridstore(dir).with_out_of_range_policy(policy);
+                builder.init()?;
+                builder.add_point(0, payload, &hw_counter)?;
+                builder.finalize()
+            }
+            IndexType::Mmap | IndexType::RamMmap => {
+                let mut builder = DateTimeIndex::builder_mmap(&dir, false, &empty_deleted())
+                    .with_out_of_range_policy(policy);
+                builder.init()?;
+                builder.add_point(0, payload, &hw_counter)?;
+                builder.finalize()
+            }
+        }
+    };
+
+    let far_future = Value::from("+300000-01-01T00:00:00Z");
+    let before_year_one = Value::from("-0001-06-15T12:00:00Z");
+    let regular = Value::from("2024-01-01T00:00:00Z");
+
+    // Beyond the representable range: skipped with a warning, other values are kept
+    let index = build(
+        "skip",
+        OutOfRangePolicy::Skip,
+        &[&Value::Array(vec![far_future.clone(), regular.clone()])],
+    )
+    .unwrap();
+    let values = index.get_values(0).unwrap().collect_vec();
+    assert_eq!(
+        values,
+        vec![
+            DateTimePayloadType::from_str("2024-01-01T00:00:00Z")
+                .unwrap()
+                .timestamp()
+        ],
+    );
+
+    // Beyond the representable range: rejected
+    assert!(build("error", OutOfRangePolicy::Error, &[&far_future, &regular]).is_err());
+
+    // Before year 1 is still representable, and must round-trip without wrapping
+    let index = build(
+        "before_year_one",
+        OutOfRangePolicy::Error,
+        &[&before_year_one],
+    )
+    .unwrap();
+    let values = index.get_values(0).unwrap().collect_vec();
+    let expected = DateTimePayloadType::from_str("-0001-06-15T12:00:00Z").unwrap();
+    assert_eq!(values, vec![expected.timestamp()]);
+    assert!(values[0] < 0);
+    assert_eq!(
+        DateTimePayloadType::from_timestamp(values[0]),
+        Some(expected)
+    );
+}
+
+#[rstest]
+#[case(1)]
+#[case(3)]
+fn test_estimate_ram_bytes(#[case] values_per_point: usize) {
+    let (temp_dir, _index) = random_index(1000, values_per_point, IndexType::Mmap);
+
+    let estimate =
+        universal_numeric_index::UniversalNumericIndex::<FloatPayloadType>::estimate_ram_bytes(
+            temp_dir.path(),
+        )
+        .unwrap();
+
+    let index = open_index_from_disk(temp_dir.path(), IndexType::RamMmap, &empty_deleted());
+    let actual = index.inner().ram_usage_bytes();
+
+    assert!(
+        estimate.abs_diff(actual) <= actual / 10,
+        "estimate {estimate} too far from actual {actual}",
+    );
+
+    let missing = temp_dir.path().join("missing");
+    assert!(
+        universal_numeric_index::UniversalNumericIndex::<FloatPayloadType>::estimate_ram_bytes(
+            &missing,
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_external_mmap_builder_matches_in_memory() {
+    let temp_dir = Builder::new()
+        .prefix("test_numeric_index")
+        .tempdir()
+        .unwrap();
+    let in_memory_dir = temp_dir.path().join("in_memory");
+    let external_dir = temp_dir.path().join("external");
+
+    // Ascending ids with gaps, points without values and duplicate values within a point
+    let mut rng = StdRng::seed_from_u64(42);
+    let points = (0..2000)
+        .filter(|i| i % 7 != 3)
+        .map(|i| {
+            let values = match i % 5 {
+                0 => vec![],
+                1 => vec![Value::from(1.5), Value::from(1.5)],
+                n => (0..n)
+                    .map(|_| Value::from(rng.random_range(0.0..100.0)))
+                    .collect_vec(),
+            };
+            (i as PointOffsetType, values)
+        })
+        .collect_vec();
+
+    let hw_counter = HardwareCounterCell::new();
+    let mut in_memory_builder = NumericIndex::<FloatPayloadType, FloatPayloadType>::builder_mmap(
+        &in_memory_dir,
+        true,
+        &empty_deleted(),
+    );
+    // Budget of 64 pairs, far below the thousands of pairs indexed
+    let mut external_builder =
+        NumericIndex::<FloatPayloadType, FloatPayloadType>::builder_mmap_external(
+            &external_dir,
+            true,
+            &empty_deleted(),
+            64 * size_of::<Point<FloatPayloadType>>(),
+        );
+    in_memory_builder.init().unwrap();
+    external_builder.init().unwrap();
+
+    for (id, values) in &points {
+        let values = values.iter().collect_vec();
+        in_memory_builder
+            .add_point(*id, &values, &hw_counter)
+            .unwrap();
+        external_builder
+            .add_point(*id, &values, &hw_counter)
+            .unwrap();
+    }
+
+    // Points must be added in ascending order
+    assert!(
+        external_builder
+            .add_point(0, &[&Value::from(1.0)], &hw_counter)
+            .is_err()
+    );
+
+    let in_memory_index = in_memory_builder.finalize().unwrap();
+    let external_index = external_builder.finalize().unwrap();
+
+    let list_files = |dir: &Path| {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .sorted()
+            .collect_vec()
+    };
+    let files = list_files(&in_memory_dir);
+    assert_eq!(files, list_files(&external_dir));
+
+    // Histogram borders depend on insertion order, all other files are identical
+    let histogram_files = Histogram::<FloatPayloadType>::files(&in_memory_dir)
+        .into_iter()
+        .map(|path| path.file_name().unwrap().to_owned())
+        .collect_vec();
+    for file in files.iter().filter(|file| !histogram_files.contains(file)) {
+        assert_eq!(
+            std::fs::read(in_memory_dir.join(file)).unwrap(),
+            std::fs::read(external_dir.join(file)).unwrap(),
+            "{file:?} differs",
+        );
+    }
+
+    for id in 0..2000 {
+        assert_eq!(
+            in_memory_index
+                .get_values(id)
+                .map(|values| values.collect_vec()),
+            external_index
+                .get_values(id)
+                .map(|values| values.collect_vec()),
+        );
+    }
+
+    cardinality_request(
+        &external_index,
+        Range {
+            lt: Some(60.0),
+            gt: None,
+            gte: Some(10.0),
+            lte: None,
+        },
+        HwMeasurementAcc::new(),
+    );
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore, 1)]
+#[case(IndexType::Mmap, 1)]
+#[case(IndexType::RamMmap, 1)]
+#[case(IndexType::MutableGridstore, 3)]
+#[case(IndexType::Mmap, 3)]
+#[case(IndexType::RamMmap, 3)]
+fn test_count_in_range(#[case] index_type: IndexType, #[case] values_per_point: usize) {
+    let (_temp_dir, mut index) = random_index(1000, values_per_point, index_type);
+    let hw_counter = HardwareCounterCell::new();
+
+    let ranges = [
+        Range {
+            lt: None,
+            gt: None,
+            gte: Some(10.0),
+            lte: Some(45.0),
+        },
+        Range {
+            lt: Some(50.0),
+            gt: None,
+            gte: None,
+            lte: None,
+        },
+        Range {
+            lt: None,
+            gt: Some(-1.0),
+            gte: None,
+            lte: None,
+        },
+        // Empty
+        Range {
+            lt: None,
+            gt: None,
+            gte: Some(200.0),
+            lte: Some(300.0),
+        },
+        // Inverted
+        Range {
+            lt: Some(10.0),
+            gt: Some(20.0),
+            gte: None,
+            lte: None,
+        },
+    ];
+
+    let check = |index: &NumericIndex<FloatPayloadType, FloatPayloadType>| {
+        for range in ranges {
+            let range = Range {
+                lt: range.lt.map(OrderedFloat::from),
+                gt: range.gt.map(OrderedFloat::from),
+                gte: range.gte.map(OrderedFloat::from),
+                lte: range.lte.map(OrderedFloat::from),
+            };
+            let expected = index
+                .inner()
+                .filter(
+                    &FieldCondition::new_range(JsonPath::new("unused"), range),
+                    &hw_counter,
+                )
+                .unwrap()
+                .unwrap()
+                .unique()
+                .count();
+            let count = index
+                .inner()
+                .count_in_range(&RangeInterface::Float(range), &hw_counter)
+                .unwrap();
+            assert_eq!(count, expected, "{range:?}");
+        }
+    };
+
+    check(&index);
+
+    for idx in (0..1000).step_by(7) {
+        index.remove_point(idx).unwrap();
+    }
+    check(&index);
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_point_ids_near(#[case] index_type: IndexType) {
+    let (_temp_dir, mut index_builder) = get_index_builder(index_type);
+    let hw_counter = HardwareCounterCell::new();
+
+    let values = [0.1 + 0.2, 0.3, 0.3 - 1e-12, 0.31, 0.29, -0.3];
+    for (id, value) in values.into_iter().enumerate() {
+        let value = Value::from(value);
+        index_builder
+            .add_point(id as PointOffsetType, &[&value], &hw_counter)
+            .unwrap();
+    }
+    let index = index_builder.finalize().unwrap();
+
+    // Exact lookup misses the value with representation error
+    let exact = index
+        .inner()
+        .point_ids_by_value(0.3, &hw_counter)
+        .unwrap()
+        .sorted()
+        .collect_vec();
+    assert_eq!(exact, vec![1]);
+
+    let near = |value, epsilon| {
+        index
+            .inner()
+            .point_ids_near(value, epsilon, &hw_counter)
+            .unwrap()
+            .sorted()
+            .collect_vec()
+    };
+    assert_eq!(near(0.3, 1e-9), vec![0, 1, 2]);
+    assert_eq!(near(0.3, 0.015), vec![0, 1, 2, 3, 4]);
+    assert_eq!(near(0.3, 0.0), vec![1]);
+    assert!(near(0.5, 1e-9).is_empty());
+
+    assert!(
+        index
+            .inner()
+            .point_ids_near(0.3, -1.0, &hw_counter)
+            .is_err()
+    );
+    assert!(
+        index
+            .inner()
+            .point_ids_near(FloatPayloadType::NAN, 1.0, &hw_counter)
+            .is_err()
+    );
+}
+
+#[test]
+fn test_set_on_disk() {
+    let (_temp_dir, mut index) = random_index(1000, 2, IndexType::Mmap);
+    let condition = FieldCondition::new_range(
+        JsonPath::new("unused"),
+        Range {
+            lt: None,
+            gt: None,
+            gte: Some(OrderedFloat(20.0)),
+            lte: Some(OrderedFloat(60.0)),
+        },
+    );
+    let count_matches = |index: &NumericIndex<FloatPayloadType, FloatPayloadType>| {
+        let hw_counter = HardwareCounterCell::new();
+        let count = index
+            .inner()
+            .filter(&condition, &hw_counter)
+            .unwrap()
+            .unwrap()
+            .count();
+        (count, hw_counter.payload_index_io_read_counter().get())
+    };
+
+    assert!(!index.is_on_disk());
+    let (expected, io_read) = count_matches(&index);
+    assert_eq!(io_read, 0);
+
+    index.set_on_disk(true).unwrap();
+    assert!(index.is_on_disk());
+    assert_eq!(
+        index.get_storage_type(),
+        StorageType::Mmap { is_on_disk: true },
+    );
+    let (count, io_read) = count_matches(&index);
+    assert_eq!(count, expected);
+    assert!(io_read > 0);
+
+    index.set_on_disk(false).unwrap();
+    assert!(!index.is_on_disk());
+    assert_eq!(
+        index.get_storage_type(),
+        StorageType::Mmap { is_on_disk: false },
+    );
+    assert_eq!(count_matches(&index), (expected, 0));
+
+    // In-memory variants can't be moved to disk
+    let (_temp_dir, mut index) = random_index(10, 1, IndexType::MutableGridstore);
+    index.set_on_disk(false).unwrap();
+    assert!(index.set_on_disk(true).is_err());
+}
+
+#[rstest]
+#[case(false)]
+#[case(true)]
+fn test_mmap_builder_repeated_point(#[case] strict: bool) {
+    let temp_dir = Builder::new()
+        .prefix("test_repeated_point")
+        .tempdir()
+        .unwrap();
+    let mut builder = NumericIndex::<FloatPayloadType, FloatPayloadType>::builder_mmap(
+        temp_dir.path(),
+        false,
+        &empty_deleted(),
+    )
+    .with_strict_point_ids(strict);
+    builder.init().unwrap();
+
+    let hw_counter = HardwareCounterCell::new();
+    let first = Value::from(1.0);
+    let second = Value::from(2.0);
+    builder.add_point(0, &[&first], &hw_counter).unwrap();
+    builder.add_point(1, &[&first], &hw_counter).unwrap();
+
+    let result = builder.add_point(0, &[&second], &hw_counter);
+    assert_eq!(result.is_err(), strict);
+
+    let index = builder.finalize().unwrap();
+    let values = index.get_values(0).unwrap().collect_vec();
+    if strict {
+        assert_eq!(values, vec![1.0]);
+    } else {
+        // Last values win
+        assert_eq!(values, vec![2.0]);
+    }
+    assert_eq!(index.get_values(1).unwrap().collect_vec(), vec![1.0]);
+}
+
+#[test]
+fn test_encode_decode_key() {
+    let id = 42;
+
+    for value in [-1.5, 0.0, 3.25, FloatPayloadType::MAX] {
+        let key = NumericIndexInner::<FloatPayloadType>::encode_key(&value, id);
+        assert_eq!(NumericIndexInner::decode_key(&key), (id, value));
+    }
+    for value in [IntPayloadType::MIN, -7, 0, 1_700_000_000_123_456] {
+        let key = NumericIndexInner::<IntPayloadType>::encode_key(&value, id);
+        assert_eq!(NumericIndexInner::decode_key(&key), (id, value));
+    }
+    let uuid = UuidPayloadType::parse_str("f47ac10b-58cc-4372-a567-0e02b2c3d479").unwrap();
+    let key = NumericIndexInner::<UuidIntType>::encode_key(&uuid.as_u128(), id);
+    assert_eq!(NumericIndexInner::decode_key(&key), (id, uuid.as_u128()));
+
+    // Datetimes are indexed as their timestamp, but also have an encoding of their own
+    let datetime = DateTimePayloadType::from_timestamp(1_700_000_000_123_456).unwrap();
+    let key = datetime.encode_key(id);
+    assert_eq!(key, datetime.timestamp().encode_key(id));
+    assert_eq!(DateTimePayloadType::decode_key(&key), (id, datetime));
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_encoded_keys_follow_index_order(#[case] index_type: IndexType) {
+    let (_temp_dir, index) = random_index(200, 2, index_type);
+
+    let keys = index
+        .inner()
+        .orderable_values_range(Unbounded, Unbounded)
+        .unwrap()
+        .map(|(value, id)| NumericIndexInner::encode_key(&value, id))
+        .collect_vec();
+    assert_eq!(keys.len(), 400);
+    assert!(keys.is_sorted());
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+fn test_filter_three_valued(#[case] index_type: IndexType) {
+    let (_temp_dir, mut index_builder) = get_index_builder(index_type);
+    let hw_counter = HardwareCounterCell::new();
+
+    // Every 4th point has no value, and the last points are not in the index at all
+    let num_points: PointOffsetType = 25;
+    for id in (0..20).filter(|id| id % 4 != 0) {
+        let value = Value::from(FloatPayloadType::from(id));
+        index_builder.add_point(id, &[&value], &hw_counter).unwrap();
+    }
+    let index = index_builder.finalize().unwrap();
+
+    let condition = FieldCondition::new_range(
+        JsonPath::new("unused"),
+        Range {
+            lt: None,
+            gt: Some(OrderedFloat(10.0)),
+            gte: None,
+            lte: None,
+        },
+    );
+    let (matching, non_matching, missing) = index
+        .filter_three_valued(&condition, num_points, &hw_counter)
+        .unwrap()
+        .unwrap();
+    let matching = matching.collect_vec();
+    let non_matching = non_matching.collect_vec();
+    let missing = missing.collect_vec();
+
+    assert_eq!(matching, vec![11, 13, 14, 15, 17, 18, 19]);
+    assert_eq!(non_matching, vec![1, 2, 3, 5, 6, 7, 9, 10]);
+    assert_eq!(missing, vec![0, 4, 8, 12, 16, 20, 21, 22, 23, 24]);
+
+    // The buckets partition all points
+    let all = matching
+        .into_iter()
+        .chain(non_matching)
+        .chain(missing)
+        .sorted()
+        .collect_vec();
+    assert_eq!(all, (0..num_points).collect_vec());
+
+    // Conditions the index can't serve
+    let unsupported = FieldCondition::new_match(JsonPath::new("unused"), Match::new_text("text"));
+    assert!(
+        index
+            .filter_three_valued(&unsupported, num_points, &hw_counter)
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_remove_points_batch(#[case] index_type: IndexType) {
+    let (_temp_dir, mut batched) = random_index(500, 2, index_type);
+    let (_temp_dir, mut individual) = random_index(500, 2, index_type);
+
+    // Include an id past the end of the index
+    let ids = (0..500).step_by(3).chain([1000]).collect_vec();
+    batched.remove_points(&ids).unwrap();
+    for &id in &ids {
+        individual.remove_point(id).unwrap();
+    }
+
+    assert_eq!(
+        batched.inner().get_points_count(),
+        individual.inner().get_points_count(),
+    );
+    assert_eq!(
+        batched.count_indexed_points(),
+        individual.count_indexed_points(),
+    );
+    for idx in 0..500 {
+        assert_eq!(
+            batched.get_values(idx).map(|values| values.collect_vec()),
+            individual
+                .get_values(idx)
+                .map(|values| values.collect_vec()),
+        );
+        assert_eq!(batched.values_is_empty(idx), idx % 3 == 0);
+    }
+
+    let hw_counter = HardwareCounterCell::new();
+    let condition = FieldCondition::new_range(
+        JsonPath::new("unused"),
+        Range {
+            lt: None,
+            gt: None,
+            gte: Some(OrderedFloat(10.0)),
+            lte: Some(OrderedFloat(70.0)),
+        },
+    );
+    let filtered = |index: &NumericIndex<FloatPayloadType, FloatPayloadType>| {
+        index
+            .filter(&condition, &hw_counter)
+            .unwrap()
+            .unwrap()
+            .sorted()
+            .collect_vec()
+    };
+    assert_eq!(filtered(&batched), filtered(&individual));
+}
+
+/// The block index narrows down the boundary searches of a lookup to a single block, so fewer
+/// pairs are read than with a binary search over all of them.
+#[test]
+fn test_block_index_reduces_lookup_reads() {
+    let (temp_dir, index) = random_index(100_000, 1, IndexType::Mmap);
+    let block_index_path = temp_dir
+        .path()
+        .join(universal_numeric_index::BLOCK_INDEX_PATH);
+    assert!(index.inner().files().contains(&block_index_path));
+
+    let value = index.get_values(12_345).unwrap().next().unwrap();
+    let condition = FieldCondition::new_range(
+        JsonPath::new("unused"),
+        Range {
+            lt: None,
+            gt: None,
+            gte: Some(OrderedFloat(value)),
+            lte: Some(OrderedFloat(value)),
+        },
+    );
+    let lookup = |index: &NumericIndex<FloatPayloadType, FloatPayloadType>| {
+        let hw_counter = HardwareCounterCell::new();
+        let points = index
+            .filter(&condition, &hw_counter)
+            .unwrap()
+            .unwrap()
+            .collect_vec();
+        (points, hw_counter.payload_index_io_read_counter().get())
+    };
+    drop(index);
+
+    let index = open_index_from_disk(temp_dir.path(), IndexType::Mmap, &empty_deleted());
+    let (points, block_index_reads) = lookup(&index);
+    assert!(points.contains(&12_345));
+    drop(index);
+
+    // Indexes built without a block index fall back to a search over all pairs
+    fs_err::remove_file(&block_index_path).unwrap();
+    let index = open_index_from_disk(temp_dir.path(), IndexType::Mmap, &empty_deleted());
+    assert!(!index.inner().files().contains(&block_index_path));
+    let (full_search_points, full_search_reads) = lookup(&index);
+
+    assert_eq!(points, full_search_points);
+    assert!(
+        block_index_reads < full_search_reads,
+        "{block_index_reads} reads with block index, {full_search_reads} without",
+    );
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_filter_prefiltered(#[case] index_type: IndexType) {
+    let (_temp_dir, index) = random_index(1000, 2, index_type);
+    let hw_counter = HardwareCounterCell::new();
+    let condition = FieldCondition::new_range(
+        JsonPath::new("unused"),
+        Range {
+            lt: None,
+            gt: None,
+            gte: Some(OrderedFloat(20.0)),
+            lte: Some(OrderedFloat(60.0)),
+        },
+    );
+    let allowed = |idx: PointOffsetType| idx % 10 == 0;
+
+    let unfiltered = index
+        .filter(&condition, &hw_counter)
+        .unwrap()
+        .unwrap()
+        .collect_vec();
+    let expected = unfiltered
+        .iter()
+        .copied()
+        .filter(|&idx| allowed(idx))
+        .collect_vec();
+    let prefiltered = index
+        .filter_prefiltered(&condition, &allowed, &hw_counter)
+        .unwrap()
+        .unwrap()
+        .collect_vec();
+
+    assert_eq!(prefiltered, expected);
+    assert!(!prefiltered.is_empty());
+    assert!(prefiltered.len() < unfiltered.len());
+
+    // Unsupported conditions are reported the same way as by `filter`
+    let condition = FieldCondition::new_match(JsonPath::new("unused"), Match::new_text("text"));
+    assert!(
+        index
+            .filter_prefiltered(&condition, &allowed, &hw_counter)
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn test_build_progress() {
+    let temp_dir = Builder::new()
+        .prefix("test_numeric_index")
+        .tempdir()
+        .unwrap();
+    let in_memory_index: InMemoryNumericIndex<FloatPayloadType> = (0..100)
+        .map(|idx: PointOffsetType| (idx, f64::from(idx % 10)))
+        .collect();
+
+    let mut reported = Vec::new();
+    let index = UniversalNumericIndex::<FloatPayloadType, MmapFile>::build_with_progress(
+        &MmapFs,
+        in_memory_index,
+        temp_dir.path(),
+        false,
+        &empty_deleted(),
+        BuildOptions::default(),
+        &mut |fraction| reported.push(fraction),
+    )
+    .unwrap();
+
+    assert_eq!(index.get_points_count(), 100);
+    assert!(reported.len() > 1, "{reported:?}");
+    assert!(reported.is_sorted_by(|a, b| a < b), "{reported:?}");
+    assert!(reported.iter().all(|fraction| *fraction > 0.0));
+    assert_eq!(reported.last(), Some(&1.0));
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_verify_integrity(#[case] index_type: IndexType) {
+    let (_temp_dir, mut index) = random_index(1000, 3, index_type);
+    index.inner().verify_integrity().unwrap();
+
+    for idx in [0, 17, 500, 999] {
+        index.remove_point(idx).unwrap();
+    }
+    index.inner().verify_integrity().unwrap();
+}
+
+#[test]
+fn test_verify_integrity_detects_unsorted_pairs() {
+    let (temp_dir, index) = random_index(1000, 1, IndexType::Mmap);
+    drop(index);
+
+    // Swap the first two pairs
+    let pairs_path = temp_dir.path().join(universal_numeric_index::PAIRS_PATH);
+    let mut bytes = fs_err::read(&pairs_path).unwrap();
+    let pair_size = size_of::<Point<FloatPayloadType>>();
+    let (first, rest) = bytes.split_at_mut(pair_size);
+    first.swap_with_slice(&mut rest[..pair_size]);
+    fs_err::write(&pairs_path, bytes).unwrap();
+
+    let index = open_index_from_disk(temp_dir.path(), IndexType::Mmap, &empty_deleted());
+    let err = index.inner().verify_integrity().unwrap_err();
+    assert!(err.to_string().contains("pairs are not sorted"), "{err}");
+}
+
+#[rstest]
+#[case(1)]
+#[case(3)]
+fn test_values_range_size_matches_across_storages(#[case] values_per_point: usize) {
+    let (_mutable_dir, mut mutable) =
+        random_index(1000, values_per_point, IndexType::MutableGridstore);
+    let (_mmap_dir, mut mmap) = random_index(1000, values_per_point, IndexType::Mmap);
+    let (_immutable_dir, mut immutable) = random_index(1000, values_per_point, IndexType::RamMmap);
+    let hw_counter = HardwareCounterCell::new();
+
+    let value_of = |point: PointOffsetType| mutable.get_values(point).unwrap().next().unwrap();
+    let (low, high) = (value_of(10), value_of(20));
+    let (low, high) = if low < high { (low, high) } else { (high, low) };
+    let bounds = [
+        (Unbounded, Unbounded),
+        (Included(Point::new(low, 0)), Unbounded),
+        (Unbounded, Excluded(Point::new(high, 0))),
+        (
+            Included(Point::new(low, PointOffsetType::MIN)),
+            Included(Point::new(low, PointOffsetType::MAX)),
+        ),
+        (
+            Excluded(Point::new(low, 10)),
+            Included(Point::new(high, 20)),
+        ),
+        (Included(Point::new(20.0, 0)), Excluded(Point::new(60.0, 0))),
+        // Inverted
+        (Included(Point::new(60.0, 0)), Included(Point::new(20.0, 0))),
+    ];
+
+    let check = |mutable: &NumericIndex<FloatPayloadType, FloatPayloadType>,
+                 others: [&NumericIndex<FloatPayloadType, FloatPayloadType>; 2]| {
+        for (start, end) in bounds {
+            let expected = mutable
+                .inner()
+                .values_range_size(start, end, &hw_counter)
+                .unwrap();
+            for other in others {
+                let size = other
+                    .inner()
+                    .values_range_size(start, end, &hw_counter)
+                    .unwrap();
+                assert_eq!(size, expected, "{start:?}..{end:?}");
+            }
+        }
+    };
+    check(&mutable, [&mmap, &immutable]);
+
+    // Counting stays consistent with values_range
+    for (start, end) in bounds {
+        let pairs_count = if check_boundaries(&start, &end) {
+            mutable
+                .inner()
+                .values_range(start, end, &hw_counter)
+                .unwrap()
+                .count()
+        } else {
+            0
+        };
+        assert_eq!(
+            mutable
+                .inner()
+                .values_range_size(start, end, &hw_counter)
+                .unwrap(),
+            pairs_count,
+        );
+    }
+
+    for idx in (0..1000).step_by(7) {
+        mutable.remove_point(idx).unwrap();
+        mmap.remove_point(idx).unwrap();
+        immutable.remove_point(idx).unwrap();
+    }
+    for (start, end) in bounds {
+        let expected = mutable
+            .inner()
+            .live_values_range_size(start, end, &hw_counter)
+            .unwrap();
+        for other in [&mmap, &immutable] {
+            let size = other
+                .inner()
+                .live_values_range_size(start, end, &hw_counter)
+                .unwrap();
+            assert_eq!(size, expected, "{start:?}..{end:?}");
+        }
+    }
+}
+
+#[test]
+fn test_live_values_range_size_with_points_without_values() {
+    let temp_dir = Builder::new()
+        .prefix("test_numeric_index")
+        .tempdir()
+        .unwrap();
+    let builder = NumericIndex::<IntPayloadType, IntPayloadType>::builder_mmap(
+        temp_dir.path(),
+        true,
+        &empty_deleted(),
+    );
+    let index = build_int_index(builder, 100, |i| {
+        if i % 10 == 0 {
+            Value::Array(vec![])
+        } else {
+            Value::from(IntPayloadType::from(i))
+        }
+    });
+    let hw_counter = HardwareCounterCell::new();
+
+    // Points without values don't need the live pairs to be counted one by one
+    let size = index
+        .inner()
+        .live_values_range_size(Unbounded, Unbounded, &hw_counter)
+        .unwrap();
+    assert_eq!(size, 90);
+    assert_eq!(hw_counter.payload_index_io_read_counter().get(), 0);
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_record_actual_cardinality(#[case] index_type: IndexType) {
+    let (_temp_dir, index) = random_index(1000, 1, index_type);
+    let hw_counter = HardwareCounterCell::new();
+    let condition = FieldCondition::new_range(
+        JsonPath::new("unused"),
+        Range {
+            lt: None,
+            gt: None,
+            gte: Some(OrderedFloat(20.0)),
+            lte: Some(OrderedFloat(60.0)),
+        },
+    );
+    let estimated = index
+        .estimate_cardinality(&condition, &hw_counter)
+        .unwrap()
+        .unwrap()
+        .exp;
+    assert_eq!(
+        index.get_telemetry_data().cardinality_estimation_error,
+        None
+    );
+
+    // Accurate estimations don't contribute an error
+    index
+        .record_actual_cardinality(&condition, estimated)
+        .unwrap();
+    assert_eq!(
+        index.get_telemetry_data().cardinality_estimation_error,
+        Some(0.0),
+    );
+
+    // Realized counts 8 times the estimate, after heavy inserts into the range
+    for _ in 0..9 {
+        index
+            .record_actual_cardinality(&condition, 8 * (estimated + 1) - 1)
+            .unwrap();
+    }
+    let error = index
+        .get_telemetry_data()
+        .cardinality_estimation_error
+        .unwrap();
+    assert!((error - 2.7).abs() < 1e-9, "{error}");
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore, false)]
+#[case(IndexType::Mmap, true)]
+#[case(IndexType::RamMmap, true)]
+fn test_needs_compaction(#[case] index_type: IndexType, #[case] compacts: bool) {
+    let (_temp_dir, mut index) = random_index(100, 2, index_type);
+    assert!(!index.inner().needs_compaction(0.0));
+
+    for idx in 0..10 {
+        index.remove_point(idx).unwrap();
+    }
+    assert!(!index.inner().needs_compaction(0.1));
+
+    index.remove_point(10).unwrap();
+    assert_eq!(index.inner().needs_compaction(0.1), compacts);
+}
+
+/// Points without values are marked deleted at build time, but they are no garbage to compact.
+#[test]
+fn test_needs_compaction_sparse_field() {
+    let hw_counter = HardwareCounterCell::new();
+    let (_temp_dir, mut builder) = get_index_builder(IndexType::Mmap);
+    for idx in 0..100 {
+        let values = if idx % 10 == 0 {
+            vec![Value::from(f64::from(idx))]
+        } else {
+            vec![]
+        };
+        builder
+            .add_point(idx, &values.iter().collect_vec(), &hw_counter)
+            .unwrap();
+    }
+    let mut index = builder.finalize().unwrap();
+    assert!(!index.inner().needs_compaction(0.0));
+
+    // Removing a point without values frees nothing
+    index.remove_point(1).unwrap();
+    assert!(!index.inner().needs_compaction(0.0));
+
+    // One of ten points with values
+    index.remove_point(10).unwrap();
+    assert!(index.inner().needs_compaction(0.05));
+    assert!(!index.inner().needs_compaction(0.1));
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_range_query(#[case] index_type: IndexType) {
+    use std::str::FromStr;
+
+    let hw_counter = HardwareCounterCell::new();
+    let (_temp_dir, index) = random_index(1000, 2, index_type);
+    let float_range = Range {
+        lt: None,
+        gt: None,
+        gte: Some(OrderedFloat(20.0)),
+        lte: Some(OrderedFloat(60.0)),
+    };
+    let condition = FieldCondition::new_range(JsonPath::new("unused"), float_range);
+
+    let points = index
+        .range_query(&RangeInterface::Float(float_range), &hw_counter)
+        .unwrap()
+        .sorted()
+        .dedup()
+        .collect_vec();
+    let expected = index
+        .filter(&condition, &hw_counter)
+        .unwrap()
+        .unwrap()
+        .sorted()
+        .dedup()
+        .collect_vec();
+    assert!(!points.is_empty());
+    assert_eq!(points, expected);
+
+    let datetime = |s: &str| DateTimePayloadType::from_str(s).unwrap();
+    let datetime_range = RangeInterface::DateTime(Range {
+        lt: None,
+        gt: None,
+        gte: Some(datetime("1970-01-01T00:00:00Z")),
+        lte: Some(datetime("1970-01-01T00:00:00.000060Z")),
+    });
+    assert!(index.range_query(&datetime_range, &hw_counter).is_err());
+
+    // Datetime index only takes datetime ranges
+    type DateTimeIndex = NumericIndex<IntPayloadType, DateTimePayloadType>;
+    let dir = Builder::new().prefix("datetime_index").tempdir().unwrap();
+    let mut builder = DateTimeIndex::builder_gridstore(dir.path().to_path_buf());
+    builder.init().unwrap();
+    for (idx, value) in [
+        "1970-01-01T00:00:00.000010Z",
+        "1970-01-01T00:00:00.000100Z",
+        "1970-01-01T00:00:00.000050Z",
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        builder
+            .add_point(idx as PointOffsetType, &[&Value::from(value)], &hw_counter)
+            .unwrap();
+    }
+    let datetime_index = builder.finalize().unwrap();
+
+    let points = datetime_index
+        .range_query(&datetime_range, &hw_counter)
+        .unwrap()
+        .sorted()
+        .collect_vec();
+    assert_eq!(points, vec![0, 2]);
+    assert!(
+        datetime_index
+            .range_query(&RangeInterface::Float(float_range), &hw_counter)
+            .is_err()
+    );
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+fn test_json_path_value_extractor(#[case] index_type: IndexType) {
+    let temp_dir = Builder::new().prefix("nested_index").tempdir().unwrap();
+    let extractor = || json_path_extractor(JsonPath::new("meta.price"));
+    let payloads = [
+        serde_json::json!({"meta": {"price": 5}}),
+        serde_json::json!({"meta": {"price": 12, "rating": 5}}),
+        serde_json::json!([{"meta": {"price": 1}}, {"meta": {"price": [3, 20]}}]),
+        serde_json::json!({"price": 5}),
+        serde_json::json!(5),
+        serde_json::json!({"meta": {"price": 7.5}}),
+    ];
+
+    let hw_counter = HardwareCounterCell::new();
+    let mut builder = match index_type {
+        IndexType::MutableGridstore => IndexBuilder::MutableGridstore(
+            NumericIndex::builder_gridstore(temp_dir.path().to_path_buf())
+                .with_value_extractor(extractor()),
+        ),
+        IndexType::Mmap | IndexType::RamMmap => IndexBuilder::Mmap(
+            NumericIndex::builder_mmap(temp_dir.path(), false, &empty_deleted())
+                .with_value_extractor(extractor()),
+        ),
+    };
+    match &mut builder {
+        IndexBuilder::MutableGridstore(builder) => builder.init().unwrap(),
+        IndexBuilder::Mmap(builder) => builder.init().unwrap(),
+    }
+    for (idx, payload) in payloads.iter().enumerate() {
+        builder
+            .add_point(idx as PointOffsetType, &[payload], &hw_counter)
+            .unwrap();
+    }
+    let index = builder.finalize().unwrap();
+
+    assert_eq!(
+        index
+            .get_values(2)
+            .unwrap()
+            .sorted_by(f64::total_cmp)
+            .collect_vec(),
+        vec![1.0, 3.0, 20.0]
+    );
+    assert!(index.inner().values_is_empty(3));
+    assert!(index.inner().values_is_empty(4));
+
+    let condition = FieldCondition::new_range(
+        JsonPath::new("meta.price"),
+        Range {
+            lt: None,
+            gt: None,
+            gte: Some(OrderedFloat(3.0)),
+            lte: Some(OrderedFloat(10.0)),
+        },
+    );
+    let points = index
+        .filter(&condition, &hw_counter)
+        .unwrap()
+        .unwrap()
+        .sorted()
+        .collect_vec();
+    assert_eq!(points, vec![0, 2, 5]);
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_for_each_point_values(#[case] index_type: IndexType) {
+    let (_temp_dir, mut index) = random_index(200, 3, index_type);
+    for idx in (0..200).step_by(7) {
+        index.remove_point(idx).unwrap();
+    }
+
+    let expected = (0..200)
+        .filter_map(|idx| {
+            let values = index.inner().get_values(idx)?.collect_vec();
+            (!values.is_empty()).then_some((idx, values))
+        })
+        .collect_vec();
+
+    let mut visited = Vec::new();
+    index
+        .inner()
+        .for_each_point_values(|idx, values| visited.push((idx, values.to_vec())))
+        .unwrap();
+
+    assert_eq!(visited, expected);
+    assert!(visited.iter().all(|(idx, _)| idx % 7 != 0));
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore, 10, 500)]
+#[case(IndexType::Mmap, 20, 1000)]
+#[case(IndexType::RamMmap, 20, 1000)]
+fn test_estimate_scan_depth(
+    #[case] index_type: IndexType,
+    #[case] expected_depth: usize,
+    #[case] expected_max_depth: usize,
+) {
+    let (_temp_dir, mut index) = random_index(1000, 1, index_type);
+    let hw_counter = HardwareCounterCell::new();
+    let range = RangeInterface::Float(Range {
+        lt: None,
+        gt: None,
+        gte: Some(OrderedFloat(0.0)),
+        lte: Some(OrderedFloat(100.0)),
+    });
+    assert_eq!(
+        index
+            .inner()
+            .estimate_scan_depth(&range, 0, &hw_counter)
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        index
+            .inner()
+            .estimate_scan_depth(&range, 10, &hw_counter)
+            .unwrap(),
+        10
+    );
+
+    for idx in (0..1000).step_by(2) {
+        index.remove_point(idx).unwrap();
+    }
+
+    // Only mutable indexes drop the entries of deleted points right away
+    assert_eq!(
+        index
+            .inner()
+            .estimate_scan_depth(&range, 10, &hw_counter)
+            .unwrap(),
+        expected_depth,
+    );
+    assert_eq!(
+        index
+            .inner()
+            .estimate_scan_depth(&range, 10_000, &hw_counter)
+            .unwrap(),
+        expected_max_depth,
+    );
+}
+
+#[test]
+fn test_migrate_datetime_encoding() {
+    use super::universal_numeric_index::{DateTimePrecision, migrate_datetime_encoding};
+
+    let temp_dir = Builder::new()
+        .prefix("test_numeric_index")
+        .tempdir()
+        .unwrap();
+    let path = temp_dir.path().join("datetime_index");
+    let datetimes = [
+        "2014-01-01T00:00:00Z",
+        "2024-05-17T12:34:56.789Z",
+        "1969-12-31T23:59:59.999Z",
+    ]
+    .map(|datetime| datetime.parse::<DateTimePayloadType>().unwrap());
+
+    // Point 1 has no values, it must keep its id through the migration
+    let mut in_memory_index = InMemoryNumericIndex::default();
+    in_memory_index.add_many_to_list(0, vec![datetimes[0].0.timestamp_millis()]);
+    in_memory_index.add_many_to_list(1, vec![]);
+    in_memory_index.add_many_to_list(
+        2,
+        vec![
+            datetimes[1].0.timestamp_millis(),
+            datetimes[2].0.timestamp_millis(),
+        ],
+    );
+    drop(
+        UniversalNumericIndex::<IntPayloadType>::build(
+            &MmapFs,
+            in_memory_index,
+            &path,
+            false,
+            &empty_deleted(),
+        )
+        .unwrap(),
+    );
+
+    let decoded = || {
+        let index =
+            UniversalNumericIndex::<IntPayloadType>::open(&MmapFs, &path, false, &empty_deleted())
+                .unwrap()
+                .unwrap();
+        (0..3)
+            .map(|idx| {
+                index
+                    .get_values(idx)
+                    .map(|values| {
+                        values
+                            .sorted()
+                            .map(|ts| DateTimePayloadType::from_timestamp(ts).unwrap())
+                            .collect_vec()
+                    })
+                    .unwrap_or_default()
+            })
+            .collect_vec()
+    };
+    assert_ne!(decoded()[0], vec![datetimes[0]]);
+
+    let expected = vec![vec![datetimes[0]], vec![], vec![datetimes[2], datetimes[1]]];
+    for _ in 0..2 {
+        migrate_datetime_encoding(
+            &path,
+            DateTimePrecision::Milliseconds,
+            DateTimePrecision::Microseconds,
+        )
+        .unwrap();
+        assert_eq!(decoded(), expected);
+    }
 
-    // Per-point queries: deleted points report no values; live points still
-    // see their original value.
-    assert_eq!(index.values_count(0), 1);
-    assert_eq!(index.values_count(1), 0);
-    assert_eq!(index.values_count(2), 1);
-    assert_eq!(index.values_count(3), 0);
+    // Recorded precision doesn't match
+    migrate_datetime_encoding(
+        &path,
+        DateTimePrecision::Seconds,
+        DateTimePrecision::Milliseconds,
+    )
+    .unwrap_err();
+    assert_eq!(decoded(), expected);
 }
 
-/// Regression test for #9049: fractional `f64` range bounds were truncated
-/// toward zero when converted to the integer index's storage type, so a
-/// `gte: 1.5` predicate (meaning `>= 1.5`) wrongly matched the integer
-/// value `1`. The indexed path must agree with the documented
-/// `f64`-comparison semantics for every fractional bound.
+/// A directory swap interrupted by a crash is completed or rolled back on open.
 #[test]
-fn test_integer_index_fractional_range_bounds() {
-    use crate::types::IntPayloadType;
+fn test_open_recovers_interrupted_replace() {
+    let temp_dir = Builder::new()
+        .prefix("test_numeric_index")
+        .tempdir()
+        .unwrap();
+    let path = temp_dir.path().join("index");
+    let old_path = temp_dir.path().join("index.old");
+    let build = |path: &Path, value: IntPayloadType| {
+        let in_memory_index: InMemoryNumericIndex<IntPayloadType> =
+            (0..10).map(|idx: PointOffsetType| (idx, value)).collect();
+        drop(
+            UniversalNumericIndex::<IntPayloadType>::build(
+                &MmapFs,
+                in_memory_index,
+                path,
+                false,
+                &empty_deleted(),
+            )
+            .unwrap(),
+        );
+    };
+    let open_value = || {
+        let index =
+            UniversalNumericIndex::<IntPayloadType>::open(&MmapFs, &path, false, &empty_deleted())
+                .unwrap()
+                .unwrap();
+        index.get_values(0).unwrap().next().unwrap()
+    };
+
+    // Crashed before the new directory was moved in: the old index is moved back
+    build(&path, 1);
+    fs_err::rename(&path, &old_path).unwrap();
+    assert_eq!(open_value(), 1);
+    assert!(!old_path.exists());
+
+    // Crashed after the new directory was moved in: the old index is removed
+    let new_path = temp_dir.path().join("index.new");
+    build(&new_path, 2);
+    fs_err::rename(&path, &old_path).unwrap();
+    fs_err::rename(&new_path, &path).unwrap();
+    assert_eq!(open_value(), 2);
+    assert!(!old_path.exists());
+}
+
+#[test]
+fn test_compaction_keeps_datetime_precision() {
+    use super::universal_numeric_index::{DateTimePrecision, migrate_datetime_encoding};
 
     let temp_dir = Builder::new()
-        .prefix("test_integer_index_fractional_range_bounds")
+        .prefix("test_numeric_index")
         .tempdir()
         .unwrap();
+    let path = temp_dir.path().join("datetime_index");
+    let datetime = "2024-05-17T12:34:56.789Z"
+        .parse::<DateTimePayloadType>()
+        .unwrap();
+    let in_memory_index: InMemoryNumericIndex<IntPayloadType> = (0..10)
+        .map(|idx| (idx, datetime.0.timestamp_millis()))
+        .collect();
+    drop(
+        UniversalNumericIndex::<IntPayloadType>::build(
+            &MmapFs,
+            in_memory_index,
+            &path,
+            false,
+            &empty_deleted(),
+        )
+        .unwrap(),
+    );
 
-    let mut builder = NumericIndex::<IntPayloadType, IntPayloadType>::builder_gridstore(
-        temp_dir.path().to_path_buf(),
+    let migrate = || {
+        migrate_datetime_encoding(
+            &path,
+            DateTimePrecision::Milliseconds,
+            DateTimePrecision::Microseconds,
+        )
+    };
+    let open = || {
+        UniversalNumericIndex::<IntPayloadType>::open(&MmapFs, &path, false, &empty_deleted())
+            .unwrap()
+            .unwrap()
+    };
+    migrate().unwrap();
+
+    let mut index = open();
+    index.remove_point(3);
+    let compacted = index.compact(&temp_dir.path().join("compacted")).unwrap();
+    index.swap_in(compacted).unwrap();
+    assert_eq!(
+        index.datetime_precision(),
+        Some(DateTimePrecision::Microseconds),
     );
-    builder.init().unwrap();
+    drop(index);
+
+    // Already migrated, the timestamps must not be converted twice
+    migrate().unwrap();
+    let index = open();
+    for idx in (0..10).filter(|idx| *idx != 3) {
+        assert_eq!(
+            index.get_values(idx).unwrap().collect_vec(),
+            vec![datetime.timestamp()],
+        );
+    }
+}
 
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_has_value_bitmap(#[case] index_type: IndexType) {
+    let (temp_dir, mut builder) = get_index_builder(index_type);
     let hw_counter = HardwareCounterCell::new();
-    let v1 = Value::from(1_i64);
-    let v2 = Value::from(2_i64);
-    builder.add_point(0, &[&v1], &hw_counter).unwrap();
-    builder.add_point(1, &[&v2], &hw_counter).unwrap();
+    let values = (0..50)
+        .map(|i| match i % 5 {
+            0 => Value::Array(vec![]),
+            _ => Value::from(f64::from(i)),
+        })
+        .collect_vec();
+    for (idx, value) in values.iter().enumerate() {
+        builder
+            .add_point(idx as PointOffsetType, &[value], &hw_counter)
+            .unwrap();
+    }
+    drop(builder.finalize().unwrap());
+    let mut index = open_index_from_disk(temp_dir.path(), index_type, &empty_deleted());
+    for idx in [1, 7, 10, 49] {
+        index.remove_point(idx).unwrap();
+    }
+
+    let bitmap = index.inner().has_value_bitmap();
+    for idx in 0..values.len() {
+        let expected = idx % 5 != 0 && ![1, 7, 10, 49].contains(&idx);
+        let has_value = bitmap.get(idx).is_some_and(|bit| *bit);
+        assert_eq!(has_value, expected, "point {idx}");
+    }
+}
+
+#[test]
+fn test_compact_and_swap_in_while_reading() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let (temp_dir, index) = random_index(1000, 2, IndexType::Mmap);
+    let NumericIndexInner::Mmap(mut index) = index.inner else {
+        panic!("Expected mmap index");
+    };
+    let mut deleted = BitVec::repeat(false, 1000);
+    for idx in (0..1000).step_by(3) {
+        index.remove_point(idx);
+        deleted.set(idx as usize, true);
+    }
+    let expected = (0..1000)
+        .map(|idx| index.get_values(idx).map(|values| values.collect_vec()))
+        .collect_vec();
+
+    let read_all = |index: &UniversalNumericIndex<FloatPayloadType>| {
+        (0..1000)
+            .map(|idx| index.get_values(idx).map(|values| values.collect_vec()))
+            .collect_vec()
+    };
+
+    // A second handle on the old files, as held by a concurrent reader
+    let reader =
+        UniversalNumericIndex::<FloatPayloadType>::open(&MmapFs, temp_dir.path(), true, &deleted)
+            .unwrap()
+            .unwrap();
+    let swapped = AtomicBool::new(false);
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            let mut reads_after_swap = 0;
+            while reads_after_swap < 3 {
+                if swapped.load(Ordering::Acquire) {
+                    reads_after_swap += 1;
+                }
+                assert_eq!(read_all(&reader), expected);
+                reader.verify_integrity().unwrap();
+            }
+        });
+
+        let compacted_dir = Builder::new()
+            .prefix("test_numeric_index_compacted")
+            .tempdir()
+            .unwrap();
+        let compacted = index.compact(compacted_dir.path()).unwrap();
+        assert_eq!(compacted.deleted_ratio(), index.deleted_ratio());
+        index.swap_in(compacted).unwrap();
+        swapped.store(true, Ordering::Release);
+    });
+
+    assert_eq!(read_all(&index), expected);
+    index.verify_integrity().unwrap();
+
+    // The deleted points are gone from the files, without re-supplying the deletions
+    let reopened = UniversalNumericIndex::<FloatPayloadType>::open(
+        &MmapFs,
+        temp_dir.path(),
+        true,
+        &empty_deleted(),
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(read_all(&reopened), expected);
+    reopened.verify_integrity().unwrap();
+    assert!(
+        reopened
+            .files()
+            .iter()
+            .all(|file| file.starts_with(temp_dir.path()) && file.exists())
+    );
+}
+
+#[test]
+fn test_swap_in_keeps_later_deletions() {
+    let (_temp_dir, index) = random_index(100, 2, IndexType::Mmap);
+    let NumericIndexInner::Mmap(mut index) = index.inner else {
+        panic!("Expected mmap index");
+    };
+    index.remove_point(10);
+
+    let compacted_dir = Builder::new()
+        .prefix("test_numeric_index_compacted")
+        .tempdir()
+        .unwrap();
+    let compacted = index.compact(compacted_dir.path()).unwrap();
+    // Deleted after compaction, its values are still in the compacted files
+    index.remove_point(20);
+    index.swap_in(compacted).unwrap();
+
+    assert!(index.get_values(10).is_none());
+    assert!(index.get_values(20).is_none());
+    assert_eq!(index.get_points_count(), 98);
+    assert_eq!(index.deleted_ratio(), 1.0 / 99.0);
+    index.verify_integrity().unwrap();
+}
+
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_compiled_condition_matches_direct_path(#[case] index_type: IndexType) {
+    let (_temp_dir, index) = random_index(1000, 2, index_type);
+    let hw_counter = HardwareCounterCell::new();
+
+    let conditions = [
+        FieldCondition::new_range(
+            JsonPath::new("unused"),
+            Range {
+                lt: Some(OrderedFloat(40.0)),
+                gt: None,
+                gte: Some(OrderedFloat(20.0)),
+                lte: None,
+            },
+        ),
+        FieldCondition::new_range(
+            JsonPath::new("unused"),
+            Range {
+                lt: None,
+                gt: Some(OrderedFloat(90.5)),
+                gte: None,
+                lte: None,
+            },
+        ),
+        // Empty range
+        FieldCondition::new_range(
+            JsonPath::new("unused"),
+            Range {
+                lt: Some(OrderedFloat(10.0)),
+                gt: Some(OrderedFloat(10.0)),
+                gte: None,
+                lte: None,
+            },
+        ),
+    ];
+    for condition in &conditions {
+        let compiled = NumericIndexInner::compile_condition(condition).unwrap();
+        let direct = index
+            .inner()
+            .filter(condition, &hw_counter)
+            .unwrap()
+            .unwrap()
+            .sorted()
+            .collect_vec();
+        let points = index
+            .inner()
+            .filter_compiled(&compiled, &hw_counter)
+            .unwrap()
+            .sorted()
+            .collect_vec();
+        assert_eq!(points, direct);
+        assert_eq!(
+            index
+                .inner()
+                .estimate_compiled(&compiled, &hw_counter)
+                .unwrap(),
+            index
+                .inner()
+                .estimate_cardinality(condition, &hw_counter)
+                .unwrap()
+                .unwrap(),
+        );
+    }
+
+    let text_condition = FieldCondition::new_match(JsonPath::new("unused"), Match::new_text("10"));
+    assert!(NumericIndexInner::<FloatPayloadType>::compile_condition(&text_condition).is_none());
+}
+
+#[test]
+fn test_compiled_uuid_condition_matches_direct_path() {
+    let temp_dir = Builder::new()
+        .prefix("test_numeric_index")
+        .tempdir()
+        .unwrap();
+    let hw_counter = HardwareCounterCell::new();
+    let mut builder = NumericIndex::<UuidIntType, UuidPayloadType>::builder_mmap(
+        temp_dir.path(),
+        false,
+        &empty_deleted(),
+    );
+    builder.init().unwrap();
+    for i in 0..100 {
+        let value = Value::String(UuidPayloadType::from_u128(u128::from(i % 10)).to_string());
+        builder.add_point(i, &[&value], &hw_counter).unwrap();
+    }
     let index = builder.finalize().unwrap();
 
-    let run = |range: Range<FloatPayloadType>| -> Vec<PointOffsetType> {
-        let cond = FieldCondition::new_range(JsonPath::new("price"), range.map(OrderedFloat::from));
-        let hw = HardwareCounterCell::new();
-        let mut ids: Vec<_> = index.inner().filter(&cond, &hw).unwrap().unwrap().collect();
-        ids.sort();
-        ids
+    let condition = FieldCondition::new_match(
+        JsonPath::new("unused"),
+        Match::new_value(ValueVariants::String(
+            UuidPayloadType::from_u128(3).to_string(),
+        )),
+    );
+    let compiled = NumericIndexInner::compile_condition(&condition).unwrap();
+    let points = index
+        .inner()
+        .filter_compiled(&compiled, &hw_counter)
+        .unwrap()
+        .sorted()
+        .collect_vec();
+    assert_eq!(points, (3..100).step_by(10).collect_vec());
+    assert_eq!(
+        points,
+        index
+            .inner()
+            .filter(&condition, &hw_counter)
+            .unwrap()
+            .unwrap()
+            .sorted()
+            .collect_vec(),
+    );
+    assert_eq!(
+        index
+            .inner()
+            .estimate_compiled(&compiled, &hw_counter)
+            .unwrap(),
+        index
+            .inner()
+            .estimate_cardinality(&condition, &hw_counter)
+            .unwrap()
+            .unwrap(),
+    );
+}
+
+#[test]
+fn test_validate_condition() {
+    use std::str::FromStr;
+
+    type FloatIndex = NumericIndex<FloatPayloadType, FloatPayloadType>;
+    type UuidIndex = NumericIndex<UuidIntType, UuidPayloadType>;
+
+    let range = |gt, gte, lt, lte| {
+        FieldCondition::new_range(
+            JsonPath::new("unused"),
+            Range {
+                lt: lt.map(OrderedFloat),
+                gt: gt.map(OrderedFloat),
+                gte: gte.map(OrderedFloat),
+                lte: lte.map(OrderedFloat),
+            },
+        )
     };
 
-    // `gte: 1.5` ⇒ `>= 1.5` ⇒ integer 1 must NOT match, integer 2 must match.
     assert_eq!(
-        run(Range {
-            gte: Some(1.5),
-            ..Default::default()
+        FloatIndex::validate_condition(&range(Some(5.0), None, Some(1.5), None)),
+        Err(FilterError::InvertedRange {
+            lower: "5".to_string(),
+            upper: "1.5".to_string(),
         }),
-        vec![1],
-        "gte: 1.5 must exclude integer 1",
+    );
+    assert!(matches!(
+        FloatIndex::validate_condition(&range(None, Some(2.0), None, Some(1.0))),
+        Err(FilterError::InvertedRange { .. }),
+    ));
+    // Empty, but not malformed
+    assert_eq!(
+        FloatIndex::validate_condition(&range(Some(1.0), None, Some(1.0), None)),
+        Ok(()),
+    );
+    assert_eq!(
+        FloatIndex::validate_condition(&range(Some(1.0), None, None, None)),
+        Ok(()),
     );
 
-    // `gt: 1.5` ⇒ `> 1.5` ⇒ integer 1 must NOT match, integer 2 must match.
+    let datetime_range = FieldCondition::new_datetime_range(
+        JsonPath::new("unused"),
+        Range {
+            lt: Some(DateTimePayloadType::from_str("2024-01-01T00:00:00Z").unwrap()),
+            gt: None,
+            gte: Some(DateTimePayloadType::from_str("2025-01-01T00:00:00Z").unwrap()),
+            lte: None,
+        },
+    );
+    assert!(matches!(
+        FloatIndex::validate_condition(&datetime_range),
+        Err(FilterError::InvertedRange { .. }),
+    ));
+
+    let keyword = |keyword: &str| {
+        FieldCondition::new_match(
+            JsonPath::new("unused"),
+            Match::new_value(ValueVariants::String(keyword.to_string())),
+        )
+    };
+    let Err(FilterError::NotAUuid {
+        keyword: not_a_uuid,
+        reason,
+    }) = UuidIndex::validate_condition(&keyword("not a uuid"))
+    else {
+        panic!("keyword which is not a UUID must be rejected");
+    };
+    assert_eq!(not_a_uuid, "not a uuid");
+    assert!(!reason.is_empty());
     assert_eq!(
-        run(Range {
-            gt: Some(1.5),
-            ..Default::default()
+        UuidIndex::validate_condition(&keyword(&UuidPayloadType::from_u128(3).to_string())),
+        Ok(()),
+    );
+    // A float index can't serve keywords, which is not a mistake in the condition
+    assert_eq!(
+        FloatIndex::validate_condition(&keyword("not a uuid")),
+        Ok(())
+    );
+
+    let err = OperationError::from(FilterError::NotAUuid {
+        keyword: "x".to_string(),
+        reason: "invalid length".to_string(),
+    });
+    assert!(matches!(err, OperationError::ValidationError { .. }));
+}
+
+/// Concatenated pages of `stream_range_page` must equal a single full `stream_range` scan.
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_stream_range_page_resume(#[case] index_type: IndexType) {
+    const PAGE_SIZE: usize = 37;
+
+    let (temp_dir, index) = random_index(1000, 2, index_type);
+    let ranges = [
+        RangeInterface::Float(Range::default()),
+        RangeInterface::Float(Range {
+            lt: Some(OrderedFloat(75.0)),
+            gt: Some(OrderedFloat(10.0)),
+            gte: None,
+            lte: None,
         }),
-        vec![1],
-        "gt: 1.5 must exclude integer 1",
+    ];
+
+    for range in &ranges {
+        let full = index.inner().stream_range(range).unwrap().collect_vec();
+        assert!(full.len() > PAGE_SIZE * 10);
+
+        let mut paged = Vec::new();
+        let mut cursor: Option<StreamCursor> = None;
+        loop {
+            // Cursors travel to clients as bytes
+            let restored =
+                cursor.map(|cursor| StreamCursor::from_bytes(cursor.as_bytes().to_vec()));
+            let (page, next) = index
+                .inner()
+                .stream_range_page(range, restored.as_ref(), PAGE_SIZE)
+                .unwrap();
+            assert!(page.len() <= PAGE_SIZE);
+            paged.extend(page);
+            cursor = next;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(paged, full);
+    }
+
+    let range = &ranges[0];
+    let full = index.inner().stream_range(range).unwrap().collect_vec();
+    let (first_page, cursor) = index
+        .inner()
+        .stream_range_page(range, None, PAGE_SIZE)
+        .unwrap();
+    let cursor = cursor.unwrap();
+    assert_eq!(first_page, full[..PAGE_SIZE]);
+
+    // The order is the same on every backend, so a cursor survives converting the index
+    if matches!(index_type, IndexType::Mmap) {
+        drop(index);
+        let index = open_index_from_disk(temp_dir.path(), IndexType::RamMmap, &empty_deleted());
+        let (page, _) = index
+            .inner()
+            .stream_range_page(range, Some(&cursor), PAGE_SIZE)
+            .unwrap();
+        assert_eq!(page, full[PAGE_SIZE..PAGE_SIZE * 2]);
+        return;
+    }
+
+    let invalid = StreamCursor::from_bytes(vec![0; 3]);
+    assert!(
+        index
+            .inner()
+            .stream_range_page(range, Some(&invalid), PAGE_SIZE)
+            .is_err()
+    );
+    assert!(
+        index
+            .inner()
+            .stream_range_page(range, Some(&cursor), 0)
+            .is_err()
     );
+}
 
-    // `lt: 1.5` ⇒ `< 1.5` ⇒ integer 1 must match, integer 2 must NOT.
+#[test]
+fn test_evict_to_mmap_and_promote_to_ram() {
+    let with_deletions = |index_type| {
+        let (temp_dir, mut index) = random_index(500, 2, index_type);
+        index.remove_points(&[3, 7, 250, 499]).unwrap();
+        (temp_dir, index)
+    };
+    let (_expected_dir, expected) = with_deletions(IndexType::RamMmap);
+    let (_temp_dir, index) = with_deletions(IndexType::RamMmap);
+    assert!(!index.is_on_disk());
+
+    let Ok(index) = index.evict_to_mmap() else {
+        panic!("failed to evict immutable index to mmap");
+    };
+    assert!(matches!(index.inner, NumericIndexInner::Mmap(_)));
+    assert!(index.is_on_disk());
+    assert_same_queries(&expected.inner, &index.inner);
+    // Evicting again keeps serving from disk
+    let Ok(index) = index.evict_to_mmap() else {
+        panic!("failed to evict mmap index");
+    };
+    assert!(index.is_on_disk());
+
+    let Ok(index) = index.promote_to_ram() else {
+        panic!("failed to promote mmap index to RAM");
+    };
+    assert!(matches!(index.inner, NumericIndexInner::Immutable(_)));
+    assert!(!index.is_on_disk());
+    assert_same_queries(&expected.inner, &index.inner);
+
+    // Mutable indexes have no mmap files, they are handed back untouched
+    let (_mutable_dir, mutable) = with_deletions(IndexType::MutableGridstore);
+    let Err((_, mutable)) = mutable.evict_to_mmap() else {
+        panic!("evicted a mutable index to mmap");
+    };
+    let Err((_, mutable)) = mutable.promote_to_ram() else {
+        panic!("promoted a mutable index to RAM");
+    };
+    assert!(matches!(mutable.inner, NumericIndexInner::Mutable(_)));
+    assert_same_queries(&expected.inner, &mutable.inner);
+}
+
+#[cfg(feature = "arrow")]
+fn index_pairs<T>(index: &NumericIndexInner<T>) -> Vec<(PointOffsetType, T)>
+where
+    T: Encodable + Numericable + StoredValue + Send + Sync + Default,
+    Vec<T>: Blob,
+{
+    let mut pairs = Vec::new();
+    index
+        .for_each_point_values(|point_id, values| {
+            pairs.extend(values.iter().map(|&value| (point_id, value)));
+        })
+        .unwrap();
+    pairs
+}
+
+#[cfg(feature = "arrow")]
+#[rstest]
+#[case(IndexType::MutableGridstore)]
+#[case(IndexType::Mmap)]
+#[case(IndexType::RamMmap)]
+fn test_export_arrow_round_trip(#[case] index_type: IndexType) {
+    let (_temp_dir, mut index) = random_index(300, 2, index_type);
+    index.remove_points(&[0, 17, 299]).unwrap();
+
+    let batch = index.inner().export_arrow().unwrap();
+    assert_eq!(batch.num_rows(), 297 * 2);
+    let schema = batch.schema();
+    assert_eq!(schema.field(0).name(), "point_id");
     assert_eq!(
-        run(Range {
-            lt: Some(1.5),
-            ..Default::default()
-        }),
-        vec![0],
-        "lt: 1.5 must include integer 1 and exclude 2",
+        schema.field(0).data_type(),
+        &arrow::datatypes::DataType::UInt32
+    );
+    assert_eq!(schema.field(1).name(), "value");
+    assert_eq!(
+        schema.field(1).data_type(),
+        &arrow::datatypes::DataType::Float64
     );
 
-    // `lte: 1.5` ⇒ `<= 1.5` ⇒ integer 1 must match, integer 2 must NOT.
+    let pairs = pairs_from_record_batch::<FloatPayloadType>(&batch).unwrap();
+    assert_eq!(pairs, index_pairs(index.inner()));
+
+    // Wrong value type
+    assert!(pairs_from_record_batch::<IntPayloadType>(&batch).is_err());
+    // Missing value column
+    let point_ids_only = batch.project(&[0]).unwrap();
+    assert!(pairs_from_record_batch::<FloatPayloadType>(&point_ids_only).is_err());
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_export_arrow_uuid_round_trip() {
+    let temp_dir = Builder::new()
+        .prefix("test_export_arrow_uuid_round_trip")
+        .tempdir()
+        .unwrap();
+    let hw_counter = HardwareCounterCell::new();
+    let mut builder = NumericIndex::<UuidIntType, UuidPayloadType>::builder_mmap(
+        temp_dir.path(),
+        false,
+        &empty_deleted(),
+    );
+    builder.init().unwrap();
+    let uuid = UuidPayloadType::parse_str("f47ac10b-58cc-4372-a567-0e02b2c3d479").unwrap();
+    for i in 0..20 {
+        let value =
+            Value::String(UuidPayloadType::from_u128(uuid.as_u128() + u128::from(i)).to_string());
+        builder.add_point(i, &[&value], &hw_counter).unwrap();
+    }
+    let index = builder.finalize().unwrap();
+
+    let batch = index.inner().export_arrow().unwrap();
     assert_eq!(
-        run(Range {
-            lte: Some(1.5),
-            ..Default::default()
-        }),
-        vec![0],
-        "lte: 1.5 must include integer 1 and exclude 2",
+        batch.schema().field(1).data_type(),
+        &arrow::datatypes::DataType::FixedSizeBinary(16),
     );
+    let pairs = pairs_from_record_batch::<UuidIntType>(&batch).unwrap();
+    assert_eq!(pairs, index_pairs(index.inner()));
+    assert_eq!(pairs[0], (0, uuid.as_u128()));
 }
@@ -0,0 +1,95 @@
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use common::universal_io::{OkNotFound, UniversalReadFs, read_whole_via};
+use fs_err::File;
+
+use super::super::KeyOrder;
+use super::super::integrity::integrity_error;
+use super::BLOCK_INDEX_PATH;
+use crate::common::operation_error::OperationResult;
+use crate::index::field_index::numeric_point::{Numericable, Point};
+
+/// Number of pairs per block of newly built indexes. Existing indexes keep the step recorded in
+/// their config.
+pub(super) const BLOCK_INDEX_STEP: usize = 256;
+
+/// Sparse index over the sorted pairs of `data.bin`, in their stored order: the first pair of
+/// every block of [`Self::step`] pairs.
+///
+/// Kept in RAM, so a seek in `data.bin` only has to binary search a single block.
+pub(super) struct BlockIndex<T: Numericable> {
+    step: usize,
+    /// `keys[i]` is the pair at offset `i * step`
+    keys: Vec<Point<T>>,
+}
+
+impl<T: Numericable + bytemuck::Pod> BlockIndex<T> {
+    /// Write the first pair of every block of `step` sorted `pairs`.
+    ///
+    /// Returns `false` and writes nothing if there are no pairs.
+    pub fn save(
+        path: &Path,
+        pairs: impl IntoIterator<Item = Point<T>>,
+        step: usize,
+    ) -> OperationResult<bool> {
+        let mut keys = pairs.into_iter().step_by(step).peekable();
+        if keys.peek().is_none() {
+            return Ok(false);
+        }
+
+        let mut writer = BufWriter::new(File::create(path.join(BLOCK_INDEX_PATH))?);
+        for key in keys {
+            writer.write_all(bytemuck::bytes_of(&key))?;
+        }
+        writer.flush()?;
+        Ok(true)
+    }
+
+    /// Load the block index at `path`, `None` if the index was built without one.
+    pub fn load<Fs: UniversalReadFs>(
+        fs: &Fs,
+        path: &Path,
+        step: usize,
+    ) -> OperationResult<Option<Self>> {
+        let keys = read_whole_via(fs, path.join(BLOCK_INDEX_PATH), |bytes| {
+            Ok(bytemuck::pod_collect_to_vec::<u8, Point<T>>(&bytes))
+        })
+        .ok_not_found()?;
+        Ok(keys.map(|keys| Self { step, keys }))
+    }
+}
+
+impl<T: Numericable> BlockIndex<T> {
+    /// Offsets within `pairs_len` pairs sorted in `order`, in which `bound` belongs.
+    ///
+    /// The insertion point of `bound` is within the returned range, including its end.
+    pub fn block_of(&self, bound: &Point<T>, pairs_len: usize, order: KeyOrder) -> Range<usize> {
+        let blocks_before = self
+            .keys
+            .partition_point(|key| order.cmp_stored(key, bound).is_le());
+        let start = blocks_before.saturating_sub(1) * self.step;
+        let end = if blocks_before < self.keys.len() {
+            blocks_before * self.step
+        } else {
+            pairs_len
+        };
+        start.min(pairs_len)..end.min(pairs_len)
+    }
+
+    /// Check that the keys are the first pairs of their blocks.
+    pub fn verify(&self, pairs: &[Point<T>]) -> OperationResult<()> {
+        let expected_keys = pairs.iter().step_by(self.step);
+        if self.keys.len() != expected_keys.len() || !self.keys.iter().eq(expected_keys) {
+            return Err(integrity_error(
+                "block index doesn't match the sorted pairs",
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn ram_usage_bytes(&self) -> usize {
+        self.keys.capacity() * size_of::<Point<T>>()
+    }
+}
@@ -0,0 +1,78 @@
+use std::ops::BitOrAssign;
+use std::path::Path;
+
+use common::bitvec::BitVec;
+use common::fs::bulk_sync_dir;
+use common::universal_io::MmapFs;
+
+use super::super::Encodable;
+use super::super::mutable_numeric_index::InMemoryNumericIndex;
+use super::UniversalNumericIndex;
+use super::lifecycle::replace_dir;
+use crate::common::operation_error::{OperationError, OperationResult};
+use crate::index::field_index::numeric_point::Numericable;
+use crate::index::field_index::stored_point_to_values::StoredValue;
+
+impl<T> UniversalNumericIndex<T>
+where
+    T: Encodable + Numericable + Default + StoredValue + bytemuck::Pod,
+{
+    /// Build a copy of the index at `path` that drops the values of deleted points, reclaiming
+    /// their space. Deleted points become points without values, point ids are kept.
+    ///
+    /// Only reads this index, so it keeps serving queries meanwhile. The new files are synced
+    /// to disk before returning. Use [`Self::swap_in`] to replace this index with the result,
+    /// `path` must be on the same filesystem for that.
+    pub fn compact(&self, path: &Path) -> OperationResult<Self> {
+        let mut in_memory_index = InMemoryNumericIndex::default();
+        self.for_each_point_values(|idx, values| {
+            in_memory_index.add_many_to_list(idx, values.to_vec());
+        })?;
+        // Cover the same point ids, even if the last ones have no values
+        in_memory_index
+            .point_to_values
+            .resize_with(self.storage.point_to_values.len(), Vec::new);
+
+        let compacted = Self::build_with_options(
+            &MmapFs,
+            in_memory_index,
+            path,
+            self.is_on_disk,
+            &BitVec::new(),
+            self.build_options(),
+        )?;
+        bulk_sync_dir(path)?;
+        Ok(compacted)
+    }
+
+    /// Move the directory of `new`, usually made by [`Self::compact`], in place of the directory
+    /// of this index and continue serving from it. Points deleted from this index since `new`
+    /// was built stay deleted.
+    ///
+    /// The directories are swapped by renaming them, so the index is never seen with a mix of
+    /// old and new files. If the process crashes during the swap, [`Self::open`] completes it
+    /// or rolls it back. Other handles opened on the old files keep their mappings and finish
+    /// reading safely, handles opened once this returns see the new files. Opening the index
+    /// while the swap is in progress is not supported.
+    pub fn swap_in(&mut self, mut new: Self) -> OperationResult<()> {
+        if new.storage.deleted.len() != self.storage.deleted.len() {
+            return Err(OperationError::service_error(format!(
+                "Can't swap in numeric index of {} points in place of one of {} points",
+                new.storage.deleted.len(),
+                self.storage.deleted.len(),
+            )));
+        }
+
+        replace_dir(&self.path, &new.path)?;
+
+        new.path.clone_from(&self.path);
+        // Points deleted at build time of `new` have no values in it, which `empty_count`
+        // already accounts for. Later deletions still have their values.
+        new.storage
+            .deleted
+            .bitor_assign(self.storage.deleted.as_bitslice());
+        new.deleted_count = new.storage.deleted.count_ones();
+        *self = new;
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use std::path::Path;
+
+use common::bitvec::BitVec;
+use common::counter::conditioned_counter::ConditionedCounter;
+use common::fs::bulk_sync_dir;
+use common::types::PointOffsetType;
+use common::universal_io::{MmapFs, OkNotFound, read_json_via};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+
+use super::super::mutable_numeric_index::InMemoryNumericIndex;
+use super::lifecycle::{UniversalNumericIndexConfig, replace_dir, sibling_path};
+use super::{BuildOptions, CONFIG_PATH, UniversalNumericIndex};
+use crate::common::operation_error::{OperationError, OperationResult};
+use crate::types::IntPayloadType;
+
+/// Unit of the timestamps a datetime index stores as its keys.
+///
+/// Indexes built from payloads always store [`DateTimePrecision::Microseconds`], see
+/// [`DateTimePayloadType::timestamp`].
+///
+/// [`DateTimePayloadType::timestamp`]: crate::types::DateTimePayloadType::timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateTimePrecision {
+    Seconds,
+    Milliseconds,
+    Microseconds,
+}
+
+impl DateTimePrecision {
+    fn units_per_second(self) -> i64 {
+        match self {
+            DateTimePrecision::Seconds => 1,
+            DateTimePrecision::Milliseconds => 1_000,
+            DateTimePrecision::Microseconds => 1_000_000,
+        }
+    }
+
+    /// Convert `timestamp` from this unit to `to`. Conversion to a coarser unit rounds towards
+    /// the past, `None` if the result overflows.
+    pub fn convert(self, timestamp: IntPayloadType, to: Self) -> Option<IntPayloadType> {
+        let (from_units, to_units) = (self.units_per_second(), to.units_per_second());
+        if to_units >= from_units {
+            timestamp.checked_mul(to_units / from_units)
+        } else {
+            Some(timestamp.div_euclid(from_units / to_units))
+        }
+    }
+}
+
+/// Rewrite the keys of the datetime index at `path` from timestamps in `from` units to `to`
+/// units, keeping point ids, and record `to` in the index config.
+///
+/// The converted index is built and synced next to `path` and then swapped in, the original
+/// files are not touched until the conversion succeeded. If the process crashes during the
+/// swap, opening the index completes it or rolls it back. Idempotent: an index that already records `to`
+/// is left as is. Fails if the index records a precision other than `from`.
+///
+/// Must not be called while the index is open.
+pub fn migrate_datetime_encoding(
+    path: &Path,
+    from: DateTimePrecision,
+    to: DateTimePrecision,
+) -> OperationResult<()> {
+    let Some(config) =
+        read_json_via::<_, UniversalNumericIndexConfig>(&MmapFs, &path.join(CONFIG_PATH))
+            .ok_not_found()?
+    else {
+        return Err(OperationError::service_error(format!(
+            "No numeric index to migrate at {}",
+            path.display(),
+        )));
+    };
+    match config.datetime_precision {
+        Some(recorded) if recorded == to => return Ok(()),
+        Some(recorded) if recorded != from => {
+            return Err(OperationError::validation_error(format!(
+                "Datetime index at {} is encoded in {recorded:?}, not {from:?}",
+                path.display(),
+            )));
+        }
+        Some(_) | None => {}
+    }
+
+    let index = UniversalNumericIndex::<IntPayloadType>::open(&MmapFs, path, true, &BitVec::new())?
+        .ok_or_else(|| {
+            OperationError::service_error(format!(
+                "Failed to open numeric index at {}",
+                path.display(),
+            ))
+        })?;
+    let mut in_memory_index = InMemoryNumericIndex::default();
+    let point_to_values = &index.storage.point_to_values;
+    for idx in 0..point_to_values.len() as PointOffsetType {
+        let values = match point_to_values.values_iter(idx, ConditionedCounter::never())? {
+            Some(values) => values
+                .map(|timestamp| {
+                    from.convert(*timestamp, to).ok_or_else(|| {
+                        OperationError::validation_error(format!(
+                            "Timestamp {timestamp} of point {idx} overflows in {to:?}",
+                        ))
+                    })
+                })
+                .collect::<OperationResult<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+        in_memory_index.add_many_to_list(idx, values);
+    }
+    drop(index);
+
+    let migrating_path = sibling_path(path, "migrating");
+    if migrating_path.exists() {
+        fs::remove_dir_all(&migrating_path)?;
+    }
+    drop(UniversalNumericIndex::<IntPayloadType>::build_with_options(
+        &MmapFs,
+        in_memory_index,
+        &migrating_path,
+        true,
+        &BitVec::new(),
+        BuildOptions {
+            order: config.order,
+            datetime_precision: Some(to),
+        },
+    )?);
+    bulk_sync_dir(&migrating_path)?;
+
+    replace_dir(path, &migrating_path)
+}
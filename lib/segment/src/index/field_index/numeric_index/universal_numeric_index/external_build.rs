@@ -0,0 +1,279 @@
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use common::bitvec::BitSlice;
+use common::mmap::{AdviceSetting, open_read_mmap};
+use common::types::PointOffsetType;
+use common::universal_io::{MmapFile, MmapFs};
+use fs_err as fs;
+use fs_err::File;
+use itertools::Itertools;
+use memmap2::Mmap;
+use tempfile::TempDir;
+
+use super::super::lifecycle::{HISTOGRAM_MAX_BUCKET_SIZE, HISTOGRAM_PRECISION};
+use super::super::{Encodable, KeyOrder};
+use super::lifecycle::{save_block_index, save_config, save_empty_points};
+use super::{PAIRS_PATH, UniversalNumericIndex};
+use crate::common::operation_error::{OperationError, OperationResult};
+use crate::index::field_index::histogram::Histogram;
+use crate::index::field_index::numeric_point::{Numericable, Point};
+use crate::index::field_index::stored_point_to_values::{StoredPointToValues, StoredValue};
+
+const VALUES_TMP_PATH: &str = "values.tmp";
+const COUNTS_TMP_PATH: &str = "counts.tmp";
+
+/// Bounded-memory construction of a [`UniversalNumericIndex`].
+///
+/// Pairs are buffered up to the memory budget, then sorted and spilled to a run file.
+/// Point values are streamed to a temporary file as they arrive. On [`Self::finish`] the runs
+/// are merged straight into `data.bin` and the histogram is built from the merged pairs.
+///
+/// Points must be added in ascending id order, each at most once.
+///
+/// The produced files are the same as with [`UniversalNumericIndex::build`], except for the
+/// histogram: its bucket borders depend on insertion order, which is sorted order here.
+pub struct ExternalNumericIndexBuild<T: Encodable + Numericable> {
+    tmp_dir: TempDir,
+    run_capacity: usize,
+    pairs_buffer: Vec<Point<T>>,
+    runs: Vec<(PathBuf, usize)>,
+    /// Values of all points, in point id order
+    values_writer: BufWriter<File>,
+    /// Number of values of each point id
+    counts_writer: BufWriter<File>,
+    points_count: usize,
+    max_values_per_point: usize,
+}
+
+impl<T> ExternalNumericIndexBuild<T>
+where
+    T: Encodable + Numericable + Default + StoredValue + bytemuck::Pod,
+{
+    /// Start a build of the index at `path`, keeping at most about `memory_budget_bytes` of pairs
+    /// in memory. Temporary files are placed inside `path`.
+    pub fn new(path: &Path, memory_budget_bytes: usize) -> OperationResult<Self> {
+        fs::create_dir_all(path)?;
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("external_build")
+            .tempdir_in(path)?;
+
+        let values_writer = BufWriter::new(File::create(tmp_dir.path().join(VALUES_TMP_PATH))?);
+        let counts_writer = BufWriter::new(File::create(tmp_dir.path().join(COUNTS_TMP_PATH))?);
+
+        Ok(Self {
+            tmp_dir,
+            run_capacity: (memory_budget_bytes / size_of::<Point<T>>()).max(1),
+            pairs_buffer: Vec::new(),
+            runs: Vec::new(),
+            values_writer,
+            counts_writer,
+            points_count: 0,
+            max_values_per_point: 0,
+        })
+    }
+
+    pub fn add_point(&mut self, idx: PointOffsetType, values: &[T]) -> OperationResult<()> {
+        if (idx as usize) < self.points_count {
+            return Err(OperationError::service_error(format!(
+                "External numeric index build requires ascending point ids, got {idx} after {}",
+                self.points_count - 1,
+            )));
+        }
+
+        // Points skipped in between have no values
+        for _ in self.points_count..idx as usize {
+            self.counts_writer.write_all(&0u32.to_ne_bytes())?;
+        }
+        self.counts_writer
+            .write_all(&(values.len() as u32).to_ne_bytes())?;
+        self.values_writer.write_all(bytemuck::cast_slice(values))?;
+        self.points_count = idx as usize + 1;
+        self.max_values_per_point = self.max_values_per_point.max(values.len());
+
+        for value in values {
+            self.pairs_buffer.push(Point::new(*value, idx));
+            if self.pairs_buffer.len() >= self.run_capacity {
+                self.spill_run()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sort the buffered pairs and write them out as a new run.
+    fn spill_run(&mut self) -> OperationResult<()> {
+        self.pairs_buffer.sort_unstable();
+        self.pairs_buffer.dedup();
+
+        let run_path = self
+            .tmp_dir
+            .path()
+            .join(format!("run_{}.tmp", self.runs.len()));
+        let mut writer = BufWriter::new(File::create(&run_path)?);
+        writer.write_all(bytemuck::cast_slice(&self.pairs_buffer))?;
+        writer.flush()?;
+
+        self.runs.push((run_path, self.pairs_buffer.len()));
+        self.pairs_buffer.clear();
+        Ok(())
+    }
+
+    /// Merge the runs and write all index files into `path`, then open the index.
+    pub fn finish(
+        mut self,
+        path: &Path,
+        is_on_disk: bool,
+        deleted_points: &BitSlice,
+    ) -> OperationResult<UniversalNumericIndex<T>> {
+        if !self.pairs_buffer.is_empty() {
+            self.spill_run()?;
+        }
+        self.values_writer.flush()?;
+        self.counts_writer.flush()?;
+
+        let Self {
+            tmp_dir,
+            run_capacity: _,
+            pairs_buffer,
+            runs,
+            values_writer,
+            counts_writer,
+            points_count,
+            max_values_per_point,
+        } = self;
+        drop((pairs_buffer, values_writer, counts_writer));
+
+        let pairs_count = merge_runs::<T>(&runs, &path.join(PAIRS_PATH))?;
+
+        let pairs_mmap = map_file(&path.join(PAIRS_PATH))?;
+        let pairs: &[Point<T>] = as_slice(pairs_mmap.as_ref());
+        debug_assert_eq!(pairs.len(), pairs_count);
+        let histogram = Histogram::from_sorted(
+            HISTOGRAM_MAX_BUCKET_SIZE,
+            HISTOGRAM_PRECISION,
+            pairs.iter().copied(),
+        );
+        let block_index_step = save_block_index(path, pairs.iter().copied())?;
+        drop(pairs_mmap);
+        histogram.save(path)?;
+
+        save_config(
+            path,
+            max_values_per_point,
+            block_index_step,
+            None,
+            KeyOrder::Ascending,
+        )?;
+
+        let counts_mmap = map_file(&tmp_dir.path().join(COUNTS_TMP_PATH))?;
+        let values_mmap = map_file(&tmp_dir.path().join(VALUES_TMP_PATH))?;
+        let counts: &[u32] = as_slice(counts_mmap.as_ref());
+        let values: &[T] = as_slice(values_mmap.as_ref());
+        debug_assert_eq!(counts.len(), points_count);
+
+        let point_values = counts
+            .iter()
+            .scan(0usize, |start, &count| {
+                let point_values = &values[*start..*start + count as usize];
+                *start += count as usize;
+                Some(point_values)
+            })
+            .enumerate()
+            .map(|(idx, point_values)| (idx as PointOffsetType, point_values.iter()));
+        StoredPointToValues::<T, MmapFile>::from_iter(&MmapFs, path, point_values)?;
+
+        save_empty_points(
+            path,
+            points_count,
+            counts.iter().positions(|&count| count == 0),
+        )?;
+
+        drop((counts_mmap, values_mmap));
+        tmp_dir.close()?;
+
+        UniversalNumericIndex::open(&MmapFs, path, is_on_disk, deleted_points)?.ok_or_else(|| {
+            OperationError::service_error("Failed to open UniversalNumericIndex after building it")
+        })
+    }
+}
+
+/// K-way merge of sorted runs into `pairs_path`, dropping duplicate pairs. Returns the number of
+/// pairs written.
+fn merge_runs<T: Encodable + Numericable>(
+    runs: &[(PathBuf, usize)],
+    pairs_path: &Path,
+) -> OperationResult<usize> {
+    let readers = runs
+        .iter()
+        .map(|(run_path, len)| Ok(RunReader::<T>::new(File::open(run_path)?, *len)))
+        .collect::<OperationResult<Vec<_>>>()?;
+
+    let mut writer = BufWriter::new(File::create(pairs_path)?);
+    let mut pairs_count = 0;
+    let mut last = None;
+    // Errors are ordered first, to surface them as early as possible
+    let merged = readers.into_iter().kmerge_by(|a, b| match (a, b) {
+        (Ok(a), Ok(b)) => a < b,
+        (Err(_), _) => true,
+        (Ok(_), Err(_)) => false,
+    });
+    for pair in merged {
+        let pair = pair?;
+        if last == Some(pair) {
+            continue;
+        }
+        writer.write_all(bytemuck::bytes_of(&pair))?;
+        last = Some(pair);
+        pairs_count += 1;
+    }
+    writer.flush()?;
+
+    Ok(pairs_count)
+}
+
+struct RunReader<T: Encodable + Numericable> {
+    reader: BufReader<File>,
+    remaining: usize,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Encodable + Numericable> RunReader<T> {
+    fn new(file: File, len: usize) -> Self {
+        Self {
+            reader: BufReader::new(file),
+            remaining: len,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: Encodable + Numericable> Iterator for RunReader<T> {
+    type Item = std::io::Result<Point<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let mut pair = <Point<T> as bytemuck::Zeroable>::zeroed();
+        Some(
+            self.reader
+                .read_exact(bytemuck::bytes_of_mut(&mut pair))
+                .map(|()| pair),
+        )
+    }
+}
+
+/// Map a file for reading, `None` if it's empty.
+fn map_file(path: &Path) -> OperationResult<Option<Mmap>> {
+    if fs::metadata(path)?.len() == 0 {
+        return Ok(None);
+    }
+    Ok(Some(open_read_mmap(path, AdviceSetting::Global, false)?))
+}
+
+fn as_slice<V: bytemuck::Pod>(mmap: Option<&Mmap>) -> &[V] {
+    mmap.map_or(&[], |mmap| bytemuck::cast_slice(&mmap[..]))
+}
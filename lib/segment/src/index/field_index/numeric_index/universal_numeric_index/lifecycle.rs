@@ -3,29 +3,152 @@ use std::ops::BitOrAssign;
 use std::path::{Path, PathBuf};
 
 use common::bitvec::{BitSlice, BitSliceExt};
-use common::fs::{atomic_save_json, clear_disk_cache};
+use common::fs::{atomic_save_json, clear_disk_cache, sync_parent_dir};
 use common::mmap::{AdviceSetting, MmapSlice, create_and_ensure_length};
 use common::stored_bitslice::{MmapBitSlice, StoredBitSlice};
 use common::types::PointOffsetType;
 use common::universal_io::{
-    MmapFs, OkNotFound, OpenOptions, Populate, TypedStorage, UniversalRead, read_json_via,
+    MmapFile, MmapFs, OkNotFound, OpenOptions, Populate, TypedStorage, UniversalRead, read_json_via,
 };
 use fs_err as fs;
+use itertools::Either;
 use memmap2::MmapMut;
 use serde::{Deserialize, Serialize};
 
-use super::super::Encodable;
 use super::super::mutable_numeric_index::InMemoryNumericIndex;
-use super::{CONFIG_PATH, DELETED_PATH, PAIRS_PATH, Storage, UniversalNumericIndex};
+use super::super::{Encodable, KeyOrder};
+use super::block_index::{BLOCK_INDEX_STEP, BlockIndex};
+use super::datetime_encoding::DateTimePrecision;
+use super::{
+    BLOCK_INDEX_PATH, BuildOptions, CONFIG_PATH, DELETED_PATH, PAIRS_PATH, Storage,
+    UniversalNumericIndex,
+};
 use crate::common::Flusher;
 use crate::common::operation_error::{OperationError, OperationResult};
 use crate::index::field_index::histogram::Histogram;
+use crate::index::field_index::immutable_point_to_values::ImmutablePointToValues;
 use crate::index::field_index::numeric_point::{Numericable, Point};
 use crate::index::field_index::stored_point_to_values::{StoredPointToValues, StoredValue};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct UniversalNumericIndexConfig {
+pub(super) struct UniversalNumericIndexConfig {
+    pub(super) max_values_per_point: usize,
+    /// Pairs per block of the block index, `None` if there is none
+    #[serde(default)]
+    pub(super) block_index_step: Option<usize>,
+    /// Unit of stored datetime timestamps, recorded by [`migrate_datetime_encoding`].
+    /// `None` if the index was never migrated.
+    ///
+    /// [`migrate_datetime_encoding`]: super::migrate_datetime_encoding
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) datetime_precision: Option<DateTimePrecision>,
+    /// Order of the pairs in `data.bin`, ascending for indexes built before it was introduced
+    #[serde(default)]
+    pub(super) order: KeyOrder,
+}
+
+pub(super) fn save_config(
+    path: &Path,
     max_values_per_point: usize,
+    block_index_step: Option<usize>,
+    datetime_precision: Option<DateTimePrecision>,
+    order: KeyOrder,
+) -> OperationResult<()> {
+    atomic_save_json(
+        &path.join(CONFIG_PATH),
+        &UniversalNumericIndexConfig {
+            max_values_per_point,
+            block_index_step,
+            datetime_precision,
+            order,
+        },
+    )?;
+    Ok(())
+}
+
+/// `path` with `suffix` appended to its last component.
+pub(super) fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Replace the index directory at `path` with the directory at `new_path`, on the same
+/// filesystem. The files of `new_path` must be synced to disk already.
+///
+/// `path` is moved aside to its `.old` sibling before `new_path` is moved in, and removed
+/// after. A crash in between leaves the `.old` directory behind, see [`recover_replaced_dir`].
+pub(super) fn replace_dir(path: &Path, new_path: &Path) -> OperationResult<()> {
+    let old_path = sibling_path(path, "old");
+    if old_path.exists() {
+        fs::remove_dir_all(&old_path)?;
+    }
+    fs::rename(path, &old_path)?;
+    sync_parent_dir(path)?;
+    fs::rename(new_path, path)?;
+    sync_parent_dir(path)?;
+    fs::remove_dir_all(&old_path)?;
+    Ok(())
+}
+
+/// Complete a [`replace_dir`] of `path` interrupted by a crash.
+///
+/// If `path` is missing, the crash happened before the new directory was moved in, and the old
+/// one is moved back. Otherwise the new directory is in place, and the old one is removed.
+pub(super) fn recover_replaced_dir(path: &Path) -> OperationResult<()> {
+    let old_path = sibling_path(path, "old");
+    if !old_path.exists() {
+        return Ok(());
+    }
+    if path.exists() {
+        fs::remove_dir_all(&old_path)?;
+    } else {
+        fs::rename(&old_path, path)?;
+        sync_parent_dir(path)?;
+    }
+    Ok(())
+}
+
+/// Save the block index over `pairs` in stored order, returns the step to store in the config.
+pub(super) fn save_block_index<T: Numericable + bytemuck::Pod>(
+    path: &Path,
+    pairs: impl IntoIterator<Item = Point<T>>,
+) -> OperationResult<Option<usize>> {
+    let saved = BlockIndex::save(path, pairs, BLOCK_INDEX_STEP)?;
+    Ok(saved.then_some(BLOCK_INDEX_STEP))
+}
+
+/// Persist build-time deletion flags of `points_count` points, marking the ones without values.
+///
+/// `empty_points` must be ascending.
+pub(super) fn save_empty_points(
+    path: &Path,
+    points_count: usize,
+    empty_points: impl Iterator<Item = usize>,
+) -> OperationResult<()> {
+    let deleted_path = path.join(DELETED_PATH);
+    let _ = create_and_ensure_length(
+        &deleted_path,
+        points_count
+            .div_ceil(u8::BITS as usize)
+            .next_multiple_of(size_of::<u64>()),
+    )?;
+
+    let mut deleted = MmapBitSlice::open(
+        &MmapFs,
+        &deleted_path,
+        OpenOptions {
+            writeable: true,
+            need_sequential: false,
+            populate: Populate::Auto,
+            advice: AdviceSetting::Global,
+        },
+        (),
+    )?;
+    deleted.set_ascending_bits_batch(empty_points.map(|idx| (idx as u64, true)))?;
+    deleted.flusher()()?;
+    Ok(())
 }
 
 impl<T, S> UniversalNumericIndex<T, S>
@@ -41,20 +164,60 @@ where
         is_on_disk: bool,
         deleted_points: &BitSlice,
     ) -> OperationResult<Self> {
+        Self::build_with_options(
+            fs,
+            in_memory_index,
+            path,
+            is_on_disk,
+            deleted_points,
+            BuildOptions::default(),
+        )
+    }
+
+    /// Same as [`Self::build`], with the layout given by `options`.
+    pub fn build_with_options(
+        fs: &S::Fs,
+        in_memory_index: InMemoryNumericIndex<T>,
+        path: &Path,
+        is_on_disk: bool,
+        deleted_points: &BitSlice,
+        options: BuildOptions,
+    ) -> OperationResult<Self> {
+        Self::build_with_progress(
+            fs,
+            in_memory_index,
+            path,
+            is_on_disk,
+            deleted_points,
+            options,
+            &mut |_| {},
+        )
+    }
+
+    /// Same as [`Self::build_with_options`], calling `progress` with the fraction complete after
+    /// writing each structure. The last call reports `1.0`, once the built index is opened.
+    pub fn build_with_progress(
+        fs: &S::Fs,
+        in_memory_index: InMemoryNumericIndex<T>,
+        path: &Path,
+        is_on_disk: bool,
+        deleted_points: &BitSlice,
+        options: BuildOptions,
+        progress: &mut dyn FnMut(f32),
+    ) -> OperationResult<Self> {
+        const STEPS: u8 = 6;
+        let BuildOptions {
+            order,
+            datetime_precision,
+        } = options;
+        let mut report = |step: u8| progress(f32::from(step) / f32::from(STEPS));
+
         fs::create_dir_all(path)?;
 
         let pairs_path = path.join(PAIRS_PATH);
-        let deleted_path = path.join(DELETED_PATH);
-        let config_path = path.join(CONFIG_PATH);
-
-        atomic_save_json(
-            &config_path,
-            &UniversalNumericIndexConfig {
-                max_values_per_point: in_memory_index.max_values_per_point,
-            },
-        )?;
 
         in_memory_index.histogram.save(path)?;
+        report(1);
 
         StoredPointToValues::<T, S>::from_iter(
             fs,
@@ -65,6 +228,7 @@ where
                 .enumerate()
                 .map(|(idx, values)| (idx as PointOffsetType, values.iter().map(|v| v.borrow()))),
         )?;
+        report(2);
 
         {
             let pairs_file = create_and_ensure_length(
@@ -73,54 +237,51 @@ where
             )?;
             let pairs_mmap = unsafe { MmapMut::map_mut(&pairs_file)? };
             let mut pairs = unsafe { MmapSlice::<Point<T>>::try_from(pairs_mmap)? };
-            for (src, dst) in in_memory_index.map.iter().zip(pairs.iter_mut()) {
-                *dst = *src;
+            for (src, dst) in stored_pairs(&in_memory_index, order).zip(pairs.iter_mut()) {
+                *dst = src;
             }
         }
+        report(3);
 
-        {
-            let deleted_flags_count = in_memory_index.point_to_values.len();
-            let _ = create_and_ensure_length(
-                &deleted_path,
-                deleted_flags_count
-                    .div_ceil(u8::BITS as usize)
-                    .next_multiple_of(size_of::<u64>()),
-            )?;
+        let block_index_step = save_block_index(path, stored_pairs(&in_memory_index, order))?;
+        save_config(
+            path,
+            in_memory_index.max_values_per_point,
+            block_index_step,
+            datetime_precision,
+            order,
+        )?;
+        report(4);
 
-            let mut deleted = MmapBitSlice::open(
-                &MmapFs,
-                &deleted_path,
-                OpenOptions {
-                    writeable: true,
-                    need_sequential: false,
-                    populate: Populate::Auto,
-                    advice: AdviceSetting::Global,
-                },
-                (),
-            )?;
-            deleted.set_ascending_bits_batch(
-                in_memory_index
-                    .point_to_values
-                    .iter()
-                    .enumerate()
-                    .filter(|(_, values)| values.is_empty())
-                    .map(|(idx, _)| (idx as u64, true)),
-            )?;
-            deleted.flusher()()?;
-        }
+        save_empty_points(
+            path,
+            in_memory_index.point_to_values.len(),
+            in_memory_index
+                .point_to_values
+                .iter()
+                .enumerate()
+                .filter(|(_, values)| values.is_empty())
+                .map(|(idx, _)| idx),
+        )?;
+        report(5);
 
-        Self::open(fs, path, is_on_disk, deleted_points)?.ok_or_else(|| {
+        let index = Self::open(fs, path, is_on_disk, deleted_points)?.ok_or_else(|| {
             OperationError::service_error("Failed to open UniversalNumericIndex after building it")
-        })
+        })?;
+        report(STEPS);
+        Ok(index)
     }
 
-    /// Open and load mmap numeric index from the given path
+    /// Open and load mmap numeric index from the given path. A swap of its directory
+    /// interrupted by a crash is completed or rolled back first.
     pub fn open(
         fs: &S::Fs,
         path: &Path,
         is_on_disk: bool,
         deleted_points: &BitSlice,
     ) -> OperationResult<Option<Self>> {
+        recover_replaced_dir(path)?;
+
         let pairs_path = path.join(PAIRS_PATH);
         let deleted_path = path.join(DELETED_PATH);
         let config_path = path.join(CONFIG_PATH);
@@ -142,6 +303,10 @@ where
             advice: AdviceSetting::Global,
         };
         let pairs = TypedStorage::open(fs, pairs_path, pairs_options, Default::default())?;
+        let block_index = match config.block_index_step {
+            Some(step) => BlockIndex::load(fs, path, step)?,
+            None => None,
+        };
 
         let point_to_values = StoredPointToValues::open(fs, path, do_populate)?;
         let mut deleted = deleted_points.to_owned();
@@ -168,22 +333,40 @@ where
         deleted.bitor_assign(deleted_payloads_bitslice.as_ref());
 
         let deleted_count = deleted.count_ones();
+        let empty_payloads = deleted_payloads_bitslice.as_ref();
+        let empty_count = empty_payloads[..empty_payloads.len().min(deleted.len())].count_ones();
 
         Ok(Some(Self {
             path: path.to_path_buf(),
             storage: Storage {
                 deleted,
                 pairs,
+                block_index,
                 point_to_values,
             },
             histogram,
             deleted_count,
+            empty_count,
             max_values_per_point: config.max_values_per_point,
             is_on_disk,
+            order: config.order,
+            datetime_precision: config.datetime_precision,
         }))
     }
 }
 
+/// Pairs of `in_memory_index` in the order they are stored for `order`.
+fn stored_pairs<T: Encodable + Numericable>(
+    in_memory_index: &InMemoryNumericIndex<T>,
+    order: KeyOrder,
+) -> impl Iterator<Item = Point<T>> + '_ {
+    let pairs = in_memory_index.map.iter().copied();
+    match order {
+        KeyOrder::Ascending => Either::Left(pairs),
+        KeyOrder::Descending => Either::Right(pairs.rev()),
+    }
+}
+
 impl<T: Encodable + Numericable + Default + StoredValue + 'static> UniversalNumericIndex<T> {
     pub fn wipe(self) -> OperationResult<()> {
         let files = self.files();
@@ -203,6 +386,9 @@ impl<T: Encodable + Numericable + Default + StoredValue + 'static> UniversalNume
             self.path.join(DELETED_PATH),
             self.path.join(CONFIG_PATH),
         ];
+        if self.storage.block_index.is_some() {
+            files.push(self.path.join(BLOCK_INDEX_PATH));
+        }
         files.extend(self.storage.point_to_values.files());
         files.extend(Histogram::<T>::files(&self.path));
         files
@@ -214,6 +400,9 @@ impl<T: Encodable + Numericable + Default + StoredValue + 'static> UniversalNume
             self.path.join(DELETED_PATH),
             self.path.join(CONFIG_PATH),
         ];
+        if self.storage.block_index.is_some() {
+            files.push(self.path.join(BLOCK_INDEX_PATH));
+        }
         files.extend(self.storage.point_to_values.immutable_files());
         files.extend(Histogram::<T>::immutable_files(&self.path));
         files
@@ -237,6 +426,20 @@ impl<T: Encodable + Numericable + Default + StoredValue + 'static> UniversalNume
         }
     }
 
+    /// Batch version of [`Self::remove_point`]. `ids` must be ascending.
+    pub fn remove_points(&mut self, ids: &[PointOffsetType]) {
+        debug_assert!(ids.is_sorted(), "ids must be ascending");
+        let deleted = &mut self.storage.deleted;
+        let mut newly_deleted = 0;
+        for &idx in ids {
+            let idx = idx as usize;
+            if idx < deleted.len() && !deleted.replace(idx, true) {
+                newly_deleted += 1;
+            }
+        }
+        self.deleted_count += newly_deleted;
+    }
+
     /// Populate all pages in the mmap.
     /// Block until all pages are populated.
     pub fn populate(&self) -> OperationResult<()> {
@@ -245,19 +448,43 @@ impl<T: Encodable + Numericable + Default + StoredValue + 'static> UniversalNume
         Ok(())
     }
 
+    /// Switch between serving reads from disk and from RAM, without reopening.
+    ///
+    /// Moving to RAM populates the backing files, moving to disk drops them from
+    /// the cache. Hardware counters only measure reads while on disk.
+    pub fn set_on_disk(&mut self, on_disk: bool) -> OperationResult<()> {
+        if on_disk == self.is_on_disk {
+            return Ok(());
+        }
+        if on_disk {
+            self.clear_cache()?;
+        } else {
+            self.populate()?;
+        }
+        self.is_on_disk = on_disk;
+        Ok(())
+    }
+
     /// Drop disk cache.
+    ///
+    /// Covers every backing file: the sorted pairs, the deleted flags and the point to values
+    /// storage used by `get_values` and `check_values_any`.
     pub fn clear_cache(&self) -> OperationResult<()> {
         let Self {
             path,
             storage,
             histogram: _,
             deleted_count: _,
+            empty_count: _,
             max_values_per_point: _,
             is_on_disk: _,
+            order: _,
+            datetime_precision: _,
         } = self;
         let Storage {
             deleted: _,
             pairs,
+            block_index: _,
             point_to_values,
         } = storage;
         pairs.clear_ram_cache()?;
@@ -272,10 +499,52 @@ impl<T: Encodable + Numericable + Default + StoredValue + 'static> UniversalNume
             storage,
             histogram,
             deleted_count: _,
+            empty_count: _,
             max_values_per_point: _,
             is_on_disk: _,
+            order: _,
+            datetime_precision: _,
         } = self;
 
         histogram.ram_usage_bytes() + storage.ram_usage_bytes()
     }
+
+    /// Estimate RAM needed to load the index at `path` as an in-memory immutable index,
+    /// without loading it. Only the config files, the point-to-values header and the length of
+    /// the pairs file are read.
+    ///
+    /// For indexes mixing single- and multi-value points the estimate errs on the high side.
+    pub fn estimate_ram_bytes(path: &Path) -> OperationResult<usize> {
+        let config: UniversalNumericIndexConfig = read_json_via(&MmapFs, &path.join(CONFIG_PATH))
+            .ok_not_found()?
+            .ok_or_else(|| {
+                OperationError::service_error(format!(
+                    "Numeric index not found at {}",
+                    path.display(),
+                ))
+            })?;
+        let histogram = Histogram::<T>::load_via(&MmapFs, path)?;
+
+        let pairs_count =
+            fs::metadata(path.join(PAIRS_PATH))?.len() as usize / size_of::<Point<T>>();
+        // Not populated, so only the header is read
+        let points_count = StoredPointToValues::<T, MmapFile>::open(&MmapFs, path, false)?.len();
+        // Single-value points are stored inline, only multi-value points use the shared container
+        let multi_values_count = if config.max_values_per_point > 1 {
+            pairs_count
+        } else {
+            0
+        };
+
+        // Sorted pairs and their deleted flags, see `NumericKeySortedVec`
+        let map_bytes = pairs_count * size_of::<Point<T>>()
+            + pairs_count.next_multiple_of(u64::BITS as usize) / u8::BITS as usize;
+
+        Ok(map_bytes
+            + histogram.ram_usage_bytes()
+            + ImmutablePointToValues::<T>::estimate_ram_usage_bytes(
+                points_count,
+                multi_values_count,
+            ))
+    }
 }
@@ -3,17 +3,25 @@ use std::path::PathBuf;
 use common::bitvec::BitVec;
 use common::universal_io::{MmapFile, TypedStorage, UniversalRead};
 
-use super::Encodable;
+use self::block_index::BlockIndex;
+pub use self::datetime_encoding::{DateTimePrecision, migrate_datetime_encoding};
+pub use self::external_build::ExternalNumericIndexBuild;
+use super::{Encodable, KeyOrder};
 use crate::index::field_index::histogram::Histogram;
 use crate::index::field_index::numeric_point::{Numericable, Point};
 use crate::index::field_index::stored_point_to_values::{StoredPointToValues, StoredValue};
 
+mod block_index;
+mod compaction;
+mod datetime_encoding;
+mod external_build;
 mod lifecycle;
 mod read_ops;
 
 pub(super) const PAIRS_PATH: &str = "data.bin";
 pub(super) const DELETED_PATH: &str = "deleted.bin";
 pub(super) const CONFIG_PATH: &str = "mmap_field_index_config.json";
+pub(super) const BLOCK_INDEX_PATH: &str = "block_index.bin";
 
 /// Immutable numeric index served directly from a [`UniversalRead`] storage
 /// backend.
@@ -38,8 +46,24 @@ pub struct UniversalNumericIndex<
     pub(super) storage: Storage<T, S>,
     pub(super) histogram: Histogram<T>,
     pub(super) deleted_count: usize,
+    /// Points stored without values, marked deleted at build time. Included in
+    /// `deleted_count`, but they take up no space in the sorted storage.
+    pub(super) empty_count: usize,
     pub(super) max_values_per_point: usize,
     pub(super) is_on_disk: bool,
+    /// Order of the pairs in `data.bin`
+    pub(super) order: KeyOrder,
+    /// Unit of stored datetime timestamps, see [`migrate_datetime_encoding`]
+    pub(super) datetime_precision: Option<DateTimePrecision>,
+}
+
+/// Layout of an index, chosen at build time and recorded in its config.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BuildOptions {
+    /// Order to store the pairs in
+    pub order: KeyOrder,
+    /// Unit of stored datetime timestamps, see [`migrate_datetime_encoding`]
+    pub datetime_precision: Option<DateTimePrecision>,
 }
 
 pub(in super::super) struct Storage<
@@ -47,8 +71,11 @@ pub(in super::super) struct Storage<
     S: UniversalRead = MmapFile,
 > {
     pub(super) deleted: BitVec,
-    // sorted pairs (id + value), sorted by value (by id if values are equal)
+    // sorted pairs (id + value), sorted by value (by id if values are equal), in the key order
+    // of the index
     pub(super) pairs: TypedStorage<S, Point<T>>,
+    /// Absent for indexes built before it was introduced, and for empty ones
+    pub(super) block_index: Option<BlockIndex<T>>,
     pub(in super::super) point_to_values: StoredPointToValues<T, S>,
 }
 
@@ -57,11 +84,13 @@ impl<T: Encodable + Numericable + Default + StoredValue + 'static, S: UniversalR
         let Self {
             deleted,
             pairs,
+            block_index,
             point_to_values,
         } = self;
 
         deleted.capacity().div_ceil(u8::BITS as usize)
             + pairs.ram_usage_bytes()
+            + block_index.as_ref().map_or(0, BlockIndex::ram_usage_bytes)
             + point_to_values.ram_usage_bytes()
     }
 }
@@ -1,7 +1,7 @@
 use std::borrow::Cow;
 use std::ops::Bound;
 
-use common::bitvec::BitSliceExt as _;
+use common::bitvec::{BitSliceExt as _, BitVec};
 use common::counter::conditioned_counter::ConditionedCounter;
 use common::counter::hardware_counter::HardwareCounterCell;
 use common::counter::iterator_hw_measurement::HwMeasurementIteratorExt;
@@ -10,9 +10,13 @@ use common::types::PointOffsetType;
 use common::universal_io::{ReadRange, UniversalRead};
 use itertools::Either;
 
-use super::super::Encodable;
+use super::super::integrity::{
+    check_deleted_count, check_pairs_count, check_sorted_pairs, distinct_values_count,
+    integrity_error,
+};
 use super::super::numeric_index_read::NumericIndexRead;
-use super::UniversalNumericIndex;
+use super::super::{Encodable, KeyOrder};
+use super::{BuildOptions, DateTimePrecision, UniversalNumericIndex};
 use crate::common::operation_error::OperationResult;
 use crate::index::field_index::histogram::Histogram;
 use crate::index::field_index::numeric_point::{Numericable, Point};
@@ -80,7 +84,7 @@ impl<T: Encodable + Numericable + Default + StoredValue + 'static, S: UniversalR
         let hw_counter = self.make_conditioned_counter(hw_counter);
 
         Ok(self
-            .values_range_iterator(start_bound, end_bound)?
+            .values_range_iterator(start_bound, end_bound, hw_counter)?
             .map(|point| point.idx)
             .measure_hw_with_condition_cell(hw_counter, size_of::<Point<T>>(), |i| {
                 i.payload_index_io_read_counter()
@@ -92,9 +96,15 @@ impl<T: Encodable + Numericable + Default + StoredValue + 'static, S: UniversalR
         start_bound: Bound<Point<T>>,
         end_bound: Bound<Point<T>>,
     ) -> OperationResult<impl DoubleEndedIterator<Item = (T, PointOffsetType)> + '_> {
-        Ok(self
-            .values_range_iterator(start_bound, end_bound)?
-            .map(|Point { val, idx, .. }| (val, idx)))
+        let iter = self
+            .values_range_iterator(start_bound, end_bound, ConditionedCounter::never())?
+            .map(|Point { val, idx, .. }| (val, idx));
+        // Ascending regardless of the stored order, reversing a descending index walks its
+        // storage forward
+        Ok(match self.order {
+            KeyOrder::Ascending => Either::Left(iter),
+            KeyOrder::Descending => Either::Right(iter.rev()),
+        })
     }
 
     /// Cheap `O(log n)` boundary search over the on-disk sorted pairs.
@@ -109,10 +119,35 @@ impl<T: Encodable + Numericable + Default + StoredValue + 'static, S: UniversalR
         end_bound: Bound<Point<T>>,
         _hw_counter: &HardwareCounterCell,
     ) -> OperationResult<usize> {
-        let (start, end) = self.values_range_bounds(start_bound, end_bound)?;
+        let (start, end) =
+            self.values_range_bounds(start_bound, end_bound, ConditionedCounter::never())?;
         Ok(end - start)
     }
 
+    /// Boundary search over the on-disk sorted pairs. Deletions are tracked per
+    /// point, so if there are any, the pairs within the boundaries are scanned
+    /// for deleted points.
+    fn live_values_range_size(
+        &self,
+        start_bound: Bound<Point<T>>,
+        end_bound: Bound<Point<T>>,
+        hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<usize> {
+        let (start, end) =
+            self.values_range_bounds(start_bound, end_bound, ConditionedCounter::never())?;
+        // Points without values are counted as deleted, but have no pairs in the range
+        if self.deleted_count == self.empty_count {
+            return Ok(end - start);
+        }
+
+        self.make_conditioned_counter(hw_counter)
+            .payload_index_io_read_counter()
+            .incr_delta((end - start) * size_of::<Point<T>>());
+        Ok(self
+            .values_range_iterator(start_bound, end_bound, ConditionedCounter::never())?
+            .count())
+    }
+
     fn get_histogram(&self) -> &Histogram<T> {
         &self.histogram
     }
@@ -143,18 +178,28 @@ impl<T: Encodable + Numericable + Default + StoredValue + 'static, S: UniversalR
 impl<T: Encodable + Numericable + Default + StoredValue + 'static, S: UniversalRead>
     UniversalNumericIndex<T, S>
 {
-    /// Binary search within `[lo, hi)` range of `pairs` storage.
+    /// Binary search within `[lo, hi)` range of `pairs` storage, in the stored key order.
     ///
     /// Returns `Ok(index)` if the element is found, `Err(index)` if not
     /// (where `index` is where the element would be inserted).
+    ///
+    /// If there is a block index, the search is narrowed down to the block of `bound` first.
     fn binary_search_pairs(
         &self,
         bound: &Point<T>,
         lo: usize,
         hi: usize,
+        hw_counter: ConditionedCounter,
     ) -> OperationResult<Result<usize, usize>> {
-        let mut left = lo;
-        let mut right = hi;
+        let (mut left, mut right) = match &self.storage.block_index {
+            Some(block_index) => {
+                let block = block_index.block_of(bound, hi, self.order);
+                let left = lo.max(block.start);
+                (left, left.max(block.end))
+            }
+            None => (lo, hi),
+        };
+        let hw_counter = hw_counter.payload_index_io_read_counter();
         while left < right {
             let mid = left + (right - left) / 2;
             // TODO(luis): use read_one
@@ -162,7 +207,8 @@ impl<T: Encodable + Numericable + Default + StoredValue + 'static, S: UniversalR
                 byte_offset: (mid * size_of::<Point<T>>()) as u64,
                 length: 1,
             })?;
-            match elem[0].cmp(bound) {
+            hw_counter.incr_delta(size_of::<Point<T>>());
+            match self.order.cmp_stored(&elem[0], bound) {
                 std::cmp::Ordering::Less => left = mid + 1,
                 std::cmp::Ordering::Equal => return Ok(Ok(mid)),
                 std::cmp::Ordering::Greater => right = mid,
@@ -171,19 +217,28 @@ impl<T: Encodable + Numericable + Default + StoredValue + 'static, S: UniversalR
         Ok(Err(left))
     }
 
-    /// Find the `[start_index, end_index)` range for the given bounds.
+    /// Find the `[start_index, end_index)` range of `pairs` storage for the given bounds.
+    ///
+    /// Reads of the boundary searches are measured with `hw_counter`.
     fn values_range_bounds(
         &self,
         start_bound: Bound<Point<T>>,
         end_bound: Bound<Point<T>>,
+        hw_counter: ConditionedCounter,
     ) -> OperationResult<(usize, usize)> {
         let len = self.storage.pairs.len()? as usize;
 
+        // The upper bound of the values comes first in descending storage
+        let (start_bound, end_bound) = match self.order {
+            KeyOrder::Ascending => (start_bound, end_bound),
+            KeyOrder::Descending => (end_bound, start_bound),
+        };
+
         let start_index = match start_bound {
             Bound::Included(bound) => self
-                .binary_search_pairs(&bound, 0, len)?
+                .binary_search_pairs(&bound, 0, len, hw_counter)?
                 .unwrap_or_else(|idx| idx),
-            Bound::Excluded(bound) => match self.binary_search_pairs(&bound, 0, len)? {
+            Bound::Excluded(bound) => match self.binary_search_pairs(&bound, 0, len, hw_counter)? {
                 Ok(idx) => idx + 1,
                 Err(idx) => idx,
             },
@@ -195,12 +250,14 @@ impl<T: Encodable + Numericable + Default + StoredValue + 'static, S: UniversalR
         }
 
         let end_index = match end_bound {
-            Bound::Included(bound) => match self.binary_search_pairs(&bound, start_index, len)? {
-                Ok(idx) => idx + 1,
-                Err(idx) => idx,
-            },
+            Bound::Included(bound) => {
+                match self.binary_search_pairs(&bound, start_index, len, hw_counter)? {
+                    Ok(idx) => idx + 1,
+                    Err(idx) => idx,
+                }
+            }
             Bound::Excluded(bound) => self
-                .binary_search_pairs(&bound, start_index, len)?
+                .binary_search_pairs(&bound, start_index, len, hw_counter)?
                 .unwrap_or_else(|idx| idx),
             Bound::Unbounded => len,
         };
@@ -208,15 +265,16 @@ impl<T: Encodable + Numericable + Default + StoredValue + 'static, S: UniversalR
         Ok((start_index, end_index))
     }
 
-    /// Returns an iterator over non-deleted pairs.
+    /// Returns an iterator over non-deleted pairs, in the stored key order.
     ///
     /// This will read the entire range upfront if it was not already cached.
     fn values_range_iterator(
         &self,
         start_bound: Bound<Point<T>>,
         end_bound: Bound<Point<T>>,
+        hw_counter: ConditionedCounter,
     ) -> OperationResult<impl DoubleEndedIterator<Item = Point<T>> + '_> {
-        let (start_pos, end_pos) = self.values_range_bounds(start_bound, end_bound)?;
+        let (start_pos, end_pos) = self.values_range_bounds(start_bound, end_bound, hw_counter)?;
         let count = end_pos - start_pos;
 
         let iter = if count > 0 {
@@ -236,6 +294,68 @@ impl<T: Encodable + Numericable + Default + StoredValue + 'static, S: UniversalR
         Ok(iter.filter(move |point| !deleted.get_bit(point.idx as usize).unwrap_or(true)))
     }
 
+    /// Call `f` with the values of every live point that has any, in ascending point order.
+    ///
+    /// Values are read sequentially into a single buffer that is reused for all points.
+    pub fn for_each_point_values(
+        &self,
+        mut f: impl FnMut(PointOffsetType, &[T]),
+    ) -> OperationResult<()> {
+        let mut buffer = Vec::with_capacity(self.max_values_per_point);
+        for entry in self.storage.point_to_values.iter() {
+            let (idx, values) = entry?;
+            if self.storage.deleted.get_bit(idx as usize).unwrap_or(true) {
+                continue;
+            }
+            let Some(values) = values else {
+                continue;
+            };
+            buffer.clear();
+            buffer.extend(values.map(|v| *v));
+            if !buffer.is_empty() {
+                f(idx, &buffer);
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the invariants of the stored index, see [`NumericIndexInner::verify_integrity`].
+    ///
+    /// Reads all backing files.
+    ///
+    /// [`NumericIndexInner::verify_integrity`]: super::super::NumericIndexInner::verify_integrity
+    pub fn verify_integrity(&self) -> OperationResult<()> {
+        let points_count = self.storage.point_to_values.len();
+        if self.storage.deleted.len() != points_count {
+            return Err(integrity_error(format_args!(
+                "{} deleted flags for {points_count} points",
+                self.storage.deleted.len(),
+            )));
+        }
+        check_deleted_count(&self.storage.deleted, self.deleted_count)?;
+
+        let pairs = self.storage.pairs.read_whole()?;
+        let pairs_count = match self.order {
+            KeyOrder::Ascending => check_sorted_pairs(pairs.iter().copied(), points_count)?,
+            KeyOrder::Descending => check_sorted_pairs(pairs.iter().rev().copied(), points_count)?,
+        };
+        if let Some(block_index) = &self.storage.block_index {
+            block_index.verify(&pairs)?;
+        }
+
+        let mut values_count = 0;
+        for idx in 0..points_count as PointOffsetType {
+            if let Some(values) = self
+                .storage
+                .point_to_values
+                .values_iter(idx, ConditionedCounter::never())?
+            {
+                values_count += distinct_values_count(values.map(|v| *v));
+            }
+        }
+        check_pairs_count(pairs_count, values_count)
+    }
+
     fn make_conditioned_counter<'a>(
         &self,
         hw_counter: &'a HardwareCounterCell,
@@ -246,4 +366,40 @@ impl<T: Encodable + Numericable + Default + StoredValue + 'static, S: UniversalR
     pub fn is_on_disk(&self) -> bool {
         self.is_on_disk
     }
+
+    /// Order the pairs are stored in, chosen at build time.
+    pub fn order(&self) -> KeyOrder {
+        self.order
+    }
+
+    /// Unit of stored datetime timestamps, `None` if the index was never migrated.
+    pub fn datetime_precision(&self) -> Option<DateTimePrecision> {
+        self.datetime_precision
+    }
+
+    /// Options to build an index of the same layout with.
+    pub fn build_options(&self) -> BuildOptions {
+        BuildOptions {
+            order: self.order,
+            datetime_precision: self.datetime_precision,
+        }
+    }
+
+    /// See [`NumericIndexInner::has_value_bitmap`].
+    ///
+    /// [`NumericIndexInner::has_value_bitmap`]: super::super::NumericIndexInner::has_value_bitmap
+    pub fn has_value_bitmap(&self) -> BitVec {
+        // Points without values are marked deleted at build time
+        !self.storage.deleted.clone()
+    }
+
+    /// Share of stored points with values that are deleted, but still take up space until a
+    /// rebuild. Points stored without values are left out, they take up no space.
+    pub fn deleted_ratio(&self) -> f64 {
+        let points_with_values = self.storage.point_to_values.len() - self.empty_count;
+        if points_with_values == 0 {
+            return 0.0;
+        }
+        (self.deleted_count - self.empty_count) as f64 / points_with_values as f64
+    }
 }
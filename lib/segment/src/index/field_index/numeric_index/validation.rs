@@ -0,0 +1,72 @@
+//! [`FilterError`]: why a condition given to a numeric index is malformed, as opposed to one
+//! the index just can't serve.
+
+use std::str::FromStr;
+
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::common::operation_error::OperationError;
+use crate::types::{FieldCondition, Match, MatchValue, Range, RangeInterface, ValueVariants};
+
+/// A malformed `match`/`range` condition, see [`NumericIndex::validate_condition`].
+///
+/// [`NumericIndex::validate_condition`]: super::NumericIndex::validate_condition
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum FilterError {
+    /// The lower bound of the range is above its upper bound. Equal bounds of which one is
+    /// exclusive make an empty range, which is fine.
+    #[error("Range lower bound {lower} is above its upper bound {upper}")]
+    InvertedRange { lower: String, upper: String },
+    /// Numeric indexes only match keywords which are UUIDs
+    #[error("Can't match keyword {keyword:?} against a numeric index, it is not a UUID: {reason}")]
+    NotAUuid { keyword: String, reason: String },
+}
+
+impl From<FilterError> for OperationError {
+    fn from(err: FilterError) -> Self {
+        OperationError::validation_error(err.to_string())
+    }
+}
+
+/// Check that the lower bound of `range` is not above its upper bound.
+///
+/// Like [`Range::as_index_key_bounds`], an exclusive bound wins over an inclusive one.
+pub(super) fn check_range_order(range: &RangeInterface) -> Result<(), FilterError> {
+    match range {
+        RangeInterface::Float(range) => check_bounds_order(range, ToString::to_string),
+        RangeInterface::DateTime(range) => check_bounds_order(range, |dt| dt.0.to_rfc3339()),
+    }
+}
+
+/// Check that the keyword of a keyword `match` condition, if any, is a UUID.
+pub(super) fn check_uuid_keyword(condition: &FieldCondition) -> Result<(), FilterError> {
+    let Some(Match::Value(MatchValue {
+        value: ValueVariants::String(keyword),
+    })) = &condition.r#match
+    else {
+        return Ok(());
+    };
+    Uuid::from_str(keyword)
+        .map(|_| ())
+        .map_err(|err| FilterError::NotAUuid {
+            keyword: keyword.clone(),
+            reason: err.to_string(),
+        })
+}
+
+fn check_bounds_order<V: PartialOrd + Copy>(
+    range: &Range<V>,
+    display: impl Fn(&V) -> String,
+) -> Result<(), FilterError> {
+    let (Some(lower), Some(upper)) = (range.gt.or(range.gte), range.lt.or(range.lte)) else {
+        return Ok(());
+    };
+    if lower > upper {
+        return Err(FilterError::InvertedRange {
+            lower: display(&lower),
+            upper: display(&upper),
+        });
+    }
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use std::str::FromStr;
 
+use chrono::{Datelike, NaiveDate};
 use common::counter::hardware_counter::HardwareCounterCell;
 use common::types::PointOffsetType;
 use serde_json::{Number, Value};
@@ -14,8 +15,54 @@ use crate::index::field_index::utils::value_to_integer;
 use crate::index::query_optimization::rescore_formula::value_retriever::VariableRetrieverFn;
 use crate::types::{
     DateTimePayloadType, FloatPayloadType, IntPayloadType, UuidIntType, UuidPayloadType,
+    ValueVariants,
 };
 
+/// What to do with a payload value that is of the indexed kind, but can't be
+/// represented by the index, e.g. a datetime beyond the supported year range.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutOfRangePolicy {
+    /// Log a warning and index the point without the offending value.
+    #[default]
+    Skip,
+    /// Reject the whole point with a validation error.
+    Error,
+}
+
+/// Year of a datetime-like string, if it starts with a (possibly signed)
+/// `YYYY-` date component.
+fn leading_year(value: &str) -> Option<i64> {
+    let (sign, unsigned) = match value.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let digits = unsigned
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(unsigned.len());
+    if digits < 4 || !unsigned[digits..].starts_with('-') {
+        return None;
+    }
+    unsigned[..digits]
+        .parse::<i64>()
+        .ok()
+        .map(|year| sign * year)
+}
+
+/// Whether `value` is a datetime string which is well-formed, but lies outside
+/// of the range we can index.
+///
+/// Every datetime `chrono` can represent fits into the i64 microseconds used
+/// as the index key, so the only unrepresentable datetimes are the ones
+/// `chrono` refuses to parse because of their year.
+fn is_out_of_range_datetime(value: &str) -> bool {
+    if DateTimePayloadType::from_str(value).is_ok() {
+        return false;
+    }
+    leading_year(value).is_some_and(|year| {
+        year < i64::from(NaiveDate::MIN.year()) || year > i64::from(NaiveDate::MAX.year())
+    })
+}
+
 impl ValueIndexer for NumericIndex<IntPayloadType, IntPayloadType> {
     type ValueType = IntPayloadType;
 
@@ -51,6 +98,10 @@ impl NumericIndexIntoInnerValue<IntPayloadType, IntPayloadType>
     fn into_inner_value(value: IntPayloadType) -> IntPayloadType {
         value
     }
+
+    fn integer_keys() -> bool {
+        true
+    }
 }
 
 impl ValueIndexer for NumericIndex<IntPayloadType, DateTimePayloadType> {
@@ -81,6 +132,21 @@ impl ValueIndexer for NumericIndex<IntPayloadType, DateTimePayloadType> {
         DateTimePayloadType::from_str(value.as_str()?).ok()
     }
 
+    fn add_point(
+        &mut self,
+        id: PointOffsetType,
+        payload: &[&Value],
+        hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<()> {
+        Self::check_representable(id, payload, self.out_of_range_policy)?;
+        self.remove_point(id)?;
+        let values = payload
+            .iter()
+            .flat_map(|value| Self::get_values(value))
+            .collect();
+        self.add_many(id, values, hw_counter)
+    }
+
     fn remove_point(&mut self, id: PointOffsetType) -> OperationResult<()> {
         self.inner.remove_point(id)
     }
@@ -92,6 +158,45 @@ impl NumericIndexIntoInnerValue<IntPayloadType, DateTimePayloadType>
     fn into_inner_value(value: DateTimePayloadType) -> IntPayloadType {
         value.timestamp()
     }
+
+    fn datetime_ranges() -> bool {
+        true
+    }
+
+    fn check_representable(
+        id: PointOffsetType,
+        payload: &[&Value],
+        policy: OutOfRangePolicy,
+    ) -> OperationResult<()> {
+        let out_of_range = payload
+            .iter()
+            .flat_map(|value| match value {
+                Value::Array(values) => values.iter().collect(),
+                Value::Null
+                | Value::Bool(_)
+                | Value::Number(_)
+                | Value::String(_)
+                | Value::Object(_) => vec![*value],
+            })
+            .filter_map(Value::as_str)
+            .find(|value| is_out_of_range_datetime(value));
+
+        let Some(out_of_range) = out_of_range else {
+            return Ok(());
+        };
+
+        match policy {
+            OutOfRangePolicy::Skip => {
+                log::warn!(
+                    "Skipping datetime {out_of_range:?} of point {id}: outside of the supported range",
+                );
+                Ok(())
+            }
+            OutOfRangePolicy::Error => Err(OperationError::validation_error(format!(
+                "Datetime {out_of_range:?} of point {id} is outside of the supported range",
+            ))),
+        }
+    }
 }
 
 impl ValueIndexer for NumericIndex<FloatPayloadType, FloatPayloadType> {
@@ -155,7 +260,41 @@ impl ValueIndexer for NumericIndex<UuidIntType, UuidPayloadType> {
     }
 
     fn get_value(value: &Value) -> Option<Self::ValueType> {
-        Uuid::parse_str(value.as_str()?).ok()
+        match value {
+            Value::String(value) => Uuid::parse_str(value).ok(),
+            Value::Array(bytes) => uuid_from_bytes(bytes),
+            Value::Null | Value::Bool(_) | Value::Number(_) | Value::Object(_) => None,
+        }
+    }
+
+    /// Same as the default implementation, except that an array of 16 bytes is a single UUID
+    /// in binary form rather than a list of values.
+    fn get_values(value: &Value) -> Vec<Self::ValueType> {
+        match value {
+            Value::Array(values) => match uuid_from_bytes(values) {
+                Some(uuid) => vec![uuid],
+                None => values.iter().filter_map(Self::get_value).collect(),
+            },
+            Value::Null
+            | Value::Bool(_)
+            | Value::Number(_)
+            | Value::String(_)
+            | Value::Object(_) => Self::get_value(value).into_iter().collect(),
+        }
+    }
+
+    fn add_point(
+        &mut self,
+        id: PointOffsetType,
+        payload: &[&Value],
+        hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<()> {
+        self.remove_point(id)?;
+        let values = payload
+            .iter()
+            .flat_map(|value| Self::get_values(value))
+            .collect();
+        self.add_many(id, values, hw_counter)
     }
 
     fn remove_point(&mut self, id: PointOffsetType) -> OperationResult<()> {
@@ -163,12 +302,76 @@ impl ValueIndexer for NumericIndex<UuidIntType, UuidPayloadType> {
     }
 }
 
+/// UUID in binary form: a JSON array of exactly 16 integers in `0..=255`, in big-endian order
+/// like [`Uuid::from_bytes`] expects.
+fn uuid_from_bytes(values: &[Value]) -> Option<Uuid> {
+    let mut bytes = [0u8; 16];
+    if values.len() != bytes.len() {
+        return None;
+    }
+    for (byte, value) in bytes.iter_mut().zip(values) {
+        *byte = u8::try_from(value.as_u64()?).ok()?;
+    }
+    Some(Uuid::from_bytes(bytes))
+}
+
 impl NumericIndexIntoInnerValue<UuidIntType, UuidPayloadType>
     for NumericIndex<UuidIntType, UuidPayloadType>
 {
     fn into_inner_value(value: UuidPayloadType) -> UuidIntType {
         value.as_u128()
     }
+
+    fn uuid_keywords() -> bool {
+        true
+    }
+}
+
+impl ValueIndexer for NumericIndex<IntPayloadType, bool> {
+    type ValueType = bool;
+
+    fn add_many(
+        &mut self,
+        id: PointOffsetType,
+        values: Vec<bool>,
+        hw_counter: &HardwareCounterCell,
+    ) -> OperationResult<()> {
+        match &mut self.inner {
+            NumericIndexInner::Mutable(index) => index.add_many_to_list(
+                id,
+                values.into_iter().map(Self::into_inner_value).collect(),
+                hw_counter,
+            ),
+            NumericIndexInner::Immutable(_) => Err(OperationError::service_error(
+                "Can't add values to immutable numeric index",
+            )),
+            NumericIndexInner::Mmap(_) => Err(OperationError::service_error(
+                "Can't add values to mmap numeric index",
+            )),
+        }
+    }
+
+    fn get_value(value: &Value) -> Option<bool> {
+        value.as_bool()
+    }
+
+    fn remove_point(&mut self, id: PointOffsetType) -> OperationResult<()> {
+        self.inner.remove_point(id)
+    }
+}
+
+/// Booleans are stored as `0` and `1`.
+impl NumericIndexIntoInnerValue<IntPayloadType, bool> for NumericIndex<IntPayloadType, bool> {
+    fn into_inner_value(value: bool) -> IntPayloadType {
+        IntPayloadType::from(value)
+    }
+
+    fn match_value_key(value: &ValueVariants) -> Option<IntPayloadType> {
+        match value {
+            ValueVariants::Bool(value) => Some(Self::into_inner_value(*value)),
+            ValueVariants::String(_) | ValueVariants::Integer(_) => None,
+        }
+    }
 }
 
 // Per-(T, U) value retrievers — produce a closure that maps a point id
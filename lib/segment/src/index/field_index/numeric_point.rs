@@ -6,7 +6,7 @@ use ordered_float::OrderedFloat;
 use serde::Serialize;
 
 pub use self::point::Point;
-use crate::types::{FloatPayloadType, Range};
+use crate::types::{FloatPayloadType, IntPayloadType, Range};
 
 // bytemuck macros expand to code that triggers this clippy lint
 // The only reason this is its own module is so that we scope the lint suppression
@@ -94,6 +94,12 @@ pub trait Numericable: Num + PartialEq + PartialOrd + Copy + bytemuck::Pod + Sen
     fn to_f64(self) -> f64;
     fn from_f64(x: f64) -> Self;
     fn from_u128(x: u128) -> Self;
+    /// Key of an integer payload value, converted exactly.
+    ///
+    /// `None` for key types which don't store integers as they are, like floats and UUIDs.
+    fn from_integer(_x: IntPayloadType) -> Option<Self> {
+        None
+    }
     fn min(self, b: Self) -> Self {
         if self < b { self } else { b }
     }
@@ -135,6 +141,9 @@ impl Numericable for i64 {
     fn from_u128(x: u128) -> Self {
         x as i64
     }
+    fn from_integer(x: IntPayloadType) -> Option<Self> {
+        Some(x)
+    }
     fn abs_diff(self, b: Self) -> Self {
         i64::abs_diff(self, b) as i64
     }
@@ -30,6 +30,12 @@ pub struct PayloadIndexTelemetry {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[anonymize(false)]
     pub histogram_bucket_size: Option<usize>,
+
+    /// Mean absolute `log2` ratio between realized and estimated cardinalities
+    /// reported back to the index. High values suggest a stale histogram.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[anonymize(false)]
+    pub cardinality_estimation_error: Option<f64>,
 }
 
 impl PayloadIndexTelemetry {